@@ -0,0 +1,272 @@
+//! Derive macro for `adbf_rs::RecordOps`.
+//!
+//! Each field of the struct is annotated with `#[dbf(...)]` describing the DBF column
+//! it is stored in. The byte conversion is done by `Raw*Field` of `adbf_rs::foxpro`
+//! through their `ConversionField` implementation so the field type shall be the one
+//! that the raw field converts into.
+//!
+//! | `datatype` | Raw field | Rust type | Default size |
+//! | --- | --- | --- | --- |
+//! | `'C'` | `RawCharField` | `String` or `Vec<u8>` | required |
+//! | `'V'` | `RawVarCharField` | `String` | required |
+//! | `'Y'` | `RawCurrencyField` | `Currency` or `f64` | 8 |
+//! | `'B'` | `RawDoubleField` | `f64` | 8 |
+//! | `'N'`, `'F'` | `RawFloatField` | `Decimal`, `Option<Decimal>` or `f32` | required |
+//! | `'I'` | `RawIntegerField` | `i32` | 4 |
+//! | `'L'` | `RawBoolField` | `bool` or `Option<bool>` | 1 |
+//! | `'D'` | `RawDateField` | `NaiveDate` or `Option<NaiveDate>` | 8 |
+//! | `'T'` | `RawDateTimeField` | `NaiveDateTime` or `Option<NaiveDateTime>` | 8 |
+//! | `'M'` | `RawMemoField` | `u32` block number | 4 |
+//! | `'G'`, `'P'` | `RawGeneralField` | `u32` block number | 4 |
+//!
+//! `C` column is read without trailing spaces. `V` column is padded with spaces like `C`
+//! as the record has no `_NullFlags` to keep its length, so it is also read without
+//! trailing spaces. Writing a value longer than the column is `Error::Overflow`.
+//!
+//! Field attributes:
+//! - `datatype = 'C'` - column type, see above.
+//! - `name = "CUSTNO"` - column name. Default is the field name in upper case.
+//! - `size = 10` - column size in bytes.
+//! - `precision = 2` - number of decimal places of `N`, `F` and `B` column.
+//! - `offset = 1` - column offset in a record. Default is right after previous column,
+//!   starting at 1 as byte 0 is the deletion flag.
+//! - `skip` - the field isn't stored. It is set to `Default::default()` when read.
+//!
+//! Struct attribute `#[dbf(codepage = "cp874")]` set codepage of character column.
+//! Default is `cp1252`.
+//!
+//! Beside `RecordOps`, the derive also generates `dbf_fields()` which return
+//! `Vec<adbf_rs::foxpro::Field>` describing the layout so it can be given to
+//! `TableWriter::create` or compared with fields read from a table.
+//!
+//! ```ignore
+//! #[derive(RecordOps)]
+//! #[dbf(codepage = "cp874")]
+//! struct Order {
+//!     #[dbf(datatype = 'C', size = 20)]
+//!     name: String,
+//!     #[dbf(datatype = 'Y')]
+//!     cost: f64,
+//!     #[dbf(name = "QTY", datatype = 'I')]
+//!     quantity: i32
+//! }
+//! ```
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, spanned::Spanned, Data, DeriveInput, Fields, LitChar, LitInt, LitStr};
+
+#[proc_macro_derive(RecordOps, attributes(dbf))]
+pub fn derive_record_ops(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match expand(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into()
+    }
+}
+
+/// Column layout of a struct field
+struct Column {
+    ident: syn::Ident,
+    name: String,
+    datatype: char,
+    offset: usize,
+    size: usize,
+    precision: usize
+}
+
+/// `#[dbf(...)]` of a struct field as written
+#[derive(Default)]
+struct ColumnAttr {
+    name: Option<String>,
+    datatype: Option<char>,
+    size: Option<usize>,
+    precision: Option<usize>,
+    offset: Option<usize>,
+    skip: bool
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let fields = match input.data {
+        Data::Struct(ref data) => match data.fields {
+            Fields::Named(ref fields) => &fields.named,
+            _ => return Err(syn::Error::new(input.span(), "RecordOps can only be derived for struct with named fields"))
+        },
+        _ => return Err(syn::Error::new(input.span(), "RecordOps can only be derived for struct"))
+    };
+
+    let codepage = struct_codepage(&input.attrs)?;
+    let mut columns = Vec::new();
+    let mut skipped = Vec::new();
+    let mut next_offset = 1;
+    for field in fields.iter() {
+        let ident = field.ident.clone().expect("Named field has an ident");
+        let attr = column_attr(field)?;
+        if attr.skip {
+            skipped.push(ident);
+            continue;
+        }
+        let datatype = attr.datatype.ok_or_else(|| syn::Error::new(field.span(), "missing `datatype` in #[dbf(...)]"))?;
+        let size = match attr.size.or_else(|| default_size(datatype)) {
+            Some(size) => size,
+            None => return Err(syn::Error::new(field.span(), format!("`size` is required for '{}' column", datatype)))
+        };
+        let offset = attr.offset.unwrap_or(next_offset);
+        next_offset = offset + size;
+        columns.push(Column {
+            name: attr.name.unwrap_or_else(|| ident.to_string().to_uppercase()),
+            ident,
+            datatype,
+            offset,
+            size,
+            precision: attr.precision.unwrap_or(0)
+        });
+    }
+
+    let record_len = columns.iter().map(|c| c.offset + c.size).max().unwrap_or(1);
+    let mut reads = Vec::new();
+    let mut writes = Vec::new();
+    let mut metas = Vec::new();
+    for column in columns.iter() {
+        let Column { ident, name, datatype, offset, size, precision } = column;
+        let raw = raw_field(*datatype, &codepage, *size, *precision).ok_or_else(|| syn::Error::new(ident.span(), format!("'{}' column is not supported", datatype)))?;
+        let pad = if matches!(datatype, 'C' | 'V' | 'N' | 'F') { b' ' } else { 0u8 };
+        let flag = *datatype as u8;
+        // without `_NullFlags` there's nowhere to tell the length so `V` is padded like `C`
+        // and the padding is trimmed when it is read back
+        reads.push(if *datatype == 'V' {
+            quote! {
+                #ident: {
+                    let bytes = ::adbf_rs::record_slice(record, #offset, #size)?;
+                    let value: ::std::string::String = ::adbf_rs::ConversionField::get(&#raw)?;
+                    value.trim_end_matches(' ').to_owned()
+                }
+            }
+        } else {
+            quote! {
+                #ident: {
+                    let bytes = ::adbf_rs::record_slice(record, #offset, #size)?;
+                    ::adbf_rs::ConversionField::get(&#raw)?
+                }
+            }
+        });
+        writes.push(if *datatype == 'V' {
+            quote! {
+                ::adbf_rs::put_field(&mut record, #offset, #size, &::adbf_rs::encode(#codepage, &self.#ident)?, #pad)?;
+            }
+        } else {
+            quote! {
+                {
+                    let bytes = ::adbf_rs::record_slice(&record, #offset, #size)?;
+                    let mut raw = #raw;
+                    ::adbf_rs::ConversionField::set(&mut raw, &self.#ident)?;
+                    ::adbf_rs::put_field(&mut record, #offset, #size, raw.as_bytes(), #pad)?;
+                }
+            }
+        });
+        metas.push(quote! {
+            {
+                let mut field = ::adbf_rs::foxpro::Field::new(#name, #flag, #size, #precision);
+                field.offset = #offset;
+                field
+            }
+        });
+    }
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::adbf_rs::RecordOps for #ident #ty_generics #where_clause {
+            fn from_bytes(record: &[u8]) -> ::adbf_rs::Result<Self> {
+                Ok(#ident {
+                    #(#reads,)*
+                    #(#skipped: ::core::default::Default::default(),)*
+                })
+            }
+
+            fn to_bytes(&self) -> ::adbf_rs::Result<::std::vec::Vec<u8>> {
+                let mut record = ::std::vec![b' '; #record_len];
+                #(#writes)*
+                Ok(record)
+            }
+        }
+
+        impl #impl_generics #ident #ty_generics #where_clause {
+            /// Layout of every column of this record
+            pub fn dbf_fields() -> ::std::vec::Vec<::adbf_rs::foxpro::Field> {
+                ::std::vec![#(#metas),*]
+            }
+        }
+    })
+}
+
+fn struct_codepage(attrs: &[syn::Attribute]) -> syn::Result<String> {
+    let mut codepage = "cp1252".to_owned();
+    for attr in attrs.iter().filter(|a| a.path().is_ident("dbf")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("codepage") {
+                codepage = meta.value()?.parse::<LitStr>()?.value();
+                Ok(())
+            } else {
+                Err(meta.error("unknown #[dbf(...)] attribute of struct"))
+            }
+        })?;
+    }
+    Ok(codepage)
+}
+
+fn column_attr(field: &syn::Field) -> syn::Result<ColumnAttr> {
+    let mut column = ColumnAttr::default();
+    for attr in field.attrs.iter().filter(|a| a.path().is_ident("dbf")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("name") {
+                column.name = Some(meta.value()?.parse::<LitStr>()?.value());
+            } else if meta.path.is_ident("datatype") {
+                column.datatype = Some(meta.value()?.parse::<LitChar>()?.value());
+            } else if meta.path.is_ident("size") {
+                column.size = Some(meta.value()?.parse::<LitInt>()?.base10_parse()?);
+            } else if meta.path.is_ident("precision") {
+                column.precision = Some(meta.value()?.parse::<LitInt>()?.base10_parse()?);
+            } else if meta.path.is_ident("offset") {
+                column.offset = Some(meta.value()?.parse::<LitInt>()?.base10_parse()?);
+            } else if meta.path.is_ident("skip") {
+                column.skip = true;
+            } else {
+                return Err(meta.error("unknown #[dbf(...)] attribute of field"));
+            }
+            Ok(())
+        })?;
+    }
+    Ok(column)
+}
+
+/// Size of column which has fixed size
+fn default_size(datatype: char) -> Option<usize> {
+    match datatype {
+        'Y' | 'B' | 'D' | 'T' => Some(8),
+        'I' | 'M' | 'G' | 'P' => Some(4),
+        'L' => Some(1),
+        _ => None
+    }
+}
+
+/// Expression that construct raw field out of `bytes`
+fn raw_field(datatype: char, codepage: &str, size: usize, precision: usize) -> Option<TokenStream2> {
+    let raw = match datatype {
+        'C' => quote!(::adbf_rs::foxpro::RawCharField::new(bytes, #codepage).with_strict(true)),
+        'V' => quote!(::adbf_rs::foxpro::RawVarCharField::new(bytes, #codepage, #size)),
+        'Y' => quote!(::adbf_rs::foxpro::RawCurrencyField::new(bytes)),
+        'B' => quote!(::adbf_rs::foxpro::RawDoubleField::new(bytes)),
+        'N' | 'F' => quote!(::adbf_rs::foxpro::RawFloatField::new(bytes, #precision)),
+        'I' => quote!(::adbf_rs::foxpro::RawIntegerField::new(bytes)),
+        'L' => quote!(::adbf_rs::foxpro::RawBoolField::new(bytes)),
+        'D' => quote!(::adbf_rs::foxpro::RawDateField::new(bytes)),
+        'T' => quote!(::adbf_rs::foxpro::RawDateTimeField::new(bytes)),
+        'M' => quote!(::adbf_rs::foxpro::RawMemoField::new(bytes)),
+        'G' | 'P' => quote!(::adbf_rs::foxpro::RawGeneralField::new(bytes)),
+        _ => return None
+    };
+    Some(raw)
+}
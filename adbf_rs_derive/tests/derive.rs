@@ -0,0 +1,115 @@
+use adbf_rs::{foxpro::{self, Field, TableWriter}, Error, Header, DBFType, RecordOps, TableOps};
+use futures::executor::block_on;
+
+#[derive(Debug, PartialEq, RecordOps)]
+#[dbf(codepage = "tis620")]
+struct Order {
+    #[dbf(datatype = 'C', size = 6)]
+    name: String,
+    #[dbf(datatype = 'Y')]
+    cost: f64,
+    #[dbf(name = "QTY", datatype = 'I')]
+    quantity: i32,
+    #[dbf(datatype = 'L')]
+    paid: bool,
+    #[dbf(skip)]
+    note: Option<String>
+}
+
+#[derive(Debug, PartialEq, RecordOps)]
+struct Sparse {
+    #[dbf(datatype = 'N', size = 6, precision = 2, offset = 5)]
+    total: f32,
+    #[dbf(datatype = 'M')]
+    remark: u32
+}
+
+#[derive(Debug, PartialEq, RecordOps)]
+struct Titled {
+    #[dbf(datatype = 'V', size = 10)]
+    title: String
+}
+
+#[test]
+fn test_derive_layout() {
+    let fields = Order::dbf_fields();
+    assert_eq!(fields.iter().map(|f| f.name.as_str()).collect::<Vec<_>>(), vec!["NAME", "COST", "QTY", "PAID"]);
+    assert_eq!(fields.iter().map(|f| f.offset).collect::<Vec<_>>(), vec![1, 7, 15, 19]);
+    assert_eq!(fields.iter().map(|f| f.size).collect::<Vec<_>>(), vec![6, 8, 4, 1]);
+    assert_eq!(fields[0].datatype, b'C');
+
+    let fields = Sparse::dbf_fields();
+    assert_eq!(fields.iter().map(|f| f.offset).collect::<Vec<_>>(), vec![5, 11]);
+    assert_eq!(fields[0].precision, 2);
+}
+
+#[test]
+fn test_derive_round_trip() {
+    let order = Order {
+        name: "กขค".to_owned(),
+        cost: 1.5,
+        quantity: 3,
+        paid: true,
+        note: Some("not stored".to_owned())
+    };
+    let bytes = order.to_bytes().unwrap();
+    assert_eq!(bytes.len(), 20);
+    assert_eq!(bytes[0], b' ');
+    assert_eq!(&bytes[1..7], &[0xA1, 0xA2, 0xA4, b' ', b' ', b' ']);
+    assert_eq!(&bytes[15..19], &3i32.to_le_bytes());
+
+    let read = Order::from_bytes(&bytes).unwrap();
+    assert_eq!(read.name.trim_end(), "กขค");
+    assert_eq!(read.quantity, 3);
+    assert!(read.paid);
+    assert_eq!(read.note, None);
+
+    match Order::from_bytes(&bytes[..10]) {
+        Err(Error::Truncated { expected: 15, found: 10 }) => (),
+        other => panic!("Expect truncated but found {:?}", other)
+    }
+
+    let too_long = Order { name: "abcdefg".to_owned(), cost: 0.0, quantity: 0, paid: false, note: None };
+    match too_long.to_bytes() {
+        Err(Error::Overflow { size: 7, max: 6 }) => (),
+        other => panic!("Expect overflow but found {:?}", other)
+    }
+}
+
+#[test]
+fn test_derive_varchar_round_trip() {
+    let titled = Titled { title: "ab".to_owned() };
+    let bytes = titled.to_bytes().unwrap();
+    assert_eq!(&bytes[1..], b"ab        ");
+    assert_eq!(Titled::from_bytes(&bytes).unwrap(), titled);
+}
+
+#[test]
+fn test_derive_with_table_writer() {
+    let path = std::env::temp_dir().join("adbf_rs_derive_orders.dbf");
+    let path = path.to_str().unwrap();
+    let header = Header {
+        db_type: DBFType::VisualFoxPro,
+        codepage: "tis620",
+        ..Header::default()
+    };
+    let rows = [
+        Order { name: "a".to_owned(), cost: 2.0, quantity: 1, paid: false, note: None },
+        Order { name: "b".to_owned(), cost: 4.0, quantity: 2, paid: true, note: None }
+    ];
+
+    block_on(async {
+        let mut writer = TableWriter::create(path, &header, &Order::dbf_fields()).await.unwrap();
+        for row in rows.iter() {
+            writer.append(row).await.unwrap();
+        }
+        writer.finish().await.unwrap();
+    });
+
+    let table = block_on(foxpro::Table::<Order>::open(path)).unwrap();
+    let fields: Vec<Field> = Order::dbf_fields();
+    assert_eq!(table.fields.iter().map(|f| f.offset).collect::<Vec<_>>(), fields.iter().map(|f| f.offset).collect::<Vec<_>>());
+    assert_eq!(table.len(), 2);
+    assert_eq!(table[1].quantity, 2);
+    assert!(table[1].paid);
+}
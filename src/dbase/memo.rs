@@ -0,0 +1,147 @@
+use std::{
+    fs::File,
+    io::{
+        Read, Seek, SeekFrom
+    }
+};
+
+use super::*;
+
+/// Block size of dBase III+ memo file. dBase IV may store other size in the header.
+const DEFAULT_BLOCK_SIZE: u32 = 512;
+/// Header of a dBase IV memo block
+const DBASE_IV_BLOCK_SIGNATURE: [u8; 4] = [0xFF, 0xFF, 0x08, 0x00];
+
+/// dBase memo file (.dbt) which store content of `M` field.
+///
+/// ## Header
+/// ---
+/// | Byte offset | Description |
+/// | --- | --- |
+/// | 0 - 3 | Location of next free block (little endian) |
+/// | 4 - 19 | Unused |
+/// | 20 - 21 | Block size in bytes (little endian, dBase IV only) |
+/// | 22 - 511 | Unused |
+///
+/// ## Memo block
+/// ---
+/// dBase III+ memo is stored as is starting at the block and terminated by `0x1A 0x1A`.
+///
+/// dBase IV memo block starts with 8 bytes header:
+///
+/// | Byte offset | Description |
+/// | --- | --- |
+/// | 0 - 3 | `0xFF 0xFF 0x08 0x00` |
+/// | 4 - 7 | Length of memo including this header (little endian) |
+/// | 8 - n | Memo content |
+/// ---
+///
+/// A block number stored in a record is counted from the beginning of file
+/// so the byte offset of a memo is `block * block_size`.
+pub struct MemoFile<R = File> {
+    source: R,
+    next_free_block: u32,
+    block_size: u32,
+    codepage: &'static str
+}
+
+impl MemoFile<File> {
+    /// Open memo file at given path.
+    /// Memo is decoded using given codepage which shall be the codepage of the table.
+    pub fn open<P: AsRef<std::path::Path>>(path: P, codepage: &'static str) -> Result<MemoFile<File>> {
+        MemoFile::new(File::open(path)?, codepage)
+    }
+}
+
+impl<R> MemoFile<R> where R: Read + Seek {
+    /// Read memo header from given source.
+    pub fn new(mut source: R, codepage: &'static str) -> Result<MemoFile<R>> {
+        let mut header = [0u8; 22];
+        source.seek(SeekFrom::Start(0))?;
+        read_full(&mut source, &mut header)?;
+        let next_free_block = u32::from_le_bytes(take_bytes(&header[0..4])?);
+        let block_size = match u16::from_le_bytes(take_bytes(&header[20..22])?) {
+            0 => DEFAULT_BLOCK_SIZE,
+            size => size as u32
+        };
+
+        Ok(MemoFile {
+            source,
+            next_free_block,
+            block_size,
+            codepage
+        })
+    }
+
+    /// Size in bytes of each block
+    pub fn block_size(&self) -> u32 {
+        self.block_size
+    }
+
+    /// Block number where next memo will be written
+    pub fn next_free_block(&self) -> u32 {
+        self.next_free_block
+    }
+
+    /// Codepage used to decode memo
+    pub fn codepage(&self) -> &'static str {
+        self.codepage
+    }
+
+    /// Read memo at given block number as is.
+    /// Block number 0 means there is no memo so it return `None`.
+    ///
+    /// Memo without dBase IV block header is read until `0x1A 0x1A` or the end of file.
+    pub fn read_binary(&mut self, block: u32) -> Result<Option<Vec<u8>>> {
+        if block == 0 {
+            return Ok(None);
+        }
+        if block >= self.next_free_block {
+            return Err(Error::Decode(format!("Memo block {} is beyond next free block {}", block, self.next_free_block)));
+        }
+
+        // dBase III+ memo may be shorter than dBase IV block header at the end of file
+        let start = block as u64 * self.block_size as u64;
+        let end = self.source.seek(SeekFrom::End(0))?;
+        let mut content = Vec::with_capacity(8);
+        self.source.seek(SeekFrom::Start(start))?;
+        (&mut self.source).take(8).read_to_end(&mut content)?;
+        if content.len() == 8 && content[0..4] == DBASE_IV_BLOCK_SIGNATURE {
+            // length includes the block header and is checked against the file before it is allocated
+            let len = u32::from_le_bytes(take_bytes(&content[4..8])?) as usize;
+            let available = end.saturating_sub(start);
+            if len as u64 > available {
+                return Err(Error::Truncated {
+                    expected: len,
+                    found: available as usize
+                });
+            }
+            let mut content = vec![0u8; len.saturating_sub(8)];
+            read_full(&mut self.source, &mut content)?;
+            return Ok(Some(content));
+        }
+
+        let mut chunk = vec![0u8; self.block_size as usize];
+        let mut searched = 0;
+        loop {
+            if let Some(end) = content[searched..].windows(2).position(|w| w == [0x1A, 0x1A]) {
+                content.truncate(searched + end);
+                return Ok(Some(content));
+            }
+            searched = content.len().saturating_sub(1);
+            let read = self.source.read(&mut chunk)?;
+            if read == 0 {
+                return Ok(Some(content));
+            }
+            content.extend(&chunk[..read]);
+        }
+    }
+
+    /// Read memo at given block number and decode it as text using table codepage.
+    pub fn read_text(&mut self, block: u32) -> Result<Option<String>> {
+        match self.read_binary(block)? {
+            Some(content) => Ok(Some(decode(self.codepage, &content)?)),
+            None => Ok(None)
+        }
+    }
+}
@@ -0,0 +1,282 @@
+use chrono::Datelike;
+use core::fmt::Display;
+use std::fmt;
+use std::{
+    fs::File,
+    io::{
+        Read, Seek, SeekFrom
+    }
+};
+
+use super::*;
+use crate::foxpro::{self, Field};
+
+#[cfg(test)]
+mod tests;
+
+mod memo;
+
+pub use memo::MemoFile;
+
+/// Read field descriptors from dBase III+/IV file.
+///
+/// Unlike FoxPro, dBase doesn't store field offset in the descriptor so
+/// it is computed from the size of preceding fields, starting after the deletion flag.
+/// Only name, type, size and precision are filled in returned [Field](../foxpro/struct.Field.html).
+///
+/// ## Field Descriptor Structure
+/// ---
+/// | Byte offset | Description |
+/// ---
+/// | 0 - 10 | Field name with a maximum of 10 chars, terminated by 0 |
+/// | 11 | Field type:<br/>C - Character<br/>N - Numeric<br/>F - Float (dBase IV)<br/>D - Date<br/>L - Logical<br/>M - Memo |
+/// | 12 - 15 | Field data address in memory (unused) |
+/// | 16 | Length of field (bytes) |
+/// | 17 | Number of decimal places |
+/// | 18 - 19 | Reserved |
+/// | 20 | Work area ID |
+/// | 21 - 30 | Reserved |
+/// | 31 | Production MDX field flag (dBase IV) |
+pub async fn read_fields(f: &mut File, h: &Header) -> Result<Vec<Field>> {
+    f.seek(SeekFrom::Start(32))?;
+    let mut buffer = [0u8; 32];
+    let mut fields = vec![];
+    let mut offset = 1;
+    read_full(f, &mut buffer[..1])?;
+
+    while buffer[0] != 0x0D {
+        read_full(f, &mut buffer[1..])?;
+        let field = read_field_meta(buffer, offset, h.codepage)?;
+        offset += field.size;
+        fields.push(field);
+        read_full(f, &mut buffer[..1])?;
+    }
+
+    Ok(fields)
+}
+
+/// Parse field descriptors out of header area that starts at byte 32, up to the 0x0D terminator.
+fn parse_fields(area: &[u8], codepage: &str) -> Result<Vec<Field>> {
+    let mut fields = vec![];
+    let mut offset = 1;
+    for bytes in area.chunks(32) {
+        if bytes[0] == 0x0D {
+            return Ok(fields);
+        }
+        let field = read_field_meta(take_bytes(bytes)?, offset, codepage)?;
+        offset += field.size;
+        fields.push(field);
+    }
+    Err(Error::Truncated {
+        expected: area.len() + 1,
+        found: area.len()
+    })
+}
+
+fn read_field_meta(bytes: [u8; 32], offset: usize, codepage: &str) -> Result<Field> {
+    let name_len = bytes[0..11].iter().position(|b| *b == 0).unwrap_or(11);
+    let mut field = Field::new(&decode(codepage, &bytes[..name_len])?, bytes[11], bytes[16] as usize, bytes[17] as usize);
+    field.offset = offset;
+    Ok(field)
+}
+
+/// Map dBase language driver ID into codepage.
+///
+/// dBase III+ files usually don't have language driver ID so 0 is treated as `cp1252`.
+/// Language driver ID that dBase share with FoxPro is mapped by [foxpro::cp_mapper](../foxpro/fn.cp_mapper.html).
+pub fn cp_mapper(codepage: u8) -> Result<&'static str> {
+    match codepage {
+        0 | 0x57 | 0x58 | 0x59 => Ok("cp1252"),
+        0x26 => Ok("cp866"),
+        _ => foxpro::cp_mapper(codepage)
+    }
+}
+
+/// Read header and fields of a dBase table from given async source then return
+/// a [RecordStream](../struct.RecordStream.html) over its records.
+///
+/// Records are read one by one as the stream is polled.
+/// Like [foxpro::stream_table](../foxpro/fn.stream_table.html), the source is any
+/// `AsyncRead + AsyncSeek` such as an async file of your runtime.
+pub async fn stream_table<T, R>(mut source: R) -> Result<RecordStream<R, T>>
+    where T: RecordOps, R: AsyncRead + AsyncSeek + Unpin
+{
+    let (header, area) = read_header_area(&mut source, cp_mapper).await?;
+    if !header.db_type.is_dbase() {
+        return Err(Error::BadHeader(format!("{:?} is not a dBase table", header.db_type)));
+    }
+    let fields = parse_fields(&area, header.codepage)?;
+    Ok(RecordStream::new(source, &header).with_schema(foxpro::Schema::new(fields, header.codepage)))
+}
+
+/// A `D` field which dBase store as 8 ASCII digits `YYYYMMDD`.
+///
+/// Field that is all spaces, or all zero, is empty. It is read as `None` by `Option<NaiveDate>`
+/// and is `Error::BadDate` when it is read as `NaiveDate`. Anything else that isn't
+/// `YYYYMMDD`, such as the binary layout FoxPro may use, is `Error::BadDate`.
+#[derive(Clone)]
+pub struct RawDateField {
+    bytes: MemReferer<[u8]>
+}
+
+impl RawDateField {
+    /// Take the field bytes out of a record
+    pub fn new(bytes: &[u8]) -> RawDateField {
+        RawDateField {
+            bytes: MemReferer::from(bytes)
+        }
+    }
+
+    /// Field bytes to be put back into a record
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+impl ConversionField<Option<NaiveDate>> for RawDateField {
+    fn get(&self) -> Result<Option<NaiveDate>> {
+        foxpro::parse_ascii_date(&self.bytes)
+    }
+
+    fn set(&mut self, value: &Option<NaiveDate>) -> Result<()> {
+        self.bytes = match value {
+            Some(value) if (0..=9999).contains(&value.year()) => value.format("%Y%m%d").to_string().into_bytes().into(),
+            Some(value) => return Err(Error::Encode(format!("{} cannot be stored as YYYYMMDD", value))),
+            None => vec![b' '; 8].into()
+        };
+        Ok(())
+    }
+}
+
+impl ConversionField<NaiveDate> for RawDateField {
+    fn get(&self) -> Result<NaiveDate> {
+        foxpro::parse_ascii_date(&self.bytes)?.ok_or_else(|| Error::BadDate(self.bytes.to_vec()))
+    }
+
+    fn set(&mut self, value: &NaiveDate) -> Result<()> {
+        self.set(&Some(*value))
+    }
+}
+
+#[derive(Clone)]
+pub struct DateField<'a> {
+    pub meta: Field,
+    content: Option<NaiveDate>,
+    ready: Option<()>,
+    record: &'a [u8]
+}
+
+impl<'a> DateField<'a> {
+    /// Create a date field that will read its content from given record
+    /// once [from_record_bytes](../trait.FieldOps.html#tymethod.from_record_bytes) is called.
+    pub fn new(meta: Field, record: &'a [u8]) -> DateField<'a> {
+        DateField {
+            meta,
+            content: None,
+            ready: None,
+            record
+        }
+    }
+}
+
+impl<'a> FieldMeta for DateField<'a> {
+    fn nullable(&self) -> bool {
+        self.meta.nullable()
+    }
+    fn datatype_flag(&self) -> u8 {
+        b'D'
+    }
+    fn autoincrement(&self) -> bool {
+        self.meta.autoincrement()
+    }
+    fn name(&self) -> &str {
+        self.meta.name()
+    }
+    fn rec_offset(&self) -> usize {
+        self.meta.rec_offset()
+    }
+    fn size(&self) -> usize {
+        self.meta.size()
+    }
+    fn precision(&self) -> usize {
+        self.meta.precision()
+    }
+    fn next_id(&mut self) -> u32 {
+        self.meta.next_id()
+    }
+}
+
+/// Empty field is displayed as empty string
+impl<'a> Display for DateField<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.content {
+            Some(ref content) => write!(f, "{}", content),
+            None => Ok(())
+        }
+    }
+}
+
+impl<'a> FieldOps for DateField<'a> {
+
+    fn from_record_bytes(&mut self) -> BoxFuture<'_, Result<()>> {
+        Box::pin(
+            async move {
+                let field = record_slice(self.record, self.meta.rec_offset(), self.meta.size())?;
+                self.content = foxpro::parse_ascii_date(field)?;
+                self.ready = Some(());
+                Ok(())
+            }
+        )
+    }
+
+    fn to_bytes(&self) -> BoxFuture<'_, Result<&[u8]>> {
+        Box::pin(
+            async move {
+                record_slice(self.record, self.meta.rec_offset(), self.meta.size())
+            }
+        )
+    }
+
+    fn ready(&self) -> bool {
+        self.ready.is_some()
+    }
+}
+
+/// A block number inside `.dbt` memo file stored as 10 ASCII digits.
+/// The content itself can be read by [load](struct.RawMemoField.html#method.load).
+#[derive(Clone)]
+pub struct RawMemoField {
+    bytes: MemReferer<[u8]>
+}
+
+impl RawMemoField {
+    /// Take the field bytes out of a record
+    pub fn new(bytes: &[u8]) -> RawMemoField {
+        RawMemoField {
+            bytes: MemReferer::from(bytes)
+        }
+    }
+
+    /// Read text content pointed by this field from given memo file.
+    /// It return `None` if this field doesn't point to any memo block.
+    pub fn load<R: Read + Seek>(&self, memo: &mut MemoFile<R>) -> Result<Option<String>> {
+        memo.read_text(self.get()?)
+    }
+
+    /// Field bytes to be put back into a record
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+impl ConversionField<u32> for RawMemoField {
+    fn get(&self) -> Result<u32> {
+        foxpro::memo::parse_block_number(&self.bytes)
+    }
+
+    fn set(&mut self, value: &u32) -> Result<()> {
+        let size = if self.bytes.is_empty() { 10 } else { self.bytes.len() };
+        self.bytes = foxpro::memo::block_number_bytes(*value, size)?.into();
+        Ok(())
+    }
+}
@@ -0,0 +1,176 @@
+use super::*;
+
+use futures::executor::block_on;
+
+fn descriptor(name: &str, datatype: u8, size: u8, precision: u8) -> Vec<u8> {
+    let mut bytes = vec![0u8; 32];
+    bytes[..name.len()].copy_from_slice(name.as_bytes());
+    bytes[11] = datatype;
+    // dBase III+ leave memory address of the field here which has nothing to do with offset
+    bytes[12..16].copy_from_slice(&[0x12, 0x34, 0x56, 0x78]);
+    bytes[16] = size;
+    bytes[17] = precision;
+    bytes
+}
+
+/// dBase III+ table with memo (0x83) containing NAME C5, BORN D8 and NOTE M10
+fn people_dbf(people: &[(&str, &str, &str)]) -> Vec<u8> {
+    let mut bytes = vec![0u8; 32];
+    bytes[0] = 0x83;
+    bytes[1..4].copy_from_slice(&[98, 12, 31]);
+    bytes[4..8].copy_from_slice(&(people.len() as u32).to_le_bytes());
+    bytes[8..10].copy_from_slice(&129u16.to_le_bytes());
+    bytes[10..12].copy_from_slice(&24u16.to_le_bytes());
+    bytes.extend(descriptor("NAME", b'C', 5, 0));
+    bytes.extend(descriptor("BORN", b'D', 8, 0));
+    bytes.extend(descriptor("NOTE", b'M', 10, 0));
+    bytes.push(0x0D);
+    for (name, born, note) in people {
+        bytes.push(b' ');
+        bytes.extend(format!("{:<5}", name).as_bytes());
+        bytes.extend(born.as_bytes());
+        bytes.extend(format!("{:>10}", note).as_bytes());
+    }
+    bytes.push(0x1A);
+    bytes
+}
+
+fn temp_file(name: &str, bytes: &[u8]) -> String {
+    let path = std::env::temp_dir().join(format!("adbf_rs_{}", name));
+    std::fs::write(&path, bytes).unwrap();
+    path.to_str().unwrap().to_owned()
+}
+
+struct Person {
+    name: String,
+    born: RawDateField,
+    note: RawMemoField
+}
+
+impl RecordOps for Person {
+    fn from_bytes(record: &[u8]) -> Result<Self> {
+        Ok(Person {
+            name: decode("cp1252", &record[1..6])?.trim_end().to_owned(),
+            born: RawDateField::new(&record[6..14]),
+            note: RawMemoField::new(&record[14..24])
+        })
+    }
+
+    fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut bytes = vec![b' '];
+        bytes.extend(encode("cp1252", &format!("{:<5}", self.name))?);
+        bytes.extend(self.born.as_bytes());
+        bytes.extend(self.note.as_bytes());
+        Ok(bytes)
+    }
+}
+
+#[test]
+fn test_read_dbase_table() {
+    use futures::stream::TryStreamExt;
+
+    let path = temp_file("dbase_people.dbf", &people_dbf(&[("ann", "19800229", "1"), ("bob", "20011231", "")]));
+    let header = block_on(read_header(&path, cp_mapper)).unwrap();
+    assert!(header.db_type.is_dbase());
+    assert!(!header.db_type.is_visual_foxpro());
+    assert_eq!(header.codepage, "cp1252");
+    assert_eq!(header.last_update, NaiveDate::from_ymd_opt(1998, 12, 31).unwrap());
+
+    let mut f = File::open(&path).unwrap();
+    let fields = block_on(read_fields(&mut f, &header)).unwrap();
+    assert_eq!(fields.iter().map(|f| f.name.as_str()).collect::<Vec<_>>(), vec!["NAME", "BORN", "NOTE"]);
+    assert_eq!(fields.iter().map(|f| f.offset).collect::<Vec<_>>(), vec![1, 6, 14]);
+
+    let people: Vec<Person> = block_on(async {
+        stream_table(futures::io::Cursor::new(std::fs::read(&path).unwrap())).await.unwrap().try_collect().await
+    }).unwrap();
+    assert_eq!(people[0].name, "ann");
+    assert_eq!(ConversionField::<NaiveDate>::get(&people[0].born).unwrap(), NaiveDate::from_ymd_opt(1980, 2, 29).unwrap());
+    assert_eq!(people[0].note.get().unwrap(), 1);
+    assert_eq!(people[1].note.get().unwrap(), 0);
+
+    let mut born = people[1].born.clone();
+    born.set(&NaiveDate::from_ymd_opt(2002, 1, 5).unwrap()).unwrap();
+    assert_eq!(born.as_bytes(), b"20020105");
+    assert_eq!(people[1].to_bytes().unwrap(), &people_dbf(&[("bob", "20011231", "")])[129..153]);
+}
+
+#[test]
+fn test_dbase_date_field() {
+    let record = b" 20200229        ";
+    let mut field = DateField::new(Field { offset: 1, ..Field::new("BORN", b'D', 8, 0) }, record);
+    block_on(field.from_record_bytes()).unwrap();
+    assert!(field.ready());
+    assert_eq!(field.to_string(), "2020-02-29");
+
+    // blank date is empty rather than an error
+    let mut blank = DateField::new(Field { offset: 9, ..Field::new("DIED", b'D', 8, 0) }, record);
+    block_on(blank.from_record_bytes()).unwrap();
+    assert_eq!(blank.to_string(), "");
+    match ConversionField::<NaiveDate>::get(&RawDateField::new(b"20200230")) {
+        Err(Error::BadDate(bytes)) => assert_eq!(bytes, b"20200230"),
+        other => panic!("Expect bad date but found {:?}", other)
+    }
+    assert_eq!(ConversionField::<Option<NaiveDate>>::get(&RawDateField::new(b"        ")).unwrap(), None);
+    assert!(ConversionField::<NaiveDate>::get(&RawDateField::new(b"        ")).is_err());
+
+    // FoxPro binary layout isn't a dBase date
+    let binary = 737_484i64.to_le_bytes();
+    match ConversionField::<Option<NaiveDate>>::get(&RawDateField::new(&binary)) {
+        Err(Error::BadDate(bytes)) => assert_eq!(bytes, binary),
+        other => panic!("Expect bad date but found {:?}", other)
+    }
+    let mut record = vec![b' '];
+    record.extend(&binary);
+    let mut field = DateField::new(Field { offset: 1, ..Field::new("BORN", b'D', 8, 0) }, &record);
+    assert!(matches!(block_on(field.from_record_bytes()), Err(Error::BadDate(_))));
+}
+
+#[test]
+fn test_read_dbt_memo() {
+    // dBase III+ memo file with 3 memos, the second one span across 2 blocks
+    let mut bytes = vec![0u8; 512];
+    bytes[0..4].copy_from_slice(&5u32.to_le_bytes());
+    let long_memo = "x".repeat(600);
+    for memo in ["first", long_memo.as_str(), "last"].iter() {
+        let mut block = memo.as_bytes().to_vec();
+        block.extend(&[0x1A, 0x1A]);
+        block.resize(block.len().div_ceil(512) * 512, 0);
+        bytes.extend(block);
+    }
+    // the last memo isn't padded to the block boundary
+    bytes.truncate(512 * 4 + 6);
+
+    let mut memo = MemoFile::new(std::io::Cursor::new(bytes), "cp1252").unwrap();
+    assert_eq!(memo.block_size(), 512);
+    assert_eq!(memo.read_text(1).unwrap(), Some("first".to_owned()));
+    assert_eq!(memo.read_text(2).unwrap(), Some(long_memo));
+    assert_eq!(RawMemoField::new(b"         4").load(&mut memo).unwrap(), Some("last".to_owned()));
+    assert_eq!(memo.read_text(0).unwrap(), None);
+    assert!(memo.read_text(5).is_err());
+}
+
+#[test]
+fn test_read_dbase_iv_memo() {
+    let mut bytes = vec![0u8; 64];
+    bytes[0..4].copy_from_slice(&3u32.to_le_bytes());
+    bytes[20..22].copy_from_slice(&64u16.to_le_bytes());
+    let mut block = vec![0xFF, 0xFF, 0x08, 0x00];
+    // content with 0x1A 0x1A inside is kept as length is known
+    let content = [b'a', 0x1A, 0x1A, b'b'];
+    block.extend(&(content.len() as u32 + 8).to_le_bytes());
+    block.extend(&content);
+    block.resize(64, 0);
+    bytes.extend(block);
+
+    let mut memo = MemoFile::new(std::io::Cursor::new(bytes.clone()), "cp1252").unwrap();
+    assert_eq!(memo.block_size(), 64);
+    assert_eq!(memo.read_binary(1).unwrap(), Some(content.to_vec()));
+
+    bytes[68..72].copy_from_slice(&u32::MAX.to_le_bytes());
+    let mut memo = MemoFile::new(std::io::Cursor::new(bytes), "cp1252").unwrap();
+    match memo.read_binary(1) {
+        Err(Error::Truncated { expected, found }) => assert_eq!((expected, found), (u32::MAX as usize, 64)),
+        other => panic!("Expect truncated error but found {:?}", other)
+    }
+}
@@ -0,0 +1,157 @@
+use std::{
+    convert::TryFrom,
+    fmt,
+    ops::{Add, AddAssign, Mul, Neg, Sub, SubAssign},
+    str::FromStr
+};
+
+use super::*;
+
+/// Money value of `Y` field.
+///
+/// Visual FoxPro store currency as 64 bits integer of ten-thousandths, e.g. `1.5` is stored
+/// as `15000`. `Currency` keep that integer as is so value read from a field is written back
+/// bit-for-bit and arithmetic is exact.
+/// Like integer, `+`, `-` and `*` panic on overflow in debug build. Use `checked_*` to
+/// handle overflow.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Currency(i64);
+
+impl Currency {
+    /// Number of ten-thousandths in a unit
+    pub const SCALE: i64 = 10_000;
+
+    /// Create currency from number of ten-thousandths as stored in the field
+    pub fn from_raw(raw: i64) -> Currency {
+        Currency(raw)
+    }
+
+    /// Number of ten-thousandths as stored in the field
+    pub fn raw(&self) -> i64 {
+        self.0
+    }
+
+    /// Convert floating point number, rounded to 4 decimal places.
+    /// It return `Error::Encode` if the value is not finite or too large.
+    pub fn from_f64(value: f64) -> Result<Currency> {
+        let raw = (value * Currency::SCALE as f64).round();
+        if !raw.is_finite() || raw < i64::MIN as f64 || raw >= i64::MAX as f64 {
+            return Err(Error::Encode(format!("{} doesn't fit in currency", value)));
+        }
+        Ok(Currency(raw as i64))
+    }
+
+    /// Nearest floating point number
+    pub fn to_f64(&self) -> f64 {
+        Decimal::from(*self).to_f64()
+    }
+
+    /// The value as integer if it has no fraction
+    pub fn to_i64(&self) -> Option<i64> {
+        match self.0 % Currency::SCALE {
+            0 => Some(self.0 / Currency::SCALE),
+            _ => None
+        }
+    }
+
+    /// Sum of two currencies or `None` on overflow
+    pub fn checked_add(self, other: Currency) -> Option<Currency> {
+        self.0.checked_add(other.0).map(Currency)
+    }
+
+    /// Difference of two currencies or `None` on overflow
+    pub fn checked_sub(self, other: Currency) -> Option<Currency> {
+        self.0.checked_sub(other.0).map(Currency)
+    }
+
+    /// Currency multiplied by a quantity or `None` on overflow
+    pub fn checked_mul(self, quantity: i64) -> Option<Currency> {
+        self.0.checked_mul(quantity).map(Currency)
+    }
+}
+
+impl Add for Currency {
+    type Output = Currency;
+
+    fn add(self, other: Currency) -> Currency {
+        Currency(self.0 + other.0)
+    }
+}
+
+impl AddAssign for Currency {
+    fn add_assign(&mut self, other: Currency) {
+        self.0 += other.0;
+    }
+}
+
+impl Sub for Currency {
+    type Output = Currency;
+
+    fn sub(self, other: Currency) -> Currency {
+        Currency(self.0 - other.0)
+    }
+}
+
+impl SubAssign for Currency {
+    fn sub_assign(&mut self, other: Currency) {
+        self.0 -= other.0;
+    }
+}
+
+impl Mul<i64> for Currency {
+    type Output = Currency;
+
+    fn mul(self, quantity: i64) -> Currency {
+        Currency(self.0 * quantity)
+    }
+}
+
+impl Neg for Currency {
+    type Output = Currency;
+
+    fn neg(self) -> Currency {
+        Currency(-self.0)
+    }
+}
+
+impl From<i32> for Currency {
+    fn from(value: i32) -> Currency {
+        Currency(value as i64 * Currency::SCALE)
+    }
+}
+
+impl From<Currency> for Decimal {
+    fn from(value: Currency) -> Decimal {
+        Decimal::new(value.0 as i128, 4)
+    }
+}
+
+/// Decimal is rounded to 4 decimal places.
+/// It return `Error::Overflow` if the value doesn't fit in currency.
+impl TryFrom<Decimal> for Currency {
+    type Error = Error;
+
+    fn try_from(value: Decimal) -> Result<Currency> {
+        let raw = value.rescale(4)?.mantissa();
+        i64::try_from(raw).map(Currency).map_err(|_| Error::Overflow {
+            size: raw.unsigned_abs().to_string().len(),
+            max: i64::MAX.to_string().len()
+        })
+    }
+}
+
+/// Always print 4 decimal places, e.g. `-1.5000`
+impl fmt::Display for Currency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", Decimal::from(*self))
+    }
+}
+
+/// Parse decimal text, rounded to 4 decimal places
+impl FromStr for Currency {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<Currency> {
+        Currency::try_from(text.parse::<Decimal>()?)
+    }
+}
@@ -0,0 +1,173 @@
+use std::{
+    cmp::Ordering,
+    fmt,
+    str::FromStr
+};
+
+use super::*;
+
+/// Exact decimal number of `N` and `F` field.
+///
+/// The value is `mantissa / 10^scale`, e.g. `Decimal::new(12345, 2)` is `123.45`.
+/// Number stored in DBF is at most 20 characters so it always fit in the 128 bits mantissa.
+/// Two decimals are equal if they have the same value regardless of scale,
+/// e.g. `1.5` equals `1.50`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Decimal {
+    mantissa: i128,
+    scale: u32
+}
+
+impl Decimal {
+    /// Create a decimal of value `mantissa / 10^scale`
+    pub fn new(mantissa: i128, scale: u32) -> Decimal {
+        Decimal {
+            mantissa,
+            scale
+        }
+    }
+
+    /// The value multiplied by 10^scale
+    pub fn mantissa(&self) -> i128 {
+        self.mantissa
+    }
+
+    /// Number of digits after the decimal point
+    pub fn scale(&self) -> u32 {
+        self.scale
+    }
+
+    /// Change number of digits after the decimal point.
+    /// Extra digits are rounded half away from zero.
+    /// It return `Error::Overflow` if the mantissa doesn't fit in 128 bits.
+    pub fn rescale(&self, scale: u32) -> Result<Decimal> {
+        let mantissa = match scale.cmp(&self.scale) {
+            Ordering::Equal => self.mantissa,
+            Ordering::Greater => pow10(scale - self.scale).and_then(|p| self.mantissa.checked_mul(p)).ok_or(Error::Overflow {
+                size: (scale - self.scale) as usize,
+                max: 38
+            })?,
+            Ordering::Less => match pow10(self.scale - scale) {
+                Some(p) => {
+                    let (quotient, remainder) = (self.mantissa / p, self.mantissa % p);
+                    if remainder.abs() * 2 >= p {
+                        quotient + self.mantissa.signum()
+                    } else {
+                        quotient
+                    }
+                },
+                // dropping more than 38 digits leaves nothing
+                None => 0
+            }
+        };
+        Ok(Decimal::new(mantissa, scale))
+    }
+
+    /// Convert floating point number into decimal of given scale.
+    /// It return `Error::Encode` if the value is not finite.
+    pub fn from_f64(value: f64, scale: u32) -> Result<Decimal> {
+        if !value.is_finite() {
+            return Err(Error::Encode(format!("{} is not a decimal number", value)));
+        }
+        format!("{:.scale$}", value, scale = scale as usize).parse().map_err(|_| Error::Encode(format!("{} doesn't fit in decimal", value)))
+    }
+
+    /// Nearest floating point number
+    pub fn to_f64(&self) -> f64 {
+        // parsing the text is exact unlike dividing by power of 10
+        self.to_string().parse().unwrap_or(f64::NAN)
+    }
+
+    /// The value as integer if it has no fraction
+    pub fn to_i128(&self) -> Option<i128> {
+        let p = pow10(self.scale)?;
+        match self.mantissa % p {
+            0 => Some(self.mantissa / p),
+            _ => None
+        }
+    }
+}
+
+/// 10^exp if it fit in i128
+fn pow10(exp: u32) -> Option<i128> {
+    10i128.checked_pow(exp)
+}
+
+impl From<i32> for Decimal {
+    fn from(value: i32) -> Decimal {
+        Decimal::new(value as i128, 0)
+    }
+}
+
+impl From<i64> for Decimal {
+    fn from(value: i64) -> Decimal {
+        Decimal::new(value as i128, 0)
+    }
+}
+
+/// Parse decimal text such as `-12.50`, `+3` or `.5`
+impl FromStr for Decimal {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<Decimal> {
+        let invalid = || Error::Decode(format!("{:?} is not a number", text));
+        let (negative, digits) = match text.as_bytes().first() {
+            Some(b'-') => (true, &text[1..]),
+            Some(b'+') => (false, &text[1..]),
+            _ => (false, text)
+        };
+        let (integer, fraction) = match digits.find('.') {
+            Some(dot) => (&digits[..dot], &digits[dot + 1..]),
+            None => (digits, "")
+        };
+        if integer.is_empty() && fraction.is_empty() || !integer.bytes().chain(fraction.bytes()).all(|b| b.is_ascii_digit()) {
+            return Err(invalid());
+        }
+        let mut mantissa = 0i128;
+        for b in integer.bytes().chain(fraction.bytes()) {
+            mantissa = mantissa.checked_mul(10).and_then(|m| m.checked_add((b - b'0') as i128)).ok_or_else(invalid)?;
+        }
+        Ok(Decimal::new(if negative { -mantissa } else { mantissa }, fraction.len() as u32))
+    }
+}
+
+/// Print every digit up to the scale, e.g. `Decimal::new(1250, 2)` is `12.50`
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let digits = self.mantissa.unsigned_abs().to_string();
+        let scale = self.scale as usize;
+        let sign = if self.mantissa < 0 { "-" } else { "" };
+        if scale == 0 {
+            return write!(f, "{}{}", sign, digits);
+        }
+        let digits = format!("{:0>width$}", digits, width = scale + 1);
+        let (integer, fraction) = digits.split_at(digits.len() - scale);
+        write!(f, "{}{}.{}", sign, integer, fraction)
+    }
+}
+
+impl PartialEq for Decimal {
+    fn eq(&self, other: &Decimal) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Decimal {}
+
+impl PartialOrd for Decimal {
+    fn partial_cmp(&self, other: &Decimal) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Decimal {
+    fn cmp(&self, other: &Decimal) -> Ordering {
+        let scale = self.scale.max(other.scale);
+        match (self.rescale(scale), other.rescale(scale)) {
+            (Ok(a), Ok(b)) => a.mantissa.cmp(&b.mantissa),
+            // only a number too large for 128 bits fail to rescale
+            (Err(_), _) => if self.mantissa < 0 { Ordering::Less } else { Ordering::Greater },
+            (_, Err(_)) => if other.mantissa < 0 { Ordering::Greater } else { Ordering::Less }
+        }
+    }
+}
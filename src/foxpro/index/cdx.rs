@@ -0,0 +1,311 @@
+use super::*;
+
+/// Visual FoxPro compound index file (.cdx).
+///
+/// A compound index file hold many tags. Each tag is a compact index with its own header
+/// and B-tree. The file start with a header of tag directory which is also a compact index
+/// whose keys are tag names and record numbers are the offset of each tag header.
+///
+/// Structural index, i.e. the one that has the same name as the table and is opened along
+/// with it, is marked by bit 0x01 of [Header::table_flag](../../struct.Header.html#structfield.table_flag).
+///
+/// Index opened for writing can be kept up to date with the table by
+/// [insert_record](struct.Cdx.html#method.insert_record) and
+/// [update_record](struct.Cdx.html#method.update_record) or rebuilt by
+/// [reindex](struct.Cdx.html#method.reindex). Only tags whose key expression is supported
+/// by [KeyExpr](struct.KeyExpr.html) and that has no FOR clause can be written.
+pub struct Cdx<R = File> {
+    source: R,
+    directory: IndexHeader,
+    tags: Vec<Tag>
+}
+
+impl Cdx<File> {
+    /// Open compound index file at given path.
+    /// The schema is the table that the index belongs to. It is used to parse key expressions.
+    pub fn open<P: AsRef<std::path::Path>>(path: P, schema: &Schema) -> Result<Cdx<File>> {
+        Cdx::new(File::open(path)?, schema)
+    }
+
+    /// Same as [open](struct.Cdx.html#method.open) but the file is opened for writing as well.
+    pub fn open_rw<P: AsRef<std::path::Path>>(path: P, schema: &Schema) -> Result<Cdx<File>> {
+        Cdx::new(std::fs::OpenOptions::new().read(true).write(true).open(path)?, schema)
+    }
+}
+
+impl<R> Cdx<R> where R: Read + Seek {
+    /// Read tag directory and header of every tag from given source.
+    pub fn new(mut source: R, schema: &Schema) -> Result<Cdx<R>> {
+        let directory = read_header(&mut source, 0)?;
+        let mut tag_headers = Vec::new();
+        for entry in Entries::new(&mut source, directory.root, directory_layout(&directory))? {
+            let entry = entry?;
+            let name = String::from_utf8_lossy(&entry.key).trim_end_matches(&[' ', '\0'][..]).to_uppercase();
+            tag_headers.push((name, entry.record));
+        }
+        // directory is sorted by name but tags are listed in the order they are created
+        tag_headers.sort_by_key(|(_, offset)| *offset);
+
+        let mut tags = Vec::with_capacity(tag_headers.len());
+        for (name, offset) in tag_headers {
+            let header = read_header(&mut source, offset)?;
+            tags.push(Tag::from_header(name, header, offset, true, schema));
+        }
+
+        Ok(Cdx {
+            source,
+            directory,
+            tags
+        })
+    }
+
+    /// Every tag in the order they are created
+    pub fn tags(&self) -> &[Tag] {
+        &self.tags
+    }
+
+    /// Tag with given name. The name is case-insensitive.
+    pub fn tag(&self, name: &str) -> Option<&Tag> {
+        self.tags.iter().find(|tag| tag.name.eq_ignore_ascii_case(name.trim()))
+    }
+
+    /// Iterate every key of given tag in index order
+    pub fn entries(&mut self, tag: &str) -> Result<Entries<&mut R>> {
+        let i = self.tag_position(tag)?;
+        self.tags[i].entries(&mut self.source)
+    }
+
+    /// Record numbers of every key of given tag that starts with given key, in index order.
+    /// It is the same as `SEEK` with `SET EXACT OFF`.
+    pub fn seek(&mut self, tag: &str, key: &[u8]) -> Result<Vec<u32>> {
+        let entries = self.range(tag, key.to_vec()..=key.to_vec())?;
+        Ok(entries.into_iter().map(|entry| entry.record).collect())
+    }
+
+    /// Keys of given tag that lie within given range, in index order.
+    /// Only the first bytes of key, as many as the bound has, is compared with the bound
+    /// so `b"AB".to_vec()..=b"AB".to_vec()` include every key that starts with `AB`.
+    /// Entries of descending tag are ordered from the end of range.
+    pub fn range<B: RangeBounds<Vec<u8>>>(&mut self, tag: &str, range: B) -> Result<Vec<IndexEntry>> {
+        let i = self.tag_position(tag)?;
+        self.tags[i].range(&mut self.source, range)?.collect()
+    }
+
+    /// Same as [range](struct.Cdx.html#method.range) but the index is consumed and
+    /// keys are read as they are taken.
+    pub fn into_range<B: RangeBounds<Vec<u8>>>(self, tag: &str, range: B) -> Result<RangeEntries<R>> {
+        let i = self.tag_position(tag)?;
+        self.tags[i].range(self.source, range)
+    }
+
+    fn tag_position(&self, name: &str) -> Result<usize> {
+        self.tags.iter()
+            .position(|tag| tag.name.eq_ignore_ascii_case(name.trim()))
+            .ok_or_else(|| Error::TagNotFound(name.to_owned()))
+    }
+
+    /// Record numbers of given tag in index order. Record number starts at 1.
+    pub fn record_numbers(&mut self, tag: &str) -> Result<Vec<u32>> {
+        self.entries(tag)?.map(|entry| entry.map(|entry| entry.record)).collect()
+    }
+
+    /// Take the underlying source out of this index
+    pub fn into_inner(self) -> R {
+        self.source
+    }
+}
+
+impl<R> Cdx<R> where R: Read + Write + Seek {
+    /// Check that every tag can be written so that nothing is changed if any of them can't.
+    pub fn check_writable(&self) -> Result<()> {
+        self.tags.iter().try_for_each(|tag| tag.writable_key().map(|_| ()))
+    }
+
+    /// Add key of given record to given tag. The key shall be as long as key of the tag.
+    /// Unique tag keeps only the first record of each key so key that is already in it is skipped.
+    pub fn insert(&mut self, tag: &str, key: &[u8], record: u32) -> Result<()> {
+        let i = self.tag_position(tag)?;
+        self.tags[i].check_key_len(key)?;
+        if self.tags[i].unique && !self.seek(tag, key)?.is_empty() {
+            return Ok(());
+        }
+        let entry = IndexEntry {
+            key: key.to_vec(),
+            record
+        };
+        self.with_tree(i, |tree| tree.insert(entry))
+    }
+
+    /// Take key of given record out of given tag.
+    /// It return false if the tag doesn't have such key.
+    pub fn remove(&mut self, tag: &str, key: &[u8], record: u32) -> Result<bool> {
+        let i = self.tag_position(tag)?;
+        self.tags[i].check_key_len(key)?;
+        let entry = IndexEntry {
+            key: key.to_vec(),
+            record
+        };
+        self.with_tree(i, |tree| tree.remove(&entry))
+    }
+
+    /// Add keys of a new record to every tag.
+    /// The bytes is the whole record, including deletion flag, laid out by given schema.
+    pub fn insert_record(&mut self, record: u32, bytes: &[u8], schema: &Schema) -> Result<()> {
+        let keys = self.record_keys(bytes, schema)?;
+        self.insert_keys(record, &keys)
+    }
+
+    /// Key of given record in every tag, in the order of [tags](struct.Cdx.html#method.tags).
+    /// Nothing is written so it can check a record before anything else is changed.
+    pub fn record_keys(&self, bytes: &[u8], schema: &Schema) -> Result<Vec<Vec<u8>>> {
+        self.check_writable()?;
+        self.tags.iter().map(|tag| {
+            let key = tag.writable_key()?.key(bytes, schema)?;
+            tag.check_key_len(&key)?;
+            Ok(key)
+        }).collect()
+    }
+
+    /// Add keys of a new record, as given by [record_keys](struct.Cdx.html#method.record_keys),
+    /// to every tag. If any tag fails, the keys that are already added to other tags are
+    /// taken out again.
+    pub fn insert_keys(&mut self, record: u32, keys: &[Vec<u8>]) -> Result<()> {
+        if keys.len() != self.tags.len() {
+            return Err(Error::Encode(format!("Index has {} tags but {} keys are given", self.tags.len(), keys.len())));
+        }
+        for i in 0..self.tags.len() {
+            let name = self.tags[i].name.clone();
+            if let Err(e) = self.insert(&name, &keys[i], record) {
+                for (j, key) in keys[..i].iter().enumerate() {
+                    let name = self.tags[j].name.clone();
+                    self.remove(&name, key, record)?;
+                }
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Move record to its new key in every tag whose key expression reads any field
+    /// that is changed.
+    ///
+    /// Unique tag may have had the record as the only one of its old key while other records
+    /// share the key. Such tag and key are returned so that the caller can
+    /// [insert](struct.Cdx.html#method.insert) the first of those records.
+    ///
+    /// Keys are built before any tag is written. If any tag fails, tags that are already
+    /// moved are moved back to the old key.
+    pub fn update_record(&mut self, record: u32, old: &[u8], new: &[u8], schema: &Schema) -> Result<Vec<(String, Vec<u8>)>> {
+        self.check_writable()?;
+        let mut changes = Vec::new();
+        for tag in self.tags.iter() {
+            if let Some((old_key, new_key)) = tag.changed_key(old, new, schema)? {
+                tag.check_key_len(&new_key)?;
+                changes.push((tag.name.clone(), tag.unique, old_key, new_key));
+            }
+        }
+        // tag along with whether the old key was removed and whether the new key was inserted
+        let mut moved: Vec<(usize, bool, bool)> = Vec::with_capacity(changes.len());
+        let mut failure = None;
+        for (i, (name, _, old_key, new_key)) in changes.iter().enumerate() {
+            let removed = match self.remove(name, old_key, record) {
+                Ok(removed) => removed,
+                Err(e) => {
+                    failure = Some(e);
+                    break;
+                }
+            };
+            let inserted = self.insert(name, new_key, record);
+            moved.push((i, removed, inserted.is_ok()));
+            if let Err(e) = inserted {
+                failure = Some(e);
+                break;
+            }
+        }
+        if let Some(e) = failure {
+            for (i, removed, inserted) in moved.into_iter().rev() {
+                let (name, _, old_key, new_key) = &changes[i];
+                if inserted {
+                    self.remove(name, new_key, record)?;
+                }
+                if removed {
+                    self.insert(name, old_key, record)?;
+                }
+            }
+            return Err(e);
+        }
+        Ok(moved.into_iter()
+            .filter(|(i, removed, _)| *removed && changes[*i].1)
+            .map(|(i, _, _)| (changes[i].0.clone(), changes[i].2.clone()))
+            .collect())
+    }
+
+    /// Rebuild every tag from scratch out of given records, each one along with its record
+    /// number. The bytes is the whole record, including deletion flag, laid out by given schema.
+    ///
+    /// The whole file is rewritten, tags keep their definition and order. Key length of each tag
+    /// is taken from its key expression. Space left after the new content, if the file used to be
+    /// larger, is put into the free list.
+    pub fn reindex<I>(&mut self, records: I, schema: &Schema) -> Result<()> where I: IntoIterator<Item=(u32, Vec<u8>)> {
+        self.check_writable()?;
+        let records: Vec<(u32, Vec<u8>)> = records.into_iter().collect();
+        let mut content = vec![0u8; HEADER_LEN];
+        let mut directory = Vec::with_capacity(self.tags.len());
+        for tag in self.tags.iter_mut() {
+            let key_len = tag.refresh_key_len()?;
+            let keys = tag.sorted_keys(&records, schema)?;
+            let offset = to_offset(content.len())?;
+            let (root, nodes) = build_tree(keys, &tag.layout(), offset + HEADER_LEN as u32)?;
+            let mut header = read_header(&mut self.source, tag.header)?;
+            header.key_len = key_len;
+            header.root = root;
+            header.free = NO_NODE;
+            content.extend(header.to_bytes()?);
+            content.extend(nodes);
+            tag.header = offset;
+            tag.root = root;
+            let mut name = tag.name.clone().into_bytes();
+            name.resize(self.directory.key_len, b' ');
+            directory.push(IndexEntry {
+                key: name,
+                record: offset
+            });
+        }
+        directory.sort_by(|a, b| index_order(a, b, false));
+        let (root, nodes) = build_tree(directory, &directory_layout(&self.directory), to_offset(content.len())?)?;
+        content.extend(nodes);
+
+        self.directory.root = root;
+        self.directory.free = free_after(&mut self.source, content.len())?;
+        content[..HEADER_LEN].copy_from_slice(&self.directory.to_bytes()?);
+        write_at(&mut self.source, 0, &content)
+    }
+
+    /// Run given operation on B-tree of tag `i` then write its new root and the new free list
+    /// into their headers
+    fn with_tree<F, T>(&mut self, i: usize, op: F) -> Result<T> where F: FnOnce(&mut Tree<'_, R>) -> Result<T> {
+        let tag = &self.tags[i];
+        let header = tag.header;
+        let mut tree = Tree::new(&mut self.source, tag.root, self.directory.free, tag.layout(), tag.descending);
+        let result = op(&mut tree);
+        let (root, free) = (tree.root, tree.free);
+        if root != self.tags[i].root {
+            write_at(&mut self.source, header, &root.to_le_bytes())?;
+            self.tags[i].root = root;
+        }
+        if free != self.directory.free {
+            write_at(&mut self.source, 4, &free.to_le_bytes())?;
+            self.directory.free = free;
+        }
+        result
+    }
+}
+
+/// Tag directory, whose keys are tag names
+fn directory_layout(directory: &IndexHeader) -> Layout {
+    Layout {
+        key_len: directory.key_len,
+        trail: b' ',
+        compact: true
+    }
+}
@@ -0,0 +1,421 @@
+use super::*;
+
+/// Type of an index key, inferred from the key expression.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeyType {
+    /// Text in table codepage padded with spaces
+    Character,
+    /// `N`, `F`, `B` and `Y` value as 8 bytes double
+    Numeric,
+    /// `I` value as 4 bytes integer
+    Integer,
+    /// `D` value as 8 bytes double of Julian day
+    Date,
+    /// `T` value as 8 bytes double of Julian day including fraction of day
+    DateTime,
+    /// `L` value as `T` or `F`
+    Logical
+}
+
+impl KeyType {
+    /// Byte that trailing bytes of compressed key are filled with
+    pub fn trail(&self) -> u8 {
+        match self {
+            KeyType::Character => b' ',
+            _ => 0
+        }
+    }
+
+    /// Turn a value into key of given length.
+    ///
+    /// `Value::Char` is encoded using the codepage and padded with spaces while
+    /// `Value::Binary` is taken as is. Both are `Error::Overflow` if they are longer than the key.
+    /// Number, date and datetime key is big endian double whose sign bit is flipped, or every bit is
+    /// flipped for negative number, so that keys sort the same way as the values.
+    /// Empty date and datetime, i.e. `Value::Blank` or `Value::Null`, is all zero and sort before any date.
+    pub fn encode(&self, value: &Value, len: usize, codepage: &str) -> Result<Vec<u8>> {
+        let key = match (self, value) {
+            (KeyType::Character, Value::Char(v)) => pad_key(encode(codepage, v)?, len)?,
+            (KeyType::Character, Value::Binary(v)) => pad_key(v.clone(), len)?,
+            (KeyType::Numeric, Value::Numeric(v)) => double_key(v.to_f64()),
+            (KeyType::Numeric, Value::Currency(v)) => double_key(v.to_f64()),
+            (KeyType::Numeric, Value::Double(v)) => double_key(*v),
+            (KeyType::Numeric, Value::Integer(v)) => double_key(*v as f64),
+            (KeyType::Integer, Value::Integer(v)) => ((*v as u32) ^ 0x8000_0000).to_be_bytes().to_vec(),
+            (KeyType::Date, Value::Date(v)) => double_key(julian_day(v) as f64),
+            (KeyType::DateTime, Value::DateTime(v)) => {
+                let millis = v.num_seconds_from_midnight() as f64 * 1000.0 + (v.nanosecond() / 1_000_000) as f64;
+                double_key(julian_day(&v.date()) as f64 + millis / MILLIS_PER_DAY as f64)
+            },
+            (KeyType::Date, Value::Null | Value::Blank) | (KeyType::DateTime, Value::Null | Value::Blank) => vec![0; 8],
+            (KeyType::Logical, Value::Logical(v)) => vec![if *v { b'T' } else { b'F' }],
+            // blank logical field is false
+            (KeyType::Logical, Value::Null | Value::Blank) => vec![b'F'],
+            (KeyType::Numeric, Value::Null | Value::Blank) => double_key(0.0),
+            (KeyType::Integer, Value::Null) => 0x8000_0000u32.to_be_bytes().to_vec(),
+            _ => return Err(Error::Encode(format!("{:?} cannot be {:?} key", value, self)))
+        };
+        Ok(key)
+    }
+
+    /// Turn a value into key to search for.
+    /// It is the same as [encode](enum.KeyType.html#method.encode) except that text isn't
+    /// padded so it match every key that starts with it.
+    pub fn search_key(&self, value: &Value, len: usize, codepage: &str) -> Result<Vec<u8>> {
+        let text = match (self, value) {
+            (KeyType::Character, Value::Char(v)) => encode(codepage, v)?,
+            (KeyType::Character, Value::Binary(v)) => v.clone(),
+            _ => return self.encode(value, len, codepage)
+        };
+        if text.len() > len {
+            return Err(Error::Overflow {
+                size: text.len(),
+                max: len
+            });
+        }
+        Ok(text)
+    }
+}
+
+/// Julian day number of a date
+fn julian_day(date: &NaiveDate) -> i64 {
+    date.num_days_from_ce() as i64 - 1 + JULIAN_DAY_OF_CE
+}
+
+/// Key of a double which sort like the value when compared byte by byte
+fn double_key(value: f64) -> Vec<u8> {
+    // adding 0 turns -0 into 0
+    let mut bytes = (value + 0.0).to_be_bytes();
+    if bytes[0] & 0x80 == 0 {
+        bytes[0] ^= 0x80;
+    } else {
+        bytes.iter_mut().for_each(|b| *b = !*b);
+    }
+    bytes.to_vec()
+}
+
+/// Pad text key with spaces up to given length
+fn pad_key(mut key: Vec<u8>, len: usize) -> Result<Vec<u8>> {
+    if key.len() > len {
+        return Err(Error::Overflow {
+            size: key.len(),
+            max: len
+        });
+    }
+    key.resize(len, b' ');
+    Ok(key)
+}
+
+/// Cut or pad text to given length
+fn fit_text(mut text: Vec<u8>, len: usize) -> Vec<u8> {
+    text.resize(len, b' ');
+    text
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Node {
+    Field(usize),
+    Upper(Box<Node>),
+    Lower(Box<Node>),
+    Dtos(Box<Node>),
+    Str {
+        value: Box<Node>,
+        len: usize,
+        decimals: usize
+    },
+    Concat(Vec<Node>)
+}
+
+/// Key expression of an index tag, e.g. `UPPER(NAME)+DTOS(BORN)`.
+///
+/// Only a subset of FoxPro expression is understood. It is a field name, optionally with
+/// table alias, or a function `UPPER`, `LOWER`, `DTOS` and `STR` of it, and `+` of
+/// character expressions. Key is compared byte by byte, which is `MACHINE` collation.
+#[derive(Clone, Debug, PartialEq)]
+pub struct KeyExpr {
+    node: Node,
+    key_type: KeyType,
+    key_len: usize
+}
+
+impl KeyExpr {
+    /// Parse the expression against fields of the table.
+    /// It return `Error::Decode` if the expression refers to unknown field or
+    /// use a function that isn't supported.
+    pub fn parse(expression: &str, schema: &Schema) -> Result<KeyExpr> {
+        let tokens = tokenize(expression)?;
+        let mut parser = Parser {
+            tokens: &tokens,
+            pos: 0,
+            schema,
+            expression
+        };
+        let (node, key_type, width) = parser.concat()?;
+        if parser.pos != tokens.len() {
+            return Err(parser.error());
+        }
+        let key_len = match key_type {
+            KeyType::Character => width,
+            KeyType::Integer => 4,
+            KeyType::Logical => 1,
+            _ => 8
+        };
+        Ok(KeyExpr {
+            node,
+            key_type,
+            key_len
+        })
+    }
+
+    /// Return true if every name in the expression, other than function names, is a field
+    /// of the table. Unlike [parse](struct.KeyExpr.html#method.parse), functions aren't checked
+    /// so an expression that isn't supported can still be told apart from one of other table.
+    pub fn refers_to(expression: &str, schema: &Schema) -> bool {
+        let tokens = match tokenize(expression) {
+            Ok(tokens) => tokens,
+            Err(_) => return false
+        };
+        let mut fields = tokens.iter().enumerate().filter_map(|(i, token)| match (token, tokens.get(i + 1)) {
+            (Token::Name(_), Some(Token::Open)) => None,
+            (Token::Name(name), _) => Some(name),
+            _ => None
+        }).peekable();
+        fields.peek().is_some() && fields.all(|name| schema.index_of(name).is_some())
+    }
+
+    /// Type of the key
+    pub fn key_type(&self) -> KeyType {
+        self.key_type
+    }
+
+    /// Length of the key in bytes
+    pub fn key_len(&self) -> usize {
+        self.key_len
+    }
+
+    /// Index of every field that the key is made of
+    pub fn fields(&self) -> Vec<usize> {
+        let mut fields = Vec::new();
+        collect_fields(&self.node, &mut fields);
+        fields
+    }
+
+    /// Compute the key of given record
+    pub fn key(&self, record: &[u8], schema: &Schema) -> Result<Vec<u8>> {
+        let value = eval(&self.node, record, schema)?;
+        self.key_type.encode(&value, self.key_len, schema.codepage())
+    }
+}
+
+fn collect_fields(node: &Node, fields: &mut Vec<usize>) {
+    match node {
+        Node::Field(i) => if !fields.contains(i) {
+            fields.push(*i)
+        },
+        Node::Upper(inner) | Node::Lower(inner) | Node::Dtos(inner) => collect_fields(inner, fields),
+        Node::Str { value, .. } => collect_fields(value, fields),
+        Node::Concat(nodes) => nodes.iter().for_each(|node| collect_fields(node, fields))
+    }
+}
+
+/// Evaluate an expression. Text is `Value::Binary` of exactly the expression width.
+fn eval(node: &Node, record: &[u8], schema: &Schema) -> Result<Value> {
+    let value = match node {
+        Node::Field(i) => {
+            let field = &schema[*i];
+            let bytes = field_slice(record, field)?;
+            match field.datatype {
+                b'C' => Value::Binary(bytes.to_vec()),
+                b'V' => match record::read_value(bytes, field, schema.codepage(), schema.is_varlength(record, *i)?)? {
+                    Value::Char(v) => Value::Binary(fit_text(encode(schema.codepage(), &v)?, field.size)),
+                    _ => Value::Binary(vec![b' '; field.size])
+                },
+                _ if schema.is_null(record, *i)? => Value::Null,
+                _ => record::read_value(bytes, field, schema.codepage(), false)?
+            }
+        },
+        Node::Upper(inner) | Node::Lower(inner) => match eval(inner, record, schema)? {
+            Value::Binary(text) => {
+                let decoded = decode(schema.codepage(), &text)?;
+                let changed = match node {
+                    Node::Upper(_) => decoded.to_uppercase(),
+                    _ => decoded.to_lowercase()
+                };
+                Value::Binary(fit_text(encode(schema.codepage(), &changed)?, text.len()))
+            },
+            value => value
+        },
+        Node::Dtos(inner) => match eval(inner, record, schema)? {
+            Value::Date(v) => Value::Binary(v.format("%Y%m%d").to_string().into_bytes()),
+            Value::DateTime(v) => Value::Binary(v.format("%Y%m%d").to_string().into_bytes()),
+            _ => Value::Binary(vec![b' '; 8])
+        },
+        Node::Str { value, len, decimals } => {
+            let number = match eval(value, record, schema)? {
+                Value::Numeric(v) => v.to_f64(),
+                Value::Currency(v) => v.to_f64(),
+                Value::Double(v) => v,
+                Value::Integer(v) => v as f64,
+                _ => 0.0
+            };
+            let text = format!("{:>len$.decimals$}", number, len = *len, decimals = *decimals);
+            match text.len() > *len {
+                true => Value::Binary(vec![b'*'; *len]),
+                false => Value::Binary(text.into_bytes())
+            }
+        },
+        Node::Concat(nodes) => {
+            let mut text = Vec::new();
+            for node in nodes {
+                match eval(node, record, schema)? {
+                    Value::Binary(part) => text.extend_from_slice(&part),
+                    value => return Err(Error::Encode(format!("{:?} cannot be concatenated", value)))
+                }
+            }
+            Value::Binary(text)
+        }
+    };
+    Ok(value)
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Name(String),
+    Number(usize),
+    Open,
+    Close,
+    Comma,
+    Plus
+}
+
+fn tokenize(expression: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = expression.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        let token = match c {
+            c if c.is_whitespace() => continue,
+            '(' => Token::Open,
+            ')' => Token::Close,
+            ',' => Token::Comma,
+            '+' => Token::Plus,
+            c if c.is_ascii_digit() => {
+                let mut end = start + 1;
+                while let Some((i, _)) = chars.next_if(|(_, c)| c.is_ascii_digit()) {
+                    end = i + 1;
+                }
+                Token::Number(expression[start..end].parse().map_err(|_| Error::Decode(format!("Bad number in index expression {}", expression)))?)
+            },
+            c if c.is_alphabetic() || c == '_' => {
+                let mut end = start + c.len_utf8();
+                while let Some((i, c)) = chars.next_if(|(_, c)| c.is_alphanumeric() || *c == '_' || *c == '.') {
+                    end = i + c.len_utf8();
+                }
+                // alias such as `customer.name` is the table itself
+                let name = &expression[start..end];
+                Token::Name(name.rsplit('.').next().unwrap_or(name).to_uppercase())
+            },
+            c => return Err(Error::Decode(format!("Unexpected {:?} in index expression {}", c, expression)))
+        };
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    tokens: &'a [Token],
+    pos: usize,
+    schema: &'a Schema,
+    expression: &'a str
+}
+
+impl<'a> Parser<'a> {
+    fn error(&self) -> Error {
+        Error::Decode(format!("Unsupported index expression {}", self.expression))
+    }
+
+    fn next(&mut self) -> Option<&'a Token> {
+        let token = self.tokens.get(self.pos);
+        self.pos += 1;
+        token
+    }
+
+    fn expect(&mut self, token: Token) -> Result<()> {
+        match self.next() {
+            Some(t) if *t == token => Ok(()),
+            _ => Err(self.error())
+        }
+    }
+
+    /// `term ('+' term)*` where every term is character
+    fn concat(&mut self) -> Result<(Node, KeyType, usize)> {
+        let first = self.term()?;
+        if self.tokens.get(self.pos) != Some(&Token::Plus) {
+            return Ok(first);
+        }
+        let mut width = first.2;
+        let mut nodes = vec![first];
+        while self.tokens.get(self.pos) == Some(&Token::Plus) {
+            self.pos += 1;
+            let term = self.term()?;
+            width += term.2;
+            nodes.push(term);
+        }
+        if nodes.iter().any(|(_, key_type, _)| *key_type != KeyType::Character) {
+            return Err(self.error());
+        }
+        Ok((Node::Concat(nodes.into_iter().map(|(node, _, _)| node).collect()), KeyType::Character, width))
+    }
+
+    /// Field or function call
+    fn term(&mut self) -> Result<(Node, KeyType, usize)> {
+        let name = match self.next() {
+            Some(Token::Name(name)) => name,
+            _ => return Err(self.error())
+        };
+        if self.tokens.get(self.pos) != Some(&Token::Open) {
+            return self.field(name);
+        }
+        self.pos += 1;
+        let (arg, arg_type, arg_width) = self.concat()?;
+        let term = match (name.as_str(), arg_type) {
+            ("UPPER", KeyType::Character) => (Node::Upper(Box::new(arg)), KeyType::Character, arg_width),
+            ("LOWER", KeyType::Character) => (Node::Lower(Box::new(arg)), KeyType::Character, arg_width),
+            ("DTOS", KeyType::Date) | ("DTOS", KeyType::DateTime) => (Node::Dtos(Box::new(arg)), KeyType::Character, 8),
+            ("STR", KeyType::Numeric) | ("STR", KeyType::Integer) => {
+                let len = self.number_arg()?.unwrap_or(10);
+                let decimals = self.number_arg()?.unwrap_or(0);
+                (Node::Str { value: Box::new(arg), len, decimals }, KeyType::Character, len)
+            },
+            _ => return Err(self.error())
+        };
+        self.expect(Token::Close)?;
+        Ok(term)
+    }
+
+    /// Optional `, number` argument
+    fn number_arg(&mut self) -> Result<Option<usize>> {
+        if self.tokens.get(self.pos) != Some(&Token::Comma) {
+            return Ok(None);
+        }
+        self.pos += 1;
+        match self.next() {
+            Some(Token::Number(n)) => Ok(Some(*n)),
+            _ => Err(self.error())
+        }
+    }
+
+    fn field(&self, name: &str) -> Result<(Node, KeyType, usize)> {
+        let i = self.schema.index_of(name).ok_or_else(|| Error::Decode(format!("There's no field {} in index expression {}", name, self.expression)))?;
+        let field = &self.schema[i];
+        let key_type = match field.datatype {
+            b'C' | b'V' => KeyType::Character,
+            b'N' | b'F' | b'B' | b'Y' => KeyType::Numeric,
+            b'I' => KeyType::Integer,
+            b'D' => KeyType::Date,
+            b'T' => KeyType::DateTime,
+            b'L' => KeyType::Logical,
+            _ => return Err(self.error())
+        };
+        Ok((Node::Field(i), key_type, field.size))
+    }
+}
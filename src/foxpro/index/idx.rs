@@ -0,0 +1,201 @@
+use super::*;
+
+/// FoxPro single index file (.idx).
+///
+/// A single index file hold only one key expression. FoxPro 2.x writes compact index whose
+/// header and nodes are the same as a tag of [Cdx](struct.Cdx.html), starting at the beginning
+/// of file. FoxBASE and older FoxPro write standard index which has 512 bytes header and
+/// keys that aren't compressed. Both are read and written. Standard index is always ascending.
+///
+/// Its only tag is named after the file, like `ORDER` of FoxPro.
+pub struct Idx<R = File> {
+    source: R,
+    header: IndexHeader,
+    tag: Tag
+}
+
+impl Idx<File> {
+    /// Open single index file at given path.
+    /// The schema is the table that the index belongs to. It is used to parse key expression.
+    pub fn open<P: AsRef<std::path::Path>>(path: P, schema: &Schema) -> Result<Idx<File>> {
+        Idx::new(File::open(&path)?, &tag_name(path.as_ref()), schema)
+    }
+
+    /// Same as [open](struct.Idx.html#method.open) but the file is opened for writing as well.
+    pub fn open_rw<P: AsRef<std::path::Path>>(path: P, schema: &Schema) -> Result<Idx<File>> {
+        let f = std::fs::OpenOptions::new().read(true).write(true).open(&path)?;
+        Idx::new(f, &tag_name(path.as_ref()), schema)
+    }
+}
+
+/// Name of tag of index file at given path
+fn tag_name(path: &std::path::Path) -> String {
+    path.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default()
+}
+
+impl<R> Idx<R> where R: Read + Seek {
+    /// Read header from given source. The tag is given the name, in upper case.
+    pub fn new(mut source: R, name: &str, schema: &Schema) -> Result<Idx<R>> {
+        let mut bytes = [0u8; STANDARD_HEADER_LEN];
+        source.seek(SeekFrom::Start(0))?;
+        read_full(&mut source, &mut bytes)?;
+        let compact = bytes[14] & OPTION_COMPACT != 0;
+        let header = match compact {
+            true => read_header(&mut source, 0)?,
+            false => IndexHeader::parse_standard(&bytes)?
+        };
+        let tag = Tag::from_header(name.trim().to_uppercase(), header.clone(), 0, compact, schema);
+        Ok(Idx {
+            source,
+            header,
+            tag
+        })
+    }
+
+    /// Return true if it is compact index, otherwise it is standard index
+    pub fn is_compact(&self) -> bool {
+        self.tag.compact
+    }
+
+    /// The only tag of this index
+    pub fn tag(&self) -> &Tag {
+        &self.tag
+    }
+
+    /// Iterate every key in index order
+    pub fn entries(&mut self) -> Result<Entries<&mut R>> {
+        self.tag.entries(&mut self.source)
+    }
+
+    /// Record numbers of every key that starts with given key, in index order.
+    /// It is the same as `SEEK` with `SET EXACT OFF`.
+    pub fn seek(&mut self, key: &[u8]) -> Result<Vec<u32>> {
+        let entries = self.range(key.to_vec()..=key.to_vec())?;
+        Ok(entries.into_iter().map(|entry| entry.record).collect())
+    }
+
+    /// Keys that lie within given range, in index order.
+    /// Bounds are compared the same way as [Cdx::range](struct.Cdx.html#method.range).
+    pub fn range<B: RangeBounds<Vec<u8>>>(&mut self, range: B) -> Result<Vec<IndexEntry>> {
+        self.tag.range(&mut self.source, range)?.collect()
+    }
+
+    /// Same as [range](struct.Idx.html#method.range) but the index is consumed and
+    /// keys are read as they are taken.
+    pub fn into_range<B: RangeBounds<Vec<u8>>>(self, range: B) -> Result<RangeEntries<R>> {
+        self.tag.range(self.source, range)
+    }
+
+    /// Record numbers in index order. Record number starts at 1.
+    pub fn record_numbers(&mut self) -> Result<Vec<u32>> {
+        self.entries()?.map(|entry| entry.map(|entry| entry.record)).collect()
+    }
+
+    /// Take the underlying source out of this index
+    pub fn into_inner(self) -> R {
+        self.source
+    }
+}
+
+impl<R> Idx<R> where R: Read + Write + Seek {
+    /// Check that the index can be written
+    pub fn check_writable(&self) -> Result<()> {
+        self.tag.writable_key().map(|_| ())
+    }
+
+    /// Add key of given record. The key shall be as long as key of the index.
+    /// Unique index keeps only the first record of each key so key that is already in it is skipped.
+    pub fn insert(&mut self, key: &[u8], record: u32) -> Result<()> {
+        self.tag.check_key_len(key)?;
+        if self.tag.unique && !self.seek(key)?.is_empty() {
+            return Ok(());
+        }
+        let entry = IndexEntry {
+            key: key.to_vec(),
+            record
+        };
+        self.with_tree(|tree| tree.insert(entry))
+    }
+
+    /// Take key of given record out of the index.
+    /// It return false if the index doesn't have such key.
+    pub fn remove(&mut self, key: &[u8], record: u32) -> Result<bool> {
+        self.tag.check_key_len(key)?;
+        let entry = IndexEntry {
+            key: key.to_vec(),
+            record
+        };
+        self.with_tree(|tree| tree.remove(&entry))
+    }
+
+    /// Add key of a new record.
+    /// The bytes is the whole record, including deletion flag, laid out by given schema.
+    pub fn insert_record(&mut self, record: u32, bytes: &[u8], schema: &Schema) -> Result<()> {
+        let key = self.tag.writable_key()?.key(bytes, schema)?;
+        self.insert(&key, record)
+    }
+
+    /// Move record to its new key if the key expression reads any field that is changed.
+    ///
+    /// Unique index may have had the record as the only one of its old key while other records
+    /// share the key. Such key is returned so that the caller can
+    /// [insert](struct.Idx.html#method.insert) the first of those records.
+    pub fn update_record(&mut self, record: u32, old: &[u8], new: &[u8], schema: &Schema) -> Result<Option<Vec<u8>>> {
+        let (old_key, new_key) = match self.tag.changed_key(old, new, schema)? {
+            Some(keys) => keys,
+            None => return Ok(None)
+        };
+        let orphan = self.remove(&old_key, record)? && self.tag.unique;
+        self.insert(&new_key, record)?;
+        Ok(Some(old_key).filter(|_| orphan))
+    }
+
+    /// Rebuild the index from scratch out of given records, each one along with its record
+    /// number. The bytes is the whole record, including deletion flag, laid out by given schema.
+    ///
+    /// The whole file is rewritten and key length is taken from the key expression.
+    /// Space left after the new content, if the file used to be larger, is put into the free list.
+    pub fn reindex<I>(&mut self, records: I, schema: &Schema) -> Result<()> where I: IntoIterator<Item=(u32, Vec<u8>)> {
+        let records: Vec<(u32, Vec<u8>)> = records.into_iter().collect();
+        self.header.key_len = self.tag.refresh_key_len()?;
+        let keys = self.tag.sorted_keys(&records, schema)?;
+        let header_len = self.header_len();
+        let (root, nodes) = build_tree(keys, &self.tag.layout(), header_len as u32)?;
+        self.header.root = root;
+        self.header.free = free_after(&mut self.source, header_len + nodes.len())?;
+        self.tag.root = root;
+        write_at(&mut self.source, header_len as u32, &nodes)?;
+        self.write_header()
+    }
+
+    fn header_len(&self) -> usize {
+        match self.tag.compact {
+            true => HEADER_LEN,
+            false => STANDARD_HEADER_LEN
+        }
+    }
+
+    /// Standard index also keeps the end of file in its header
+    fn write_header(&mut self) -> Result<()> {
+        let bytes = match self.tag.compact {
+            true => self.header.to_bytes()?,
+            false => {
+                let end = self.source.seek(SeekFrom::End(0))?;
+                self.header.to_standard_bytes(to_offset(end as usize)?)?
+            }
+        };
+        write_at(&mut self.source, 0, &bytes)
+    }
+
+    /// Run given operation on the B-tree then write its new root and free list into the header
+    fn with_tree<F, T>(&mut self, op: F) -> Result<T> where F: FnOnce(&mut Tree<'_, R>) -> Result<T> {
+        let mut tree = Tree::new(&mut self.source, self.tag.root, self.header.free, self.tag.layout(), self.tag.descending);
+        let result = op(&mut tree);
+        let (root, free) = (tree.root, tree.free);
+        self.header.root = root;
+        self.header.free = free;
+        self.tag.root = root;
+        self.write_header()?;
+        result
+    }
+}
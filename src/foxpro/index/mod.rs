@@ -0,0 +1,808 @@
+use std::{
+    cmp::Ordering,
+    collections::VecDeque,
+    ops::{
+        Bound, RangeBounds
+    },
+    fs::File,
+    io::{
+        Read, Seek, SeekFrom, Write
+    }
+};
+
+use super::*;
+
+mod cdx;
+mod expr;
+mod idx;
+mod tree;
+
+pub use cdx::Cdx;
+pub use expr::{KeyExpr, KeyType};
+pub use idx::Idx;
+use tree::{build_tree, index_order, Tree};
+
+/// Size of index node in bytes
+pub(crate) const NODE_LEN: usize = 512;
+/// Size of compact index header, including the expression pool, in bytes
+pub(crate) const HEADER_LEN: usize = 1024;
+/// Size of standard index header in bytes
+pub(crate) const STANDARD_HEADER_LEN: usize = 512;
+/// Node pointer that point to nothing
+pub(crate) const NO_NODE: u32 = u32::MAX;
+/// Longest key that FoxPro allows in an index
+pub(crate) const MAX_KEY_LEN: usize = 240;
+
+/// A key in an index along with the record it belongs to.
+/// Record number starts at 1 like `RECNO()` in FoxPro.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IndexEntry {
+    pub key: Vec<u8>,
+    pub record: u32
+}
+
+/// How keys are laid out in nodes of an index
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Layout {
+    pub key_len: usize,
+    /// Byte that trailing bytes of compressed key are filled with
+    pub trail: u8,
+    /// Node of compact index, otherwise node of standard `.idx`
+    pub compact: bool
+}
+
+impl Layout {
+    /// Bytes that each key of interior node takes
+    fn interior_entry_len(&self) -> usize {
+        match self.compact {
+            true => self.key_len + 8,
+            false => self.key_len + 4
+        }
+    }
+}
+
+/// An index tag. A `.cdx` file has many of them while a `.idx` file is a single tag.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Tag {
+    /// Tag name in upper case. Tag of `.idx` file is named after the file.
+    pub name: String,
+    /// Key expression, e.g. `UPPER(NAME)`
+    pub expression: String,
+    /// FOR expression, only records that satisfy it are in the index
+    pub filter: Option<String>,
+    pub descending: bool,
+    pub unique: bool,
+    key_len: usize,
+    /// Offset of tag header
+    header: u32,
+    root: u32,
+    compact: bool,
+    key: Option<KeyExpr>
+}
+
+impl Tag {
+    fn from_header(name: String, header: IndexHeader, offset: u32, compact: bool, schema: &Schema) -> Tag {
+        Tag {
+            name,
+            key: KeyExpr::parse(&header.expression, schema).ok(),
+            expression: header.expression,
+            filter: header.filter,
+            descending: header.descending,
+            unique: header.options & OPTION_UNIQUE != 0,
+            key_len: header.key_len,
+            header: offset,
+            root: header.root,
+            compact
+        }
+    }
+
+    /// Length of key in bytes
+    pub fn key_len(&self) -> usize {
+        self.key_len
+    }
+
+    /// Parsed key expression.
+    /// It is `None` if the expression isn't supported by [KeyExpr](struct.KeyExpr.html).
+    pub fn key(&self) -> Option<&KeyExpr> {
+        self.key.as_ref()
+    }
+
+    /// Byte that trailing bytes of compressed key are filled with.
+    /// Tag whose expression isn't supported is assumed to be character.
+    fn trail(&self) -> u8 {
+        self.key.as_ref().map(|key| key.key_type()).unwrap_or(KeyType::Character).trail()
+    }
+
+    fn layout(&self) -> Layout {
+        Layout {
+            key_len: self.key_len,
+            trail: self.trail(),
+            compact: self.compact
+        }
+    }
+
+    /// Key expression of tag that can be kept up to date
+    fn writable_key(&self) -> Result<&KeyExpr> {
+        if self.filter.is_some() {
+            return Err(Error::Unsupported("updating index tag that has FOR clause"));
+        }
+        self.key.as_ref().ok_or(Error::Unsupported("updating index tag whose key expression isn't supported"))
+    }
+
+    fn check_key_len(&self, key: &[u8]) -> Result<()> {
+        match key.len() == self.key_len {
+            true => Ok(()),
+            false => Err(Error::Encode(format!("Key of tag {} shall be {} bytes but it is {} bytes", self.name, self.key_len, key.len())))
+        }
+    }
+
+    /// Take key length from the key expression that keys are built with.
+    /// Reindex uses it to repair a header whose key length no longer matches the expression,
+    /// e.g. after a field is resized.
+    fn refresh_key_len(&mut self) -> Result<usize> {
+        let key_len = self.writable_key()?.key_len();
+        if key_len > MAX_KEY_LEN {
+            return Err(Error::Overflow {
+                size: key_len,
+                max: MAX_KEY_LEN
+            });
+        }
+        self.key_len = key_len;
+        Ok(key_len)
+    }
+
+    fn entries<R: Read + Seek>(&self, source: R) -> Result<Entries<R>> {
+        Entries::new(source, self.root, self.layout())
+    }
+
+    fn range<R: Read + Seek, B: RangeBounds<Vec<u8>>>(&self, source: R, range: B) -> Result<RangeEntries<R>> {
+        let range = (range.start_bound().cloned(), range.end_bound().cloned());
+        let entries = Entries::starting_at(source, self.root, self.layout(), range_start(&range, self.descending), self.descending)?;
+        Ok(RangeEntries {
+            entries,
+            range,
+            descending: self.descending
+        })
+    }
+
+    /// Old and new key of a record whose fields read by the key expression are changed.
+    /// It return `None` if the key stays the same.
+    fn changed_key(&self, old: &[u8], new: &[u8], schema: &Schema) -> Result<Option<(Vec<u8>, Vec<u8>)>> {
+        let key = self.writable_key()?;
+        let changed = key.fields().into_iter()
+            .filter_map(|field| schema.fields().get(field))
+            .any(|field| old.get(field.offset..field.offset + field.size) != new.get(field.offset..field.offset + field.size));
+        if !changed {
+            return Ok(None);
+        }
+        let (old_key, new_key) = (key.key(old, schema)?, key.key(new, schema)?);
+        Ok(Some((old_key, new_key)).filter(|(old_key, new_key)| old_key != new_key))
+    }
+
+    /// Keys of given records in index order.
+    /// Unique tag keeps only the first record of each key.
+    fn sorted_keys(&self, records: &[(u32, Vec<u8>)], schema: &Schema) -> Result<Vec<IndexEntry>> {
+        let key = self.writable_key()?;
+        let mut keys = Vec::with_capacity(records.len());
+        for (record, bytes) in records {
+            let key = key.key(bytes, schema)?;
+            self.check_key_len(&key)?;
+            keys.push(IndexEntry {
+                key,
+                record: *record
+            });
+        }
+        keys.sort_by(|a, b| index_order(a, b, self.descending));
+        if self.unique {
+            keys.dedup_by(|next, first| next.key == first.key);
+        }
+        Ok(keys)
+    }
+}
+
+/// Header of an index. A `.cdx` file has one for the tag directory and one for each tag
+/// while a `.idx` file has one at the beginning, either compact or standard.
+///
+/// ## Header
+/// ---
+/// | Byte offset | Description |
+/// | --- | --- |
+/// | 0 - 3 | Offset of root node |
+/// | 4 - 7 | Offset of first free node, -1 if there's none |
+/// | 8 - 11 | Reserved |
+/// | 12 - 13 | Key length |
+/// | 14 | Index options:<br/>0x01 unique<br/>0x08 has FOR clause<br/>0x20 compact index<br/>0x40 compound index |
+/// | 15 | Index signature |
+/// | 16 - 501 | Reserved |
+/// | 502 - 503 | 0 ascending, 1 descending |
+/// | 504 - 505 | Reserved |
+/// | 506 - 507 | Length of FOR expression including the NUL terminator |
+/// | 508 - 509 | Reserved |
+/// | 510 - 511 | Length of key expression including the NUL terminator |
+/// | 512 - 1023 | Key expression followed by FOR expression, both terminated by NUL |
+/// ---
+///
+/// Every number is little endian.
+///
+/// ## Header of standard `.idx`
+/// ---
+/// | Byte offset | Description |
+/// | --- | --- |
+/// | 0 - 3 | Offset of root node |
+/// | 4 - 7 | Offset of first free node, -1 if there's none |
+/// | 8 - 11 | Offset of the end of file |
+/// | 12 - 13 | Key length |
+/// | 14 | Index options:<br/>0x01 unique<br/>0x08 has FOR clause |
+/// | 15 | Index signature |
+/// | 16 - 235 | Key expression terminated by NUL |
+/// | 236 - 455 | FOR expression terminated by NUL |
+/// | 456 - 511 | Reserved |
+/// ---
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct IndexHeader {
+    pub root: u32,
+    pub free: u32,
+    pub key_len: usize,
+    pub options: u8,
+    pub descending: bool,
+    pub expression: String,
+    pub filter: Option<String>
+}
+
+/// Index is unique
+pub(crate) const OPTION_UNIQUE: u8 = 0x01;
+/// Index is compact index
+pub(crate) const OPTION_COMPACT: u8 = 0x20;
+
+/// Room for each expression of standard index header
+const STANDARD_EXPRESSION_LEN: usize = 220;
+
+impl IndexHeader {
+    pub fn parse(bytes: &[u8]) -> Result<IndexHeader> {
+        let bytes = record_slice(bytes, 0, HEADER_LEN)?;
+        let key_len = u16::from_le_bytes(take_bytes(&bytes[12..14])?) as usize;
+        if key_len == 0 || key_len > NODE_LEN - 24 {
+            return Err(Error::BadHeader(format!("Index key length {} is out of range", key_len)));
+        }
+        let for_len = u16::from_le_bytes(take_bytes(&bytes[506..508])?) as usize;
+        let key_expr_len = u16::from_le_bytes(take_bytes(&bytes[510..512])?) as usize;
+        let pool = &bytes[512..];
+        let expression = expression_text(pool.get(..key_expr_len).unwrap_or(pool));
+        let filter = match pool.get(key_expr_len..key_expr_len + for_len) {
+            Some(filter) if for_len > 0 => Some(expression_text(filter)),
+            _ => None
+        };
+        Ok(IndexHeader {
+            root: u32::from_le_bytes(take_bytes(&bytes[0..4])?),
+            free: u32::from_le_bytes(take_bytes(&bytes[4..8])?),
+            key_len,
+            options: bytes[14],
+            descending: u16::from_le_bytes(take_bytes(&bytes[502..504])?) != 0,
+            expression,
+            filter: filter.filter(|f| !f.is_empty())
+        })
+    }
+
+    pub fn parse_standard(bytes: &[u8]) -> Result<IndexHeader> {
+        let bytes = record_slice(bytes, 0, STANDARD_HEADER_LEN)?;
+        let key_len = u16::from_le_bytes(take_bytes(&bytes[12..14])?) as usize;
+        if key_len == 0 || key_len > NODE_LEN - 16 {
+            return Err(Error::BadHeader(format!("Index key length {} is out of range", key_len)));
+        }
+        let filter = expression_text(&bytes[16 + STANDARD_EXPRESSION_LEN..16 + 2 * STANDARD_EXPRESSION_LEN]);
+        Ok(IndexHeader {
+            root: u32::from_le_bytes(take_bytes(&bytes[0..4])?),
+            free: u32::from_le_bytes(take_bytes(&bytes[4..8])?),
+            key_len,
+            options: bytes[14],
+            descending: false,
+            expression: expression_text(&bytes[16..16 + STANDARD_EXPRESSION_LEN]),
+            filter: Some(filter).filter(|f| !f.is_empty())
+        })
+    }
+
+    /// Bytes of standard index header that end at given offset
+    pub fn to_standard_bytes(&self, end: u32) -> Result<Vec<u8>> {
+        let mut bytes = vec![0u8; STANDARD_HEADER_LEN];
+        let expressions = [self.expression.as_bytes(), self.filter.as_deref().unwrap_or("").as_bytes()];
+        for (i, expression) in expressions.iter().enumerate() {
+            if expression.len() >= STANDARD_EXPRESSION_LEN {
+                return Err(Error::Overflow {
+                    size: expression.len() + 1,
+                    max: STANDARD_EXPRESSION_LEN
+                });
+            }
+            let start = 16 + i * STANDARD_EXPRESSION_LEN;
+            bytes[start..start + expression.len()].copy_from_slice(expression);
+        }
+        bytes[0..4].copy_from_slice(&self.root.to_le_bytes());
+        bytes[4..8].copy_from_slice(&self.free.to_le_bytes());
+        bytes[8..12].copy_from_slice(&end.to_le_bytes());
+        bytes[12..14].copy_from_slice(&(self.key_len as u16).to_le_bytes());
+        bytes[14] = self.options;
+        bytes[15] = 1;
+        Ok(bytes)
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let expression = self.expression.as_bytes();
+        let filter = self.filter.as_deref().unwrap_or("").as_bytes();
+        let key_expr_len = expression.len() + 1;
+        let for_len = if filter.is_empty() { 0 } else { filter.len() + 1 };
+        if key_expr_len + for_len > HEADER_LEN - 512 {
+            return Err(Error::Overflow {
+                size: key_expr_len + for_len,
+                max: HEADER_LEN - 512
+            });
+        }
+        let mut bytes = vec![0u8; HEADER_LEN];
+        bytes[0..4].copy_from_slice(&self.root.to_le_bytes());
+        bytes[4..8].copy_from_slice(&self.free.to_le_bytes());
+        bytes[12..14].copy_from_slice(&(self.key_len as u16).to_le_bytes());
+        bytes[14] = self.options;
+        bytes[15] = 1;
+        bytes[502..504].copy_from_slice(&(self.descending as u16).to_le_bytes());
+        bytes[506..508].copy_from_slice(&(for_len as u16).to_le_bytes());
+        bytes[510..512].copy_from_slice(&(key_expr_len as u16).to_le_bytes());
+        bytes[512..512 + expression.len()].copy_from_slice(expression);
+        bytes[512 + key_expr_len..512 + key_expr_len + filter.len()].copy_from_slice(filter);
+        Ok(bytes)
+    }
+}
+
+/// Text of expression up to the NUL terminator
+fn expression_text(bytes: &[u8]) -> String {
+    let end = bytes.iter().position(|b| *b == 0).unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..end]).trim().to_owned()
+}
+
+/// A node of compact index.
+///
+/// ## Node
+/// ---
+/// | Byte offset | Description |
+/// | --- | --- |
+/// | 0 - 1 | Node attributes:<br/>0 interior node<br/>1 root node<br/>2 leaf node |
+/// | 2 - 3 | Number of keys |
+/// | 4 - 7 | Offset of node to the left, -1 if there's none |
+/// | 8 - 11 | Offset of node to the right, -1 if there's none |
+/// | 12 - 511 | Keys |
+/// ---
+///
+/// Interior node has each key followed by its record number and the offset of child node
+/// that the key is the largest of. Both numbers are big endian.
+///
+/// ## Leaf node keys
+/// ---
+/// | Byte offset | Description |
+/// | --- | --- |
+/// | 12 - 13 | Free space in bytes |
+/// | 14 - 17 | Record number mask |
+/// | 18 | Duplicate count mask |
+/// | 19 | Trailing count mask |
+/// | 20 | Number of bits of record number |
+/// | 21 | Number of bits of duplicate count |
+/// | 22 | Number of bits of trailing count |
+/// | 23 | Number of bytes of each entry |
+/// | 24 - 511 | Entries followed by free space then keys |
+/// ---
+///
+/// Each entry pack record number, duplicate count and trailing count, from the least
+/// significant bit, into a little endian number. Key is compressed by dropping the bytes
+/// it share with the previous key, the duplicate count, and its trailing spaces, or zero
+/// for non-character key, the trailing count. The rest of the key are stored from the end
+/// of node toward the beginning.
+///
+/// ## Standard node keys
+/// Node of standard `.idx` has the same first 12 bytes. Each key is followed by a big endian
+/// number, the record number in leaf node or the offset of child node in interior node.
+/// Keys aren't compressed.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Node {
+    pub attributes: u16,
+    pub left: u32,
+    pub right: u32,
+    pub entries: Vec<IndexEntry>,
+    /// Child node of each entry of interior node
+    pub children: Vec<u32>
+}
+
+/// Record number of interior key of standard index, which doesn't store one
+pub(crate) const UNKNOWN_RECORD: u32 = u32::MAX;
+
+/// Node is the root
+pub(crate) const NODE_ROOT: u16 = 0x01;
+/// Node is a leaf
+pub(crate) const NODE_LEAF: u16 = 0x02;
+
+impl Node {
+    pub fn parse(bytes: &[u8], layout: &Layout) -> Result<Node> {
+        let Layout { key_len, trail, compact } = *layout;
+        let bytes = record_slice(bytes, 0, NODE_LEN)?;
+        let attributes = u16::from_le_bytes(take_bytes(&bytes[0..2])?);
+        let count = u16::from_le_bytes(take_bytes(&bytes[2..4])?) as usize;
+        let left = u32::from_le_bytes(take_bytes(&bytes[4..8])?);
+        let right = u32::from_le_bytes(take_bytes(&bytes[8..12])?);
+        let mut entries = Vec::with_capacity(count);
+        let mut children = Vec::new();
+        let bad_node = |reason: &str| Error::Decode(format!("Bad index node: {}", reason));
+
+        if !compact || attributes & NODE_LEAF == 0 {
+            let entry_len = layout.interior_entry_len();
+            if 12 + count * entry_len > NODE_LEN {
+                return Err(bad_node("too many keys"));
+            }
+            let leaf = attributes & NODE_LEAF != 0;
+            for entry in bytes[12..12 + count * entry_len].chunks(entry_len) {
+                let number = u32::from_be_bytes(take_bytes(&entry[key_len..])?);
+                let (record, child) = match (compact, leaf) {
+                    (true, _) => (number, Some(u32::from_be_bytes(take_bytes(&entry[key_len + 4..])?))),
+                    (false, true) => (number, None),
+                    (false, false) => (UNKNOWN_RECORD, Some(number))
+                };
+                entries.push(IndexEntry {
+                    key: entry[..key_len].to_vec(),
+                    record
+                });
+                children.extend(child);
+            }
+        } else {
+            let record_mask = u32::from_le_bytes(take_bytes(&bytes[14..18])?) as u64;
+            let (dup_mask, trail_mask) = (bytes[18] as u64, bytes[19] as u64);
+            let (record_bits, dup_bits, trail_bits) = (bytes[20] as u32, bytes[21] as u32, bytes[22] as u32);
+            let entry_len = bytes[23] as usize;
+            if entry_len == 0 || entry_len > 8 || 24 + count * entry_len > NODE_LEN {
+                return Err(bad_node("bad entry length"));
+            }
+            // bit counts are used as shift amounts so they must fit in an entry
+            if record_bits > 32 || dup_bits > 32 || trail_bits > 32 || record_bits + dup_bits + trail_bits > entry_len as u32 * 8 {
+                return Err(bad_node("bad bit counts"));
+            }
+            let mut key = vec![trail; key_len];
+            let mut end = NODE_LEN;
+            for entry in bytes[24..24 + count * entry_len].chunks(entry_len) {
+                let mut packed = [0u8; 8];
+                packed[..entry_len].copy_from_slice(entry);
+                let packed = u64::from_le_bytes(packed);
+                let dup = (packed.checked_shr(record_bits).unwrap_or(0) & dup_mask) as usize;
+                let trailing = (packed.checked_shr(record_bits + dup_bits).unwrap_or(0) & trail_mask) as usize;
+                let stored = key_len.checked_sub(dup + trailing).ok_or_else(|| bad_node("key is longer than key length"))?;
+                let start = end.checked_sub(stored).filter(|start| *start >= 24 + count * entry_len).ok_or_else(|| bad_node("keys overlap entries"))?;
+                key[dup..dup + stored].copy_from_slice(&bytes[start..end]);
+                key[dup + stored..].iter_mut().for_each(|b| *b = trail);
+                end = start;
+                entries.push(IndexEntry {
+                    key: key.clone(),
+                    record: (packed & record_mask) as u32
+                });
+            }
+        }
+
+        Ok(Node {
+            attributes,
+            left,
+            right,
+            entries,
+            children
+        })
+    }
+
+    pub fn is_leaf(&self) -> bool {
+        self.attributes & NODE_LEAF != 0
+    }
+
+    /// Bytes of this node. Every key shall be `key_len` bytes long.
+    /// It return `None` if the entries don't fit in a node.
+    pub fn to_bytes(&self, layout: &Layout) -> Option<Vec<u8>> {
+        let Layout { key_len, trail, compact } = *layout;
+        let mut bytes = vec![0u8; NODE_LEN];
+        bytes[0..2].copy_from_slice(&self.attributes.to_le_bytes());
+        bytes[2..4].copy_from_slice(&(self.entries.len() as u16).to_le_bytes());
+        bytes[4..8].copy_from_slice(&self.left.to_le_bytes());
+        bytes[8..12].copy_from_slice(&self.right.to_le_bytes());
+
+        if !compact || !self.is_leaf() {
+            let entry_len = layout.interior_entry_len();
+            if 12 + self.entries.len() * entry_len > NODE_LEN {
+                return None;
+            }
+            for (i, entry) in self.entries.iter().enumerate() {
+                let start = 12 + i * entry_len;
+                let mut numbers = Vec::with_capacity(8);
+                if compact || self.is_leaf() {
+                    numbers.extend(&entry.record.to_be_bytes());
+                }
+                if !self.is_leaf() {
+                    numbers.extend(&self.children[i].to_be_bytes());
+                }
+                bytes[start..start + key_len].copy_from_slice(&entry.key);
+                bytes[start + key_len..start + entry_len].copy_from_slice(&numbers);
+            }
+            return Some(bytes);
+        }
+
+        let format = LeafFormat::new(self.entries.iter().map(|entry| entry.record).max().unwrap_or(0), key_len);
+        let entries_end = 24 + self.entries.len() * format.entry_len;
+        let mut end = NODE_LEN;
+        let mut previous: &[u8] = &[];
+        for (i, entry) in self.entries.iter().enumerate() {
+            let (dup, trailing) = format.compress(previous, &entry.key, trail);
+            let stored = &entry.key[dup..key_len - trailing];
+            end = end.checked_sub(stored.len()).filter(|end| *end >= entries_end)?;
+            bytes[end..end + stored.len()].copy_from_slice(stored);
+            let packed = entry.record as u64 | (dup as u64) << format.record_bits | (trailing as u64) << (format.record_bits + format.count_bits);
+            let start = 24 + i * format.entry_len;
+            bytes[start..start + format.entry_len].copy_from_slice(&packed.to_le_bytes()[..format.entry_len]);
+            previous = &entry.key;
+        }
+        bytes[12..14].copy_from_slice(&((end - entries_end) as u16).to_le_bytes());
+        bytes[14..18].copy_from_slice(&format.record_mask().to_le_bytes());
+        bytes[18] = format.count_mask() as u8;
+        bytes[19] = format.count_mask() as u8;
+        bytes[20] = format.record_bits as u8;
+        bytes[21] = format.count_bits as u8;
+        bytes[22] = format.count_bits as u8;
+        bytes[23] = format.entry_len as u8;
+        Some(bytes)
+    }
+}
+
+/// Layout of entries of compact leaf node.
+/// Duplicate count and trailing count have the same number of bits.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct LeafFormat {
+    pub record_bits: u32,
+    pub count_bits: u32,
+    pub entry_len: usize
+}
+
+impl LeafFormat {
+    /// The smallest entries that can hold given record number and counts up to key length.
+    /// Record number takes every bit that is left in the last byte.
+    pub fn new(max_record: u32, key_len: usize) -> LeafFormat {
+        let bits = |value: u64| 64 - value.leading_zeros();
+        // the mask of both counts is a byte
+        let count_bits = bits(key_len as u64).min(8);
+        let record_bits = bits(max_record as u64).max(8);
+        let entry_len = ((record_bits + 2 * count_bits) as usize).div_ceil(8);
+        LeafFormat {
+            record_bits: (entry_len as u32 * 8 - 2 * count_bits).min(32),
+            count_bits,
+            entry_len
+        }
+    }
+
+    pub fn record_mask(&self) -> u32 {
+        (u32::MAX as u64 >> (32 - self.record_bits)) as u32
+    }
+
+    pub fn count_mask(&self) -> usize {
+        (1 << self.count_bits) - 1
+    }
+
+    /// Duplicate count and trailing count of key that follow `previous` key
+    pub fn compress(&self, previous: &[u8], key: &[u8], trail: u8) -> (usize, usize) {
+        let trailing = key.iter().rev().take_while(|b| **b == trail).count().min(self.count_mask());
+        let dup = previous.iter().zip(key.iter())
+            .take_while(|(a, b)| a == b)
+            .count()
+            .min(key.len() - trailing)
+            .min(self.count_mask());
+        (dup, trailing)
+    }
+
+    /// Bytes that key that follow `previous` key take in a leaf node, including its entry
+    pub fn stored_len(&self, previous: &[u8], key: &[u8], trail: u8) -> usize {
+        let (dup, trailing) = self.compress(previous, key, trail);
+        self.entry_len + key.len() - dup - trailing
+    }
+}
+
+/// Read a compact node at given offset
+pub(crate) fn read_node<R: Read + Seek>(source: &mut R, offset: u32, layout: &Layout) -> Result<Node> {
+    let mut bytes = [0u8; NODE_LEN];
+    source.seek(SeekFrom::Start(offset as u64))?;
+    read_full(source, &mut bytes)?;
+    Node::parse(&bytes, layout)
+}
+
+/// Read compact index header at given offset
+fn read_header<R: Read + Seek>(source: &mut R, offset: u32) -> Result<IndexHeader> {
+    let mut bytes = [0u8; HEADER_LEN];
+    source.seek(SeekFrom::Start(offset as u64))?;
+    read_full(source, &mut bytes)?;
+    IndexHeader::parse(&bytes)
+}
+
+/// Offset of content at given position of index file
+fn to_offset(position: usize) -> Result<u32> {
+    u32::try_from(position).map_err(|_| Error::Overflow {
+        size: position,
+        max: u32::MAX as usize
+    })
+}
+
+/// Put every node after given end of content, left from a larger file, into a new free list.
+/// It return the first node of the list.
+fn free_after<R: Write + Seek>(source: &mut R, end: usize) -> Result<u32> {
+    let mut free = NO_NODE;
+    let mut unused = source.seek(SeekFrom::End(0))? / NODE_LEN as u64 * NODE_LEN as u64;
+    while unused >= (end + NODE_LEN) as u64 {
+        unused -= NODE_LEN as u64;
+        let mut node = [0u8; NODE_LEN];
+        node[0..4].copy_from_slice(&free.to_le_bytes());
+        write_at(source, unused as u32, &node)?;
+        free = unused as u32;
+    }
+    Ok(free)
+}
+
+/// Write given bytes at given offset
+pub(crate) fn write_at<W: Write + Seek>(source: &mut W, offset: u32, bytes: &[u8]) -> Result<()> {
+    source.seek(SeekFrom::Start(offset as u64))?;
+    source.write_all(bytes)?;
+    Ok(())
+}
+
+/// Iterator over keys of an index in index order.
+///
+/// It goes down to the leaf that has the first key then follow each leaf to the one on its right.
+/// Only one leaf is kept in memory at any time.
+pub struct Entries<R> {
+    source: R,
+    layout: Layout,
+    pending: VecDeque<IndexEntry>,
+    next: Option<u32>,
+    /// Number of nodes that can be read before the index is considered broken, e.g. a loop
+    remaining_nodes: u64
+}
+
+/// Return true if `entry` come before `key` in index order.
+/// Only the first `key.len()` bytes of the entry is compared so shorter key match every
+/// entry that starts with it.
+pub(crate) fn is_before(entry: &[u8], key: &[u8], descending: bool) -> bool {
+    match descending {
+        false => compare_prefix(entry, key) == Ordering::Less,
+        true => compare_prefix(entry, key) == Ordering::Greater
+    }
+}
+
+/// Compare first `key.len()` bytes of the entry with the key
+fn compare_prefix(entry: &[u8], key: &[u8]) -> Ordering {
+    entry[..entry.len().min(key.len())].cmp(key)
+}
+
+impl<R> Entries<R> where R: Read + Seek {
+    /// Start at the first key of the index
+    pub(crate) fn new(source: R, root: u32, layout: Layout) -> Result<Entries<R>> {
+        Entries::starting_at(source, root, layout, &[], false)
+    }
+
+    /// Start at the first key that doesn't come before given key in index order.
+    /// Interior node key is the last key of its child so the child to go down to is
+    /// the first one whose key doesn't come before given key.
+    pub(crate) fn starting_at(mut source: R, root: u32, layout: Layout, key: &[u8], descending: bool) -> Result<Entries<R>> {
+        let remaining_nodes = source.seek(SeekFrom::End(0))? / NODE_LEN as u64;
+        let mut entries = Entries {
+            source,
+            layout,
+            pending: VecDeque::new(),
+            next: None,
+            remaining_nodes
+        };
+        let mut offset = root;
+        loop {
+            let node = entries.read_node(offset)?;
+            if node.is_leaf() {
+                entries.take_leaf(node);
+                while entries.pending.front().map(|entry| is_before(&entry.key, key, descending)) == Some(true) {
+                    entries.pending.pop_front();
+                }
+                return Ok(entries);
+            }
+            match node.entries.iter().position(|entry| !is_before(&entry.key, key, descending)) {
+                Some(i) => offset = node.children[i],
+                // every key come before given key, including interior node without key
+                None => return Ok(entries)
+            }
+        }
+    }
+
+    fn read_node(&mut self, offset: u32) -> Result<Node> {
+        if self.remaining_nodes == 0 {
+            return Err(Error::Decode("Index nodes form a loop".to_owned()));
+        }
+        self.remaining_nodes -= 1;
+        read_node(&mut self.source, offset, &self.layout)
+    }
+
+    fn take_leaf(&mut self, node: Node) {
+        self.next = Some(node.right).filter(|right| *right != NO_NODE);
+        self.pending.extend(node.entries);
+    }
+}
+
+impl<R> Iterator for Entries<R> where R: Read + Seek {
+    type Item = Result<IndexEntry>;
+
+    fn next(&mut self) -> Option<Result<IndexEntry>> {
+        while self.pending.is_empty() {
+            let offset = self.next.take()?;
+            match self.read_node(offset) {
+                Ok(node) if node.is_leaf() => self.take_leaf(node),
+                Ok(_) => return Some(Err(Error::Decode(format!("Index node at {} is not a leaf", offset)))),
+                Err(err) => return Some(Err(err))
+            }
+        }
+        self.pending.pop_front().map(Ok)
+    }
+}
+
+/// Key where entries of given range start in index order.
+/// It is the lower bound of ascending index or upper bound of descending index.
+pub(crate) fn range_start<B: RangeBounds<Vec<u8>>>(range: &B, descending: bool) -> &[u8] {
+    let bound = match descending {
+        false => range.start_bound(),
+        true => range.end_bound()
+    };
+    match bound {
+        Bound::Included(key) | Bound::Excluded(key) => key,
+        Bound::Unbounded => &[]
+    }
+}
+
+/// Iterator over keys of an index that lie within a range, in index order.
+///
+/// Entries start at [range_start](fn.range_start.html) and it stops at the first key past
+/// the range so leaves are read only as keys are taken. Bound is compared with the first
+/// bytes of key like [is_before](fn.is_before.html).
+pub struct RangeEntries<R> {
+    entries: Entries<R>,
+    range: KeyBounds,
+    descending: bool
+}
+
+impl<R> RangeEntries<R> {
+    fn below(&self, key: &[u8]) -> bool {
+        match self.range.start_bound() {
+            Bound::Included(start) => compare_prefix(key, start) == Ordering::Less,
+            Bound::Excluded(start) => compare_prefix(key, start) != Ordering::Greater,
+            Bound::Unbounded => false
+        }
+    }
+
+    fn above(&self, key: &[u8]) -> bool {
+        match self.range.end_bound() {
+            Bound::Included(end) => compare_prefix(key, end) == Ordering::Greater,
+            Bound::Excluded(end) => compare_prefix(key, end) != Ordering::Less,
+            Bound::Unbounded => false
+        }
+    }
+}
+
+impl<R> Iterator for RangeEntries<R> where R: Read + Seek {
+    type Item = Result<IndexEntry>;
+
+    fn next(&mut self) -> Option<Result<IndexEntry>> {
+        loop {
+            let entry = match self.entries.next()? {
+                Ok(entry) => entry,
+                Err(err) => return Some(Err(err))
+            };
+            // entries start from the lower bound of ascending index or upper bound of descending one
+            let (before, after) = match self.descending {
+                false => (self.below(&entry.key), self.above(&entry.key)),
+                true => (self.above(&entry.key), self.below(&entry.key))
+            };
+            if after {
+                // nothing after it is in range
+                self.entries.pending.clear();
+                self.entries.next = None;
+                return None;
+            }
+            if !before {
+                return Some(Ok(entry));
+            }
+        }
+    }
+}
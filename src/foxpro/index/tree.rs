@@ -0,0 +1,359 @@
+use super::*;
+
+/// Deepest tree that is considered sane. Anything deeper is a loop.
+const MAX_DEPTH: usize = 64;
+
+/// B-tree of a compact index opened for writing.
+///
+/// Entries are ordered by [index_order](fn.index_order.html). Separator key of interior
+/// node is the last entry of its child.
+///
+/// Nodes that are no longer used are put into the free list of the file. The first 4 bytes
+/// of free node, little endian, is the offset of the next free node or -1 at the end of list.
+/// New node is taken from the free list before the file is extended.
+pub(crate) struct Tree<'a, R> {
+    source: &'a mut R,
+    /// Offset of root node. It changes when the root is split.
+    pub root: u32,
+    /// Offset of the first free node of the file
+    pub free: u32,
+    layout: Layout,
+    descending: bool
+}
+
+/// Interior nodes from the root down to a leaf, with the index of child taken in each of them
+type Path = Vec<(u32, Node, usize)>;
+
+impl<'a, R> Tree<'a, R> where R: Read + Write + Seek {
+    pub fn new(source: &'a mut R, root: u32, free: u32, layout: Layout, descending: bool) -> Tree<'a, R> {
+        Tree {
+            source,
+            root,
+            free,
+            layout,
+            descending
+        }
+    }
+
+    fn order(&self, a: &IndexEntry, b: &IndexEntry) -> Ordering {
+        index_order(a, b, self.descending)
+    }
+
+    /// Put an entry into its place, splitting every node that become too big.
+    /// Key shall be `key_len` bytes long.
+    pub fn insert(&mut self, entry: IndexEntry) -> Result<()> {
+        let (mut path, mut offset, mut node) = self.descend(&entry)?;
+        let at = node.entries.partition_point(|e| self.order(e, &entry) == Ordering::Less);
+        node.entries.insert(at, entry);
+        loop {
+            let pieces = self.store(offset, node)?;
+            match path.pop() {
+                Some((parent_offset, parent, i)) => match self.update_parent(parent, i, &pieces) {
+                    Some(parent) => {
+                        offset = parent_offset;
+                        node = parent;
+                    },
+                    None => return Ok(())
+                },
+                None => return self.grow(&pieces)
+            }
+        }
+    }
+
+    /// Take an entry out of the tree. Node that become empty is unlinked from its siblings
+    /// and put into the free list.
+    /// It return false if there's no such entry.
+    pub fn remove(&mut self, entry: &IndexEntry) -> Result<bool> {
+        let mut path = Vec::new();
+        let (mut offset, mut node) = match self.find(self.root, entry, &mut path)? {
+            Some(found) => found,
+            None => return Ok(false)
+        };
+        node.entries.retain(|e| e != entry);
+        loop {
+            let (parent_offset, mut parent, i) = match path.pop() {
+                Some(parent) => parent,
+                None => {
+                    if node.entries.is_empty() {
+                        node.attributes = NODE_ROOT | NODE_LEAF;
+                        node.children.clear();
+                    }
+                    let pieces = self.store(offset, node)?;
+                    self.grow(&pieces)?;
+                    return Ok(true);
+                }
+            };
+            if node.entries.is_empty() {
+                self.unlink(&node)?;
+                self.release(offset)?;
+                parent.entries.remove(i);
+                parent.children.remove(i);
+            } else {
+                // dropping a key may leave the next one with less in common with its previous key
+                let pieces = self.store(offset, node)?;
+                parent = match self.update_parent(parent, i, &pieces) {
+                    Some(parent) => parent,
+                    None => return Ok(true)
+                };
+            }
+            offset = parent_offset;
+            node = parent;
+        }
+    }
+
+    /// Go down to the leaf that the entry belongs to.
+    /// Entry that come after every key belongs to the last leaf.
+    fn descend(&mut self, entry: &IndexEntry) -> Result<(Path, u32, Node)> {
+        let mut path = Vec::new();
+        let mut offset = self.root;
+        loop {
+            let node = self.read(offset)?;
+            if node.is_leaf() {
+                return Ok((path, offset, node));
+            }
+            if node.children.is_empty() || path.len() >= MAX_DEPTH {
+                return Err(Error::Decode(format!("Bad index node at {}", offset)));
+            }
+            let i = node.entries.iter()
+                .position(|e| self.order(e, entry) != Ordering::Less)
+                .unwrap_or(node.children.len() - 1);
+            let child = node.children[i];
+            path.push((offset, node, i));
+            offset = child;
+        }
+    }
+
+    /// Find the leaf that has given entry, filling the path to it.
+    /// Keys that are the same as the entry may span many children, especially in standard
+    /// index which has no record number in interior node, so each of them is looked into.
+    fn find(&mut self, offset: u32, entry: &IndexEntry, path: &mut Path) -> Result<Option<(u32, Node)>> {
+        let node = self.read(offset)?;
+        if node.is_leaf() {
+            return Ok(Some((offset, node)).filter(|(_, node)| node.entries.contains(entry)));
+        }
+        if path.len() >= MAX_DEPTH {
+            return Err(Error::Decode(format!("Bad index node at {}", offset)));
+        }
+        let start = match node.entries.iter().position(|e| self.order(e, entry) != Ordering::Less) {
+            Some(start) => start,
+            None => return Ok(None)
+        };
+        for i in start..node.children.len() {
+            if i > start && node.entries[i - 1].key != entry.key {
+                break;
+            }
+            path.push((offset, node.clone(), i));
+            if let Some(found) = self.find(node.children[i], entry, path)? {
+                return Ok(Some(found));
+            }
+            path.pop();
+        }
+        Ok(None)
+    }
+
+    /// Point entry `i` of parent to given nodes which replace its child.
+    /// It return `None` if the parent is left as is.
+    fn update_parent(&self, mut parent: Node, i: usize, pieces: &[(IndexEntry, u32)]) -> Option<Node> {
+        if let [(last, _)] = pieces {
+            let same = match self.layout.compact {
+                true => parent.entries[i] == *last,
+                false => parent.entries[i].key == last.key
+            };
+            if same {
+                return None;
+            }
+        }
+        parent.entries.splice(i..=i, pieces.iter().map(|(last, _)| last.clone()));
+        parent.children.splice(i..=i, pieces.iter().map(|(_, child)| *child));
+        Some(parent)
+    }
+
+    /// Put a new root above the old one if it is split
+    fn grow(&mut self, pieces: &[(IndexEntry, u32)]) -> Result<()> {
+        if pieces.len() < 2 {
+            return Ok(());
+        }
+        let root = Node {
+            attributes: NODE_ROOT,
+            left: NO_NODE,
+            right: NO_NODE,
+            entries: pieces.iter().map(|(last, _)| last.clone()).collect(),
+            children: pieces.iter().map(|(_, child)| *child).collect()
+        };
+        let offset = self.allocate()?;
+        self.write(offset, &root)?;
+        self.root = offset;
+        Ok(())
+    }
+
+    /// Write a node at given offset. Node that doesn't fit is split in half, the right half
+    /// goes to a new node.
+    /// It return the last entry and offset of each resulting node that has any entry.
+    fn store(&mut self, offset: u32, mut node: Node) -> Result<Vec<(IndexEntry, u32)>> {
+        if let Some(bytes) = node.to_bytes(&self.layout) {
+            write_at(self.source, offset, &bytes)?;
+            return Ok(node.entries.last().map(|last| (last.clone(), offset)).into_iter().collect());
+        }
+
+        if node.entries.len() < 2 {
+            return Err(Error::Encode(format!("Index key of node at {} is too long", offset)));
+        }
+        let mid = node.entries.len() / 2;
+        let right_offset = self.allocate()?;
+        node.attributes &= !NODE_ROOT;
+        let right = Node {
+            attributes: node.attributes,
+            left: offset,
+            right: node.right,
+            entries: node.entries.split_off(mid),
+            children: match node.is_leaf() {
+                true => Vec::new(),
+                false => node.children.split_off(mid)
+            }
+        };
+        if right.right != NO_NODE {
+            self.relink(right.right, |next| next.left = right_offset)?;
+        }
+        node.right = right_offset;
+        self.write(offset, &node)?;
+        self.write(right_offset, &right)?;
+        Ok(vec![
+            (node.entries[mid - 1].clone(), offset),
+            (right.entries[right.entries.len() - 1].clone(), right_offset)
+        ])
+    }
+
+    /// Point siblings of given node to each other
+    fn unlink(&mut self, node: &Node) -> Result<()> {
+        let (left, right) = (node.left, node.right);
+        if left != NO_NODE {
+            self.relink(left, |previous| previous.right = right)?;
+        }
+        if right != NO_NODE {
+            self.relink(right, |next| next.left = left)?;
+        }
+        Ok(())
+    }
+
+    fn relink<F: FnOnce(&mut Node)>(&mut self, offset: u32, op: F) -> Result<()> {
+        let mut node = self.read(offset)?;
+        op(&mut node);
+        self.write(offset, &node)
+    }
+
+    fn read(&mut self, offset: u32) -> Result<Node> {
+        read_node(self.source, offset, &self.layout)
+    }
+
+    fn write(&mut self, offset: u32, node: &Node) -> Result<()> {
+        let bytes = node.to_bytes(&self.layout).ok_or_else(|| Error::Encode(format!("Index node at {} is too big", offset)))?;
+        write_at(self.source, offset, &bytes)
+    }
+
+    /// Take a node from the free list or add one at the end of file
+    fn allocate(&mut self) -> Result<u32> {
+        if self.free != NO_NODE {
+            let offset = self.free;
+            let mut next = [0u8; 4];
+            self.source.seek(SeekFrom::Start(offset as u64))?;
+            read_full(self.source, &mut next)?;
+            self.free = u32::from_le_bytes(next);
+            return Ok(offset);
+        }
+        let end = self.source.seek(SeekFrom::End(0))?.div_ceil(NODE_LEN as u64) * NODE_LEN as u64;
+        let offset = u32::try_from(end).map_err(|_| Error::Overflow {
+            size: end as usize,
+            max: u32::MAX as usize
+        })?;
+        // reserve the node so that the next allocation doesn't take it
+        write_at(self.source, offset, &[0u8; NODE_LEN])?;
+        Ok(offset)
+    }
+
+    /// Put a node into the free list
+    fn release(&mut self, offset: u32) -> Result<()> {
+        let mut bytes = [0u8; NODE_LEN];
+        bytes[0..4].copy_from_slice(&self.free.to_le_bytes());
+        write_at(self.source, offset, &bytes)?;
+        self.free = offset;
+        Ok(())
+    }
+}
+
+/// Order of entries in a tree. Entries are ordered by key then record number so every
+/// entry, even with duplicate key, has its own place.
+pub(crate) fn index_order(a: &IndexEntry, b: &IndexEntry, descending: bool) -> Ordering {
+    let keys = match descending {
+        false => a.key.cmp(&b.key),
+        true => b.key.cmp(&a.key)
+    };
+    keys.then(a.record.cmp(&b.record))
+}
+
+/// Nodes of a new B-tree that hold given entries, which are already in index order.
+/// The nodes are laid out one after another starting at `base`.
+/// It return the offset of root node along with bytes of every node.
+pub(crate) fn build_tree(entries: Vec<IndexEntry>, layout: &Layout, base: u32) -> Result<(u32, Vec<u8>)> {
+    let format = LeafFormat::new(entries.iter().map(|entry| entry.record).max().unwrap_or(0), layout.key_len);
+    // standard leaf has neither compression nor the leaf header
+    let room = if layout.compact { NODE_LEN - 24 } else { NODE_LEN - 12 };
+    let size_of = |previous: &[u8], key: &[u8]| match layout.compact {
+        true => format.stored_len(previous, key, layout.trail),
+        false => layout.interior_entry_len()
+    };
+    let mut nodes = Vec::new();
+    let mut level = Vec::new();
+    let mut leaf: Vec<IndexEntry> = Vec::new();
+    let mut used = 0;
+    let new_node = |attributes, entries, children| Node {
+        attributes,
+        left: NO_NODE,
+        right: NO_NODE,
+        entries,
+        children
+    };
+    for entry in entries {
+        let previous = leaf.last().map(|previous| &previous.key[..]).unwrap_or(&[]);
+        let mut size = size_of(previous, &entry.key);
+        if !leaf.is_empty() && used + size > room {
+            level.push(nodes.len());
+            nodes.push(new_node(NODE_LEAF, std::mem::take(&mut leaf), Vec::new()));
+            size = size_of(&[], &entry.key);
+            used = 0;
+        }
+        used += size;
+        leaf.push(entry);
+    }
+    if !leaf.is_empty() || level.is_empty() {
+        level.push(nodes.len());
+        nodes.push(new_node(NODE_LEAF, leaf, Vec::new()));
+    }
+
+    let offset_of = |i: usize| base + (i * NODE_LEN) as u32;
+    let capacity = (NODE_LEN - 12) / layout.interior_entry_len();
+    loop {
+        for (at, i) in level.iter().enumerate() {
+            nodes[*i].left = match at {
+                0 => NO_NODE,
+                _ => offset_of(level[at - 1])
+            };
+            nodes[*i].right = level.get(at + 1).map(|next| offset_of(*next)).unwrap_or(NO_NODE);
+        }
+        if let [root] = level[..] {
+            nodes[root].attributes |= NODE_ROOT;
+            let mut bytes = Vec::with_capacity(nodes.len() * NODE_LEN);
+            for node in nodes.iter() {
+                bytes.extend(node.to_bytes(layout).ok_or_else(|| Error::Encode("Index key is too long".to_owned()))?);
+            }
+            return Ok((offset_of(root), bytes));
+        }
+        let mut upper = Vec::new();
+        for children in level.chunks(capacity) {
+            let entries = children.iter().filter_map(|i| nodes[*i].entries.last().cloned()).collect();
+            let children = children.iter().map(|i| offset_of(*i)).collect();
+            upper.push(nodes.len());
+            nodes.push(new_node(0, entries, children));
+        }
+        level = upper;
+    }
+}
@@ -0,0 +1,344 @@
+use std::{
+    convert::TryFrom,
+    fs::File,
+    io::{
+        Read, Seek, SeekFrom, Write
+    }
+};
+
+use super::*;
+
+/// Type of content stored in memo block of FoxPro memo file.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MemoType {
+    /// Binary content of `G` and `P` field
+    Picture,
+    /// Text content of `M` field
+    Text,
+    /// Any other block signature
+    Other(u32)
+}
+
+impl MemoType {
+    pub fn parse_type(signature: u32) -> MemoType {
+        match signature {
+            0 => MemoType::Picture,
+            1 => MemoType::Text,
+            _ => MemoType::Other(signature)
+        }
+    }
+
+    /// Block signature as stored in memo block header
+    pub fn signature(&self) -> u32 {
+        match self {
+            MemoType::Picture => 0,
+            MemoType::Text => 1,
+            MemoType::Other(signature) => *signature
+        }
+    }
+}
+
+/// Size of memo file header in bytes
+const MEMO_HEADER_LEN: u32 = 512;
+/// Size of memo block header in bytes
+const BLOCK_HEADER_LEN: u32 = 8;
+
+/// A content of a memo block
+#[derive(Clone, Debug, PartialEq)]
+pub struct MemoBlock {
+    pub memo_type: MemoType,
+    pub content: Vec<u8>
+}
+
+/// FoxPro memo file (.fpt) which store content of `M`, `G` and `P` field.
+///
+/// ## Header
+/// ---
+/// | Byte offset | Description |
+/// | --- | --- |
+/// | 0 - 3 | Location of next free block (big endian) |
+/// | 4 - 5 | Unused |
+/// | 6 - 7 | Block size in bytes (big endian) |
+/// | 8 - 511 | Unused |
+///
+/// ## Memo block
+/// ---
+/// | Byte offset | Description |
+/// | --- | --- |
+/// | 0 - 3 | Block signature (big endian):<br/>0 - Picture (`G` and `P` field)<br/>1 - Text (`M` field) |
+/// | 4 - 7 | Length of memo in bytes (big endian) |
+/// | 8 - n | Memo content |
+/// ---
+///
+/// A block number stored in a record is counted from the beginning of file
+/// so the byte offset of a memo is `block * block_size`.
+pub struct MemoFile<R = File> {
+    source: R,
+    next_free_block: u32,
+    block_size: u32,
+    codepage: &'static str
+}
+
+impl MemoFile<File> {
+    /// Open memo file at given path.
+    /// Text memo is decoded using given codepage which shall be the codepage of the table.
+    pub fn open<P: AsRef<std::path::Path>>(path: P, codepage: &'static str) -> Result<MemoFile<File>> {
+        MemoFile::new(File::open(path)?, codepage)
+    }
+
+    /// Open memo file at given path for both reading and writing.
+    pub fn open_rw<P: AsRef<std::path::Path>>(path: P, codepage: &'static str) -> Result<MemoFile<File>> {
+        MemoFile::new(std::fs::OpenOptions::new().read(true).write(true).open(path)?, codepage)
+    }
+
+    /// Create an empty memo file at given path. Existing file will be truncated.
+    pub fn create<P: AsRef<std::path::Path>>(path: P, block_size: u16, codepage: &'static str) -> Result<MemoFile<File>> {
+        let f = std::fs::OpenOptions::new().read(true).write(true).create(true).truncate(true).open(path)?;
+        MemoFile::create_in(f, block_size, codepage)
+    }
+}
+
+impl<R> MemoFile<R> where R: Read + Seek {
+    /// Read memo header from given source.
+    pub fn new(mut source: R, codepage: &'static str) -> Result<MemoFile<R>> {
+        let mut header = [0u8; 8];
+        source.seek(SeekFrom::Start(0))?;
+        read_full(&mut source, &mut header)?;
+        let next_free_block = u32::from_be_bytes(take_bytes(&header[0..4])?);
+        let block_size = u16::from_be_bytes(take_bytes(&header[6..8])?) as u32;
+        if block_size == 0 {
+            return Err(Error::BadHeader("Memo block size is 0".to_owned()));
+        }
+
+        Ok(MemoFile {
+            source,
+            next_free_block,
+            block_size,
+            codepage
+        })
+    }
+
+    /// Size in bytes of each block
+    pub fn block_size(&self) -> u32 {
+        self.block_size
+    }
+
+    /// Block number where next memo will be written
+    pub fn next_free_block(&self) -> u32 {
+        self.next_free_block
+    }
+
+    /// Codepage used to decode text memo
+    pub fn codepage(&self) -> &'static str {
+        self.codepage
+    }
+
+    /// Take the underlying source out of this memo file
+    pub fn into_inner(self) -> R {
+        self.source
+    }
+
+    /// Read memo block at given block number.
+    /// Block number 0 means there is no memo so it return `None`.
+    pub fn read_block(&mut self, block: u32) -> Result<Option<MemoBlock>> {
+        if block == 0 {
+            return Ok(None);
+        }
+        let (memo_type, len) = self.block_header(block)?;
+        let mut content = vec![0u8; len];
+        read_full(&mut self.source, &mut content)?;
+
+        Ok(Some(MemoBlock {
+            memo_type,
+            content
+        }))
+    }
+
+    /// Read memo at given block number and decode it as text using table codepage.
+    pub fn read_text(&mut self, block: u32) -> Result<Option<String>> {
+        match self.read_block(block)? {
+            Some(memo) => Ok(Some(decode(self.codepage, &memo.content)?)),
+            None => Ok(None)
+        }
+    }
+
+    /// Read memo at given block number as is.
+    pub fn read_binary(&mut self, block: u32) -> Result<Option<Vec<u8>>> {
+        Ok(self.read_block(block)?.map(|memo| memo.content))
+    }
+
+    /// Number of blocks occupied by memo at given block number.
+    fn blocks_used(&mut self, block: u32) -> Result<u32> {
+        let (_, len) = self.block_header(block)?;
+        Ok(self.blocks_needed(len))
+    }
+
+    /// Read type and length of memo at given block number, leaving the source at its content.
+    /// Memo shall end within both the file and the blocks before next free block so that
+    /// its length is safe to allocate and reusing its blocks never overwrite other memo.
+    fn block_header(&mut self, block: u32) -> Result<(MemoType, usize)> {
+        if block >= self.next_free_block {
+            return Err(Error::Decode(format!("Memo block {} is beyond next free block {}", block, self.next_free_block)));
+        }
+
+        let start = block as u64 * self.block_size as u64;
+        let end = self.source.seek(SeekFrom::End(0))?;
+        let mut block_header = [0u8; 8];
+        self.source.seek(SeekFrom::Start(start))?;
+        read_full(&mut self.source, &mut block_header)?;
+        let memo_type = MemoType::parse_type(u32::from_be_bytes(take_bytes(&block_header[0..4])?));
+        let len = u32::from_be_bytes(take_bytes(&block_header[4..8])?) as usize;
+        let available = end.min(self.next_free_block as u64 * self.block_size as u64)
+            .saturating_sub(start + block_header.len() as u64);
+        if len as u64 > available {
+            return Err(Error::Truncated {
+                expected: len,
+                found: available as usize
+            });
+        }
+        Ok((memo_type, len))
+    }
+
+    /// Number of blocks needed to store memo of `len` bytes
+    fn blocks_needed(&self, len: usize) -> u32 {
+        (len as u64 + BLOCK_HEADER_LEN as u64).div_ceil(self.block_size as u64) as u32
+    }
+}
+
+impl<R> MemoFile<R> where R: Read + Write + Seek {
+    /// Write header of an empty memo file into given source.
+    /// The first free block is the first block right after the 512 bytes header.
+    pub fn create_in(source: R, block_size: u16, codepage: &'static str) -> Result<MemoFile<R>> {
+        if block_size == 0 {
+            return Err(Error::BadHeader("Memo block size is 0".to_owned()));
+        }
+
+        let block_size = block_size as u32;
+        let mut memo = MemoFile {
+            source,
+            next_free_block: MEMO_HEADER_LEN.div_ceil(block_size),
+            block_size,
+            codepage
+        };
+        let header_len = memo.next_free_block as u64 * block_size as u64;
+        memo.source.seek(SeekFrom::Start(0))?;
+        memo.source.write_all(&vec![0u8; header_len as usize])?;
+        memo.source.seek(SeekFrom::Start(6))?;
+        memo.source.write_all(&(block_size as u16).to_be_bytes())?;
+        memo.write_next_free_block()?;
+        Ok(memo)
+    }
+
+    /// Append a new memo at the end of memo file and return its block number.
+    /// 
+    /// The memo is padded with 0 up to the block boundary and the next free block
+    /// in the header is moved past it.
+    pub fn append_block(&mut self, memo_type: MemoType, content: &[u8]) -> Result<u32> {
+        let block = self.next_free_block;
+        let blocks = self.blocks_needed(content.len());
+        let next_free_block = block.checked_add(blocks).ok_or(Error::Overflow {
+            size: block as usize + blocks as usize,
+            max: u32::MAX as usize
+        })?;
+
+        self.write_block_at(block, blocks, memo_type, content)?;
+        self.next_free_block = next_free_block;
+        self.write_next_free_block()?;
+        Ok(block)
+    }
+
+    /// Replace memo at given block number and return the block number where new memo is stored.
+    /// 
+    /// If the new memo fits into the blocks of the old memo, the old blocks are reused.
+    /// Otherwise the new memo is appended to the end of file and old blocks are left as is
+    /// until [pack_memo](struct.Table.html#method.pack_memo) is called.
+    /// Block number 0 means there is no old memo. Empty content is not stored at all so
+    /// it return 0.
+    pub fn replace_block(&mut self, block: u32, memo_type: MemoType, content: &[u8]) -> Result<u32> {
+        if content.is_empty() {
+            return Ok(0);
+        }
+        if block != 0 {
+            let used = self.blocks_used(block)?;
+            if self.blocks_needed(content.len()) <= used {
+                self.write_block_at(block, used, memo_type, content)?;
+                return Ok(block);
+            }
+        }
+        self.append_block(memo_type, content)
+    }
+
+    /// Encode given text using memo codepage then store it in place of memo at given block number.
+    /// See [replace_block](struct.MemoFile.html#method.replace_block) for how blocks are reused.
+    pub fn write_text(&mut self, block: u32, text: &str) -> Result<u32> {
+        let content = encode(self.codepage, text)?;
+        self.replace_block(block, MemoType::Text, &content)
+    }
+
+    /// Store given binary content in place of memo at given block number.
+    /// See [replace_block](struct.MemoFile.html#method.replace_block) for how blocks are reused.
+    pub fn write_binary(&mut self, block: u32, content: &[u8]) -> Result<u32> {
+        self.replace_block(block, MemoType::Picture, content)
+    }
+
+    /// Write memo at given block and fill the rest of `blocks` blocks with 0.
+    fn write_block_at(&mut self, block: u32, blocks: u32, memo_type: MemoType, content: &[u8]) -> Result<()> {
+        let len = u32::try_from(content.len()).map_err(|_| Error::Overflow {
+            size: content.len(),
+            max: u32::MAX as usize
+        })?;
+        let padding = blocks as usize * self.block_size as usize - content.len() - BLOCK_HEADER_LEN as usize;
+
+        self.source.seek(SeekFrom::Start(block as u64 * self.block_size as u64))?;
+        self.source.write_all(&memo_type.signature().to_be_bytes())?;
+        self.source.write_all(&len.to_be_bytes())?;
+        self.source.write_all(content)?;
+        self.source.write_all(&vec![0u8; padding])?;
+        Ok(())
+    }
+
+    fn write_next_free_block(&mut self) -> Result<()> {
+        self.source.seek(SeekFrom::Start(0))?;
+        self.source.write_all(&self.next_free_block.to_be_bytes())?;
+        self.source.flush()?;
+        Ok(())
+    }
+}
+
+/// Parse block number stored in a record.
+/// Visual FoxPro store it as 4 bytes little endian integer while FoxPro 2.x
+/// and earlier store it as 10 ASCII digits. Blank value means there is no memo.
+pub(crate) fn parse_block_number(bytes: &[u8]) -> Result<u32> {
+    if bytes.len() == 4 {
+        return Ok(u32::from_le_bytes(take_bytes(bytes)?));
+    }
+
+    let digits = decode("ISO-8859-1", bytes)?;
+    let digits = digits.trim_matches(|c: char| c == ' ' || c == '\0');
+    if digits.is_empty() {
+        Ok(0)
+    } else {
+        digits.parse().map_err(|_| Error::Decode(format!("{:?} is not a memo block number", digits)))
+    }
+}
+
+/// Serialize block number into `size` bytes as stored in a record.
+/// It is an inverse of `parse_block_number`.
+pub(crate) fn block_number_bytes(block: u32, size: usize) -> Result<Vec<u8>> {
+    if size == 4 {
+        return Ok(block.to_le_bytes().to_vec());
+    }
+
+    let digits = if block == 0 {
+        format!("{:>width$}", "", width = size)
+    } else {
+        format!("{:>width$}", block, width = size)
+    };
+    if digits.len() > size {
+        return Err(Error::Overflow {
+            size: digits.len(),
+            max: size
+        });
+    }
+    Ok(digits.into_bytes())
+}
@@ -1,12 +1,14 @@
-use chrono::{Datelike, Timelike};
+use chrono::{Datelike, NaiveTime, Timelike};
 use core::fmt::Display;
-use encoding_rs::{Decoder, Encoding};
 use std::fmt;
 use std::{
-    convert::TryInto, 
+    convert::TryFrom,
     fs::File, 
     io::{
-        Read, Seek, SeekFrom
+        Read, Seek, SeekFrom, Write
+    },
+    ops::{
+        Bound, RangeBounds
     }
 };
 
@@ -15,10 +17,18 @@ use super::*;
 #[cfg(test)]
 mod tests;
 
-type MemReferer<T> = Box<T>;
+mod currency;
+mod decimal;
+pub mod index;
+pub(crate) mod memo;
+mod record;
+mod schema;
 
-#[cfg(features = "threaded")]
-type MemReferer<T> = Arc<T>;
+pub use currency::Currency;
+pub use decimal::Decimal;
+pub use memo::{MemoBlock, MemoFile, MemoType};
+pub use record::{DynamicRecord, Value, ValueField};
+pub use schema::{NullFlags, Schema};
 
 /// Read field meta data from dbf file.
 /// 
@@ -26,7 +36,7 @@ type MemReferer<T> = Arc<T>;
 /// ---
 /// | Byte offset | Description |
 /// ---
-/// | 0 - 10 | Field name with a maximum of 10 chars. If less than 10, right hand padded with 0 |
+/// | 0 - 10 | Field name with a maximum of 10 chars. If less than 10, right hand padded with 0. The padding is trimmed from `Field::name` |
 /// | 11 | Field type:<br/>C - Character<br/>Y - Currency<br/>N - Numeric<br/>F - Float<br/>D - Date<br/> T - DateTime<br/>B - Double<br/>I - Integer<br/>L - Logical<br/>M - Memo<br/>G - General<br/>C - Character(Binary)<br/>M - Memo(binary)<br/>P - Picture<br/>+ - Autoincrement(dBase 7)<br/>O - Double(dbase 7)@ - Timestamp(dbase 7) |
 /// | 12 - 15 | Displacement of field in record |
 /// | 16 | Length of field (bytes) |
@@ -35,74 +45,64 @@ type MemReferer<T> = Arc<T>;
 /// | 19 - 22 | Value of next autoincrement |
 /// | 23 | Value of autoincrement step |
 /// | 24 - 31 | Reserved |
-pub async fn read_fields(f: &mut File, h: &Header) -> Vec<Field> {
-    f.seek(SeekFrom::Start(33)).expect("Fail to move file cursor to fields meta data");
+pub async fn read_fields(f: &mut File, h: &Header) -> Result<Vec<Field>> {
+    f.seek(SeekFrom::Start(32))?;
     let mut buffer = [0u8;32];
-    let encoding = match Encoding::for_label(h.codepage.as_bytes()) {
-        Some(e) => e,
-        None => panic!("Encoding {} is not supported", &h.codepage)
-    };
-
-    let mut decoder = encoding.new_decoder();
     let mut fields = vec![];
-    f.read_exact(&mut buffer).expect("Fail to read file");
+    read_full(f, &mut buffer[..1])?;
 
-    while let Some(field) = read_field_meta(buffer.try_into().unwrap(), &mut decoder) {
-        fields.push(field);
-        f.read_exact(&mut buffer).expect("Fail to read file");
+    while buffer[0] != 0x0D {
+        read_full(f, &mut buffer[1..])?;
+        fields.push(read_field_meta(buffer, h.codepage)?);
+        read_full(f, &mut buffer[..1])?;
     }
 
-    fields
+    Ok(fields)
 }
 
-fn read_field_meta(bytes: [u8; 32], decoder: &mut Decoder) -> Option<Field> {
-    if bytes[0] == 0x0D {
-        return None
-    }
-    
-    let mut field_name = String::with_capacity(10);
-    let (reason, readed, _) = decoder.decode_to_string(&bytes[0..10], &mut field_name, false);
-    if readed != 10 {
-        match reason {
-            CoderResult::InputEmpty => {
-                panic!("Fail to read field name from meta data")
-            },
-            CoderResult::OutputFull => {
-                panic!("Insufficient field name length allocated. Please file a defect report.")
-            }
+/// Parse field subrecords out of header area that starts at byte 32, up to the 0x0D terminator.
+fn parse_fields(area: &[u8], codepage: &str) -> Result<Vec<Field>> {
+    let mut fields = vec![];
+    for bytes in area.chunks(32) {
+        if bytes[0] == 0x0D {
+            return Ok(fields);
         }
+        fields.push(read_field_meta(take_bytes(bytes)?, codepage)?);
     }
+    Err(Error::Truncated {
+        expected: area.len() + 1,
+        found: area.len()
+    })
+}
+
+fn read_field_meta(bytes: [u8; 32], codepage: &str) -> Result<Field> {
+    // name is padded with NUL and anything after the first NUL is garbage
+    let name_len = bytes[0..11].iter().position(|b| *b == 0).unwrap_or(11);
+    let field_name = decode(codepage, &bytes[..name_len])?;
     let datatype = bytes[11];
-    // let flag = match std::str::from_utf8(&bytes[11..12]) {
-    //     Ok(s) => s,
-    //     Err(err) => {
-    //         panic!(err)
-    //     }
-    // };
-    // let field_type = FieldType::from_flag(&flag).unwrap();
-    let offset = u32::from_le_bytes(bytes[12..16].try_into().unwrap()) as usize;
+    let offset = u32::from_le_bytes(take_bytes(&bytes[12..16])?) as usize;
     let size = bytes[16] as usize;
     let precision = bytes[17] as usize;
     let flag = bytes[18];
 
     // auto increment next id
-    let next_id = u32::from_le_bytes(bytes[19..23].try_into().unwrap());
+    let next_id = u32::from_le_bytes(take_bytes(&bytes[19..23])?);
     // auto increment step
-    let next_step = bytes[24] as u32;
+    let next_step = bytes[23] as u32;
 
-    Some(Field {
+    Ok(Field {
         name: field_name,
-        datatype: datatype,
-        offset: offset,
-        size: size,
-        precision: precision,
-        next_id: next_id,
+        datatype,
+        offset,
+        size,
+        precision,
+        next_id,
         step: next_step,
-        system: match flag & 0x01 == 1 {
+        system: match flag & 0x01 == 0x01 {
             true => Some(()),
             false => None
         },
-        nullable: match flag & 0x02 == 1 {
+        nullable: match flag & 0x02 == 0x02 {
             true => Some(()),
             false => None
         },
@@ -117,7 +117,81 @@ fn read_field_meta(bytes: [u8; 32], decoder: &mut Decoder) -> Option<Field> {
     })
 }
 
-pub fn cp_mapper(codepage: u8) -> Result<&'static str, &'static str> {
+/// Serialize field meta data into 32 bytes field subrecord.
+/// It is an inverse of `read_field_meta`.
+/// See [read_fields](fn.read_fields.html) for the layout.
+fn write_field_meta(field: &Field, codepage: &str) -> Result<[u8; 32]> {
+    let mut bytes = [0u8; 32];
+    let name = encode(codepage, field.name.trim_end_matches('\0'))?;
+    if name.len() > 10 {
+        return Err(Error::Overflow {
+            size: name.len(),
+            max: 10
+        });
+    }
+    bytes[..name.len()].copy_from_slice(&name);
+    bytes[11] = field.datatype;
+    bytes[12..16].copy_from_slice(&(field.offset as u32).to_le_bytes());
+    bytes[16] = to_byte(field.size)?;
+    bytes[17] = to_byte(field.precision)?;
+    if field.system.is_some() {
+        bytes[18] |= 0x01;
+    }
+    if field.nullable.is_some() {
+        bytes[18] |= 0x02;
+    }
+    if field.binary.is_some() {
+        bytes[18] |= 0x04;
+    }
+    if field.autoincrement.is_some() {
+        bytes[18] |= 0x0C;
+    }
+    bytes[19..23].copy_from_slice(&field.next_id.to_le_bytes());
+    bytes[23] = to_byte(field.step as usize)?;
+
+    Ok(bytes)
+}
+
+/// Value that shall fit in a single byte of header
+fn to_byte(value: usize) -> Result<u8> {
+    u8::try_from(value).map_err(|_| Error::Overflow {
+        size: value,
+        max: u8::MAX as usize
+    })
+}
+
+/// Put next value of every autoincrement `I` field into the record and advance it.
+/// It return true if any field is assigned.
+fn assign_autoincrement(fields: &mut [Field], record: &mut [u8]) -> Result<bool> {
+    let mut assigned = false;
+    for field in fields.iter_mut().filter(|field| field.datatype == b'I') {
+        if let Some(id) = field.take_next_id() {
+            let id = i32::try_from(id).map_err(|_| Error::Overflow {
+                size: id as usize,
+                max: i32::MAX as usize
+            })?;
+            put_field(record, field.offset, field.size, &id.to_le_bytes(), 0)?;
+            assigned = true;
+        }
+    }
+    Ok(assigned)
+}
+
+/// Write next value of every autoincrement field back to its field subrecord.
+fn write_next_ids(f: &mut File, fields: &[Field]) -> Result<()> {
+    for (i, field) in fields.iter().enumerate().filter(|(_, field)| field.autoincrement.is_some()) {
+        f.seek(SeekFrom::Start(32 + i as u64 * 32 + 19))?;
+        f.write_all(&field.next_id.to_le_bytes())?;
+    }
+    Ok(())
+}
+
+/// Slice bytes of given field out of a record.
+fn field_slice<'a>(record: &'a [u8], meta: &Field) -> Result<&'a [u8]> {
+    record_slice(record, meta.offset, meta.size)
+}
+
+pub fn cp_mapper(codepage: u8) -> Result<&'static str> {
     match codepage {
         1 => Ok("cp437"),
         2 => Ok("cp850"),
@@ -145,10 +219,17 @@ pub fn cp_mapper(codepage: u8) -> Result<&'static str, &'static str> {
         201 => Ok("cp1251"),
         202 => Ok("cp1254"),
         203 => Ok("cp1253"),
-        _ => Result::Err("Unknown codepage found")
+        _ => Err(Error::UnknownCodepage(format!("mark {}", codepage)))
     }
 }
 
+/// Find a code page mark of given codepage.
+/// It is an inverse of [cp_mapper](fn.cp_mapper.html).
+pub fn cp_mark(codepage: &str) -> Result<u8> {
+    (0..=255u8).find(|mark| cp_mapper(*mark).map(|cp| cp == codepage).unwrap_or(false))
+        .ok_or_else(|| Error::UnknownCodepage(codepage.to_owned()))
+}
+
 #[derive(Clone)]
 pub enum FieldType {
     /// Fixed length character data type
@@ -181,8 +262,8 @@ pub enum FieldType {
 }
 
 impl FieldType {
-    pub fn from_flag(f: &str) -> Result<FieldType, &'static str> {
-        match f.chars().nth(0).unwrap() {
+    pub fn from_flag(f: &str) -> Result<FieldType> {
+        match f.chars().next().unwrap_or('\0') {
             'C' => Ok(FieldType::Character),
             'Y' => Ok(FieldType::Currency),
             'D' => Ok(FieldType::Date),
@@ -197,12 +278,12 @@ impl FieldType {
             'P' => Ok(FieldType::Picture),
             'Q' => Ok(FieldType::Varbinary),
             'V' => Ok(FieldType::Varchar),
-            _ => Err("Unsupported flag")
+            _ => Err(Error::BadFieldType(f.bytes().next().unwrap_or(0)))
         }
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Field {
     pub name: String,
     pub datatype: u8,
@@ -217,6 +298,58 @@ pub struct Field {
     pub binary: Option<()>
 }
 
+impl Field {
+    /// Create a new field meta data for writing a new table.
+    /// The offset is left as 0 as [TableWriter](struct.TableWriter.html) will
+    /// lay fields out in the given order.
+    pub fn new(name: &str, datatype: u8, size: usize, precision: usize) -> Field {
+        Field {
+            name: name.to_owned(),
+            datatype,
+            offset: 0,
+            size,
+            precision,
+            next_id: 0,
+            step: 0,
+            nullable: None,
+            system: None,
+            autoincrement: None,
+            binary: None
+        }
+    }
+
+    /// Make this field an autoincrement field which start from `next_id` and increase by `step`.
+    /// Only `I` field is assigned automatically.
+    pub fn with_autoincrement(mut self, next_id: u32, step: u32) -> Field {
+        self.autoincrement = Some(());
+        self.next_id = next_id;
+        self.step = step;
+        self
+    }
+
+    /// Take the next value of autoincrement field then advance it by the step.
+    /// It return `None` if this field isn't autoincrement.
+    pub fn take_next_id(&mut self) -> Option<u32> {
+        self.autoincrement?;
+        let id = self.next_id;
+        self.next_id = id.wrapping_add(self.id_step());
+        Some(id)
+    }
+
+    /// Create `_NullFlags` system field that holds given number of bits.
+    pub fn null_flags(bits: usize) -> Field {
+        let mut field = Field::new("_NullFlags", b'0', bits.div_ceil(8).max(1), 0);
+        field.system = Some(());
+        field.binary = Some(());
+        field
+    }
+
+    /// Return true if this is the `_NullFlags` system field of Visual FoxPro
+    pub fn is_null_flags(&self) -> bool {
+        self.datatype == b'0' && self.system.is_some()
+    }
+}
+
 impl FieldMeta for Field {
     fn nullable(&self) -> bool {
         self.nullable.is_some()
@@ -242,56 +375,140 @@ impl FieldMeta for Field {
     fn next_id(&mut self) -> u32 {
         self.next_id
     }
+    /// Step stored in the field subrecord. Step 0 is treated as 1.
+    fn id_step(&self) -> u32 {
+        match self.autoincrement {
+            Some(_) => self.step.max(1),
+            None => 0
+        }
+    }
 }
 
+/// Remove padding at the end of character field
+fn trim_char(value: &str) -> &str {
+    value.trim_end_matches(&[' ', '\0'][..])
+}
+
+/// A `C` field which is padded with spaces up to the field size.
+///
+/// `String` read from the field has trailing padding removed. Use
+/// [untrimmed](struct.RawCharField.html#method.untrimmed) to read the whole field.
+/// Written value is padded with spaces, keeping the field size. Value that is too long is
+/// cut at the last character that fit so multibyte character is never cut in half.
+/// In [strict](struct.RawCharField.html#method.with_strict) mode, it is `Error::Overflow` instead.
+///
+/// Binary field, i.e. `NOCPTRANS` field, isn't translated by codepage. Its content is
+/// read and written as `Vec<u8>` and reading it as `String` is `Error::Decode`.
 #[derive(Clone)]
 pub struct RawCharField {
     bytes: MemReferer<[u8]>,
-    encoding: String
+    encoding: String,
+    binary: bool,
+    strict: bool
 }
 
-impl ConversionField<String> for RawCharField {
-    fn get(&self) -> String {
-        let mut value = String::with_capacity(self.bytes.len());
-        let (coderesult, size, _) = get_decoder(self.encoding.as_str()).decode_to_string(&self.bytes, &mut value, false);
-
-        if size != self.bytes.len() {
-            match coderesult {
-                CoderResult::InputEmpty => {
-                    panic!("Insufficient input to read or input is emptied");
-                },
-                CoderResult::OutputFull => {
-                    panic!("Insufficient output buffer for filling");
-                }
-            }
+impl RawCharField {
+    /// Take the field bytes out of a record.
+    /// The bytes are decoded using given codepage.
+    pub fn new(bytes: &[u8], encoding: &str) -> RawCharField {
+        RawCharField {
+            bytes: MemReferer::from(bytes),
+            encoding: encoding.to_owned(),
+            binary: false,
+            strict: false
         }
-        value.truncate(size);
+    }
 
-        value
+    /// Mark the field as binary field whose content isn't translated by codepage.
+    /// It is the binary flag of [Field](struct.Field.html#structfield.binary).
+    pub fn with_binary(mut self, binary: bool) -> RawCharField {
+        self.binary = binary;
+        self
     }
 
-    fn set(&mut self, value: &String) {
-        // Just in case for DBCS
-        let mut bytes = Vec::with_capacity(value.len() * 2);
-        unsafe {
-            bytes.set_len(value.len());
+    /// In strict mode, writing value longer than the field is `Error::Overflow`
+    /// instead of cutting the value.
+    pub fn with_strict(mut self, strict: bool) -> RawCharField {
+        self.strict = strict;
+        self
+    }
+
+    /// Return true if the field content isn't translated by codepage
+    pub fn is_binary(&self) -> bool {
+        self.binary
+    }
+
+    /// Whole field content including the padding
+    pub fn untrimmed(&self) -> Result<String> {
+        if self.binary {
+            return Err(Error::Decode("Binary character field has no codepage".to_owned()));
         }
-        let (result, read, write, _) = get_encoder(self.encoding.as_str()).encode_from_utf8(value, bytes.as_mut_slice(), false);
-        if read != value.len() {
-            match result {
-                CoderResult::InputEmpty => {
-                    panic!("Insufficient input to filed buffer or input is emptied");
-                },
-                CoderResult::OutputFull => {
-                    panic!("Insufficient buffered output allocated");
+        decode(self.encoding.as_str(), &self.bytes)
+    }
+
+    /// Field bytes to be put back into a record
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Put the bytes into the field and pad them with spaces.
+    /// `boundary` is the longest prefix length, not over the field size, that can be kept.
+    fn put(&mut self, bytes: &[u8], boundary: impl FnOnce(usize) -> Result<usize>) -> Result<()> {
+        let size = self.bytes.len();
+        let len = match bytes.len() {
+            len if len <= size => len,
+            len if self.strict => return Err(Error::Overflow {
+                size: len,
+                max: size
+            }),
+            _ => boundary(size)?
+        };
+        let mut field = vec![b' '; size];
+        field[..len].copy_from_slice(&bytes[..len]);
+        self.bytes = MemReferer::from(field);
+        Ok(())
+    }
+}
+
+impl ConversionField<String> for RawCharField {
+    fn get(&self) -> Result<String> {
+        self.untrimmed().map(|value| trim_char(&value).to_owned())
+    }
+
+    fn set(&mut self, value: &String) -> Result<()> {
+        let encoding = self.encoding.clone();
+        let encoded = encode(encoding.as_str(), value)?;
+        self.put(&encoded, |size| {
+            // the longest run of whole characters that fit
+            let mut len = 0;
+            for (i, c) in value.char_indices() {
+                let end = encode(encoding.as_str(), &value[..i + c.len_utf8()])?.len();
+                if end > size {
+                    break;
                 }
+                len = end;
             }
-        }
-        bytes.truncate(write);
-        self.bytes = bytes.into_boxed_slice();
+            Ok(len)
+        })
+    }
+}
+
+impl ConversionField<Vec<u8>> for RawCharField {
+    fn get(&self) -> Result<Vec<u8>> {
+        Ok(self.bytes.to_vec())
+    }
+
+    fn set(&mut self, value: &Vec<u8>) -> Result<()> {
+        self.put(value, Ok)
     }
 }
 
+/// A `C` field.
+///
+/// Content is decoded by the codepage and kept with its padding. Use
+/// [value](struct.CharField.html#method.value) to get it without the padding.
+/// Binary field isn't decoded. Its content is available by
+/// [as_bytes](struct.CharField.html#method.as_bytes).
 #[derive(Clone)]
 pub struct CharField<'a> {
     pub meta: Field,
@@ -301,6 +518,40 @@ pub struct CharField<'a> {
     record: &'a [u8]
 }
 
+impl<'a> CharField<'a> {
+    /// Create a character field that will decode its content from given record using given codepage
+    /// once [from_record_bytes](../trait.FieldOps.html#tymethod.from_record_bytes) is called.
+    pub fn new(meta: Field, codepage: &'a str, record: &'a [u8]) -> CharField<'a> {
+        CharField {
+            meta,
+            content: String::new(),
+            codepage,
+            ready: None,
+            record
+        }
+    }
+
+    /// Content without trailing padding. It is empty for binary field.
+    pub fn value(&self) -> &str {
+        trim_char(&self.content)
+    }
+
+    /// Whole content including the padding. It is empty for binary field.
+    pub fn untrimmed(&self) -> &str {
+        &self.content
+    }
+
+    /// Return true if the field content isn't translated by codepage
+    pub fn is_binary(&self) -> bool {
+        self.meta.binary.is_some()
+    }
+
+    /// Field bytes as stored in the record
+    pub fn as_bytes(&self) -> Result<&'a [u8]> {
+        field_slice(self.record, &self.meta)
+    }
+}
+
 impl<'a> FieldMeta for CharField<'a> {
     fn nullable(&self) -> bool {
         self.meta.nullable()
@@ -328,35 +579,33 @@ impl<'a> FieldMeta for CharField<'a> {
     }
 }
 
+/// Display content without trailing padding. Binary field is displayed as hex bytes.
 impl<'a> Display for CharField<'a> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.content)
+        match self.is_binary() {
+            true => write!(f, "{:02X?}", self.as_bytes().unwrap_or_default()),
+            false => write!(f, "{}", self.value())
+        }
     }
 }
 
 impl<'a> FieldOps for CharField<'a> {
 
-    fn from_record_bytes(&mut self) -> BoxFuture<()> {
+    fn from_record_bytes(&mut self) -> BoxFuture<'_, Result<()>> {
         Box::pin(async move {
-            let field = &self.record[self.meta.rec_offset()..(self.meta.rec_offset() + self.meta.size())];
-            let (reason, readed, _) = get_decoder(self.codepage).decode_to_string(field, &mut self.content, true);
-            if readed != self.meta.size() {
-                match reason {
-                    CoderResult::InputEmpty => {
-                        panic!("Insufficient record data. Expect {} but found {}", self.meta.size(), readed)
-                    },
-                    CoderResult::OutputFull => {
-                        panic!("Insufficient buffer to store converted string")
-                    }
-                }
+            let field = field_slice(self.record, &self.meta)?;
+            if !self.is_binary() {
+                self.content = decode(self.codepage, field)?;
             }
+            self.ready = Some(());
+            Ok(())
         })
     }
 
-    fn to_bytes(&self) -> BoxFuture<&[u8]> {
+    fn to_bytes(&self) -> BoxFuture<'_, Result<&[u8]>> {
         Box::pin(
             async move {
-                &self.record[self.meta.rec_offset()..(self.meta.size() + self.meta.rec_offset())]
+                record_slice(self.record, self.meta.rec_offset(), self.meta.size())
             }
         )
     }
@@ -366,29 +615,70 @@ impl<'a> FieldOps for CharField<'a> {
     }
 }
 
+/// A `Y` field which store [Currency](struct.Currency.html) as 64 bits little endian integer
+/// of ten-thousandths.
 #[derive(Clone)]
 pub struct RawCurrencyField {
     bytes: MemReferer<[u8]>
 }
 
+impl RawCurrencyField {
+    /// Take the field bytes out of a record
+    pub fn new(bytes: &[u8]) -> RawCurrencyField {
+        RawCurrencyField {
+            bytes: MemReferer::from(bytes)
+        }
+    }
+
+    /// Field bytes to be put back into a record
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+impl ConversionField<Currency> for RawCurrencyField {
+    fn get(&self) -> Result<Currency> {
+        Ok(Currency::from_raw(i64::from_le_bytes(take_bytes(&self.bytes)?)))
+    }
+
+    fn set(&mut self, value: &Currency) -> Result<()> {
+        self.bytes = MemReferer::from(value.raw().to_le_bytes());
+        Ok(())
+    }
+}
+
+/// Value is rounded to 4 decimal places when it is set
 impl ConversionField<f64> for RawCurrencyField {
-    fn get(&self) -> f64 {
-        (i64::from_le_bytes((&*self.bytes).try_into().expect("Fail to convert to currency")) as f64) / 10_000f64
+    fn get(&self) -> Result<f64> {
+        ConversionField::<Currency>::get(self).map(|value| value.to_f64())
     }
 
-    fn set(&mut self, value: &f64) {
-        self.bytes = Box::from((value * 10_000f64).to_le_bytes());
+    fn set(&mut self, value: &f64) -> Result<()> {
+        self.set(&Currency::from_f64(*value)?)
     }
 }
 
 #[derive(Clone)]
 pub struct CurrencyField<'a> {
     pub meta: Field,
-    content: String,
+    content: Currency,
     ready: Option<()>,
     record: &'a [u8]
 }
 
+impl<'a> CurrencyField<'a> {
+    /// Create a currency field that will read its content from given record
+    /// once [from_record_bytes](../trait.FieldOps.html#tymethod.from_record_bytes) is called.
+    pub fn new(meta: Field, record: &'a [u8]) -> CurrencyField<'a> {
+        CurrencyField {
+            meta,
+            content: Currency::default(),
+            ready: None,
+            record
+        }
+    }
+}
+
 impl<'a> FieldMeta for CurrencyField<'a> {
     fn nullable(&self) -> bool {
         self.meta.nullable()
@@ -424,21 +714,19 @@ impl<'a> Display for CurrencyField<'a> {
 
 impl<'a> FieldOps for CurrencyField<'a> {
 
-    fn from_record_bytes(&mut self) -> BoxFuture<()> {
+    fn from_record_bytes(&mut self) -> BoxFuture<'_, Result<()>> {
         Box::pin(async move {
-            let field = &self.record[self.meta.rec_offset()..(self.meta.rec_offset() + self.meta.size())];
-            
-            let raw = i64::from_le_bytes(field.try_into().unwrap());
-            let integer = raw / 10000;
-            let fraction = raw % 10000;
-            self.content = format!("{}.{:04}", integer, fraction);
+            let field = field_slice(self.record, &self.meta)?;
+            self.content = RawCurrencyField::new(field).get()?;
+            self.ready = Some(());
+            Ok(())
         })
     }
 
-    fn to_bytes(&self) -> BoxFuture<&[u8]> {
+    fn to_bytes(&self) -> BoxFuture<'_, Result<&[u8]>> {
         Box::pin(
             async move {
-                &self.record[self.meta.rec_offset()..(self.meta.rec_offset() + self.meta.size())]
+                record_slice(self.record, self.meta.rec_offset(), self.meta.size())
             }
         )
     }
@@ -453,28 +741,124 @@ pub struct RawDoubleField {
     bytes: MemReferer<[u8]>
 }
 
+impl RawDoubleField {
+    /// Take the field bytes out of a record
+    pub fn new(bytes: &[u8]) -> RawDoubleField {
+        RawDoubleField {
+            bytes: MemReferer::from(bytes)
+        }
+    }
+
+    /// Field bytes to be put back into a record
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
 impl ConversionField<f64> for RawDoubleField {
-    fn get(&self) -> f64 {
-        f64::from_le_bytes((&*self.bytes).try_into().unwrap())
+    fn get(&self) -> Result<f64> {
+        Ok(f64::from_le_bytes(take_bytes(&self.bytes)?))
     }
 
-    fn set(&mut self, value: &f64) {
-        self.bytes = Box::from(value.to_le_bytes());
+    fn set(&mut self, value: &f64) -> Result<()> {
+        self.bytes = MemReferer::from(value.to_le_bytes());
+        Ok(())
     }
 }
 
+/// A block number inside memo file that point to content of `G` or `P` field.
+/// The content itself can be read by [load](struct.RawGeneralField.html#method.load).
 #[derive(Clone)]
 pub struct RawGeneralField {
     bytes: MemReferer<[u8]>
 }
 
+impl RawGeneralField {
+    /// Take the field bytes out of a record
+    pub fn new(bytes: &[u8]) -> RawGeneralField {
+        RawGeneralField {
+            bytes: MemReferer::from(bytes)
+        }
+    }
+
+    /// Read binary content pointed by this field from given memo file.
+    /// It return `None` if this field doesn't point to any memo block.
+    pub fn load<R: Read + Seek>(&self, memo: &mut MemoFile<R>) -> Result<Option<Vec<u8>>> {
+        memo.read_binary(self.get()?)
+    }
+
+    /// Write given content into memo file then point this field to it.
+    /// Blocks of current content are reused if the new content fits.
+    pub fn store<R: Read + Write + Seek>(&mut self, memo: &mut MemoFile<R>, content: &[u8]) -> Result<()> {
+        let block = memo.write_binary(self.get()?, content)?;
+        self.set(&block)
+    }
+
+    /// Field bytes to be put back into a record
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
 impl ConversionField<u32> for RawGeneralField {
-    fn get(&self) -> u32 {
-        u32::from_le_bytes((&*self.bytes).try_into().unwrap())
+    fn get(&self) -> Result<u32> {
+        memo::parse_block_number(&self.bytes)
+    }
+
+    fn set(&mut self, value: &u32) -> Result<()> {
+        let size = if self.bytes.is_empty() { 4 } else { self.bytes.len() };
+        self.bytes = memo::block_number_bytes(*value, size)?.into();
+        Ok(())
+    }
+}
+
+/// Picture field is stored the same way as general field
+pub type RawPictureField = RawGeneralField;
+
+/// A block number inside memo file that point to content of `M` field.
+/// The content itself can be read by [load](struct.RawMemoField.html#method.load).
+#[derive(Clone)]
+pub struct RawMemoField {
+    bytes: MemReferer<[u8]>
+}
+
+impl RawMemoField {
+    /// Take the field bytes out of a record
+    pub fn new(bytes: &[u8]) -> RawMemoField {
+        RawMemoField {
+            bytes: MemReferer::from(bytes)
+        }
+    }
+
+    /// Read text content pointed by this field from given memo file.
+    /// The text is decoded using codepage of memo file.
+    /// It return `None` if this field doesn't point to any memo block.
+    pub fn load<R: Read + Seek>(&self, memo: &mut MemoFile<R>) -> Result<Option<String>> {
+        memo.read_text(self.get()?)
+    }
+
+    /// Write given text into memo file then point this field to it.
+    /// Blocks of current text are reused if the new text fits.
+    pub fn store<R: Read + Write + Seek>(&mut self, memo: &mut MemoFile<R>, text: &str) -> Result<()> {
+        let block = memo.write_text(self.get()?, text)?;
+        self.set(&block)
+    }
+
+    /// Field bytes to be put back into a record
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+impl ConversionField<u32> for RawMemoField {
+    fn get(&self) -> Result<u32> {
+        memo::parse_block_number(&self.bytes)
     }
 
-    fn set(&mut self, value: &u32) {
-        self.bytes = Box::from(value.to_le_bytes());
+    fn set(&mut self, value: &u32) -> Result<()> {
+        let size = if self.bytes.is_empty() { 4 } else { self.bytes.len() };
+        self.bytes = memo::block_number_bytes(*value, size)?.into();
+        Ok(())
     }
 }
 
@@ -483,64 +867,130 @@ pub struct RawIntegerField {
     bytes: MemReferer<[u8]>
 }
 
+impl RawIntegerField {
+    /// Take the field bytes out of a record
+    pub fn new(bytes: &[u8]) -> RawIntegerField {
+        RawIntegerField {
+            bytes: MemReferer::from(bytes)
+        }
+    }
+
+    /// Field bytes to be put back into a record
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
 impl ConversionField<i32> for RawIntegerField {
-    fn get(&self) -> i32 {
-        i32::from_le_bytes((&*self.bytes).try_into().unwrap())
+    fn get(&self) -> Result<i32> {
+        Ok(i32::from_le_bytes(take_bytes(&self.bytes)?))
+    }
+
+    fn set(&mut self, value: &i32) -> Result<()> {
+        self.bytes = MemReferer::from(value.to_le_bytes());
+        Ok(())
     }
+}
 
-    fn set(&mut self, value: &i32) {
-        self.bytes = Box::from(value.to_le_bytes());
+/// Parse `L` field byte.
+/// `T`, `t`, `Y` and `y` is true. `F`, `f`, `N` and `n` is false.
+/// `?`, space and 0 is unknown. Anything else is `Error::Decode`.
+fn parse_logical(byte: u8) -> Result<Option<bool>> {
+    match byte {
+        b'T' | b't' | b'Y' | b'y' => Ok(Some(true)),
+        b'F' | b'f' | b'N' | b'n' => Ok(Some(false)),
+        b'?' | b' ' | 0 => Ok(None),
+        _ => Err(Error::Decode(format!("{:?} is not a logical value", byte as char)))
     }
 }
 
+/// A `L` field. See [LogicalField](struct.LogicalField.html) for the encoding.
+/// Unknown value is read as `None` by `Option<bool>` and is an error when it is read as `bool`.
 #[derive(Clone)]
 pub struct RawBoolField {
     byte: u8
 }
 
-impl ConversionField<bool> for RawBoolField {
-    fn get(&self) -> bool {
-        self.byte != 0
+impl RawBoolField {
+    /// Take the field byte out of a record
+    pub fn new(bytes: &[u8]) -> RawBoolField {
+        RawBoolField {
+            byte: bytes.first().copied().unwrap_or(0)
+        }
     }
 
-    fn set(&mut self, value: &bool) {
-        self.byte = *value as u8
+    /// Field byte to be put back into a record
+    pub fn as_bytes(&self) -> &[u8] {
+        std::slice::from_ref(&self.byte)
     }
 }
 
-#[derive(Clone)]
-pub struct RawDateField {
-    bytes: MemReferer<[u8]>
+impl ConversionField<Option<bool>> for RawBoolField {
+    fn get(&self) -> Result<Option<bool>> {
+        parse_logical(self.byte)
+    }
+
+    fn set(&mut self, value: &Option<bool>) -> Result<()> {
+        self.byte = match value {
+            Some(true) => b'T',
+            Some(false) => b'F',
+            None => b' '
+        };
+        Ok(())
+    }
 }
 
-impl ConversionField<NaiveDate> for RawDateField {
-    fn get(&self) -> NaiveDate {
-        NaiveDate::from_num_days_from_ce(i32::from_le_bytes(self.bytes[0..4].try_into().unwrap()))
+impl ConversionField<bool> for RawBoolField {
+    fn get(&self) -> Result<bool> {
+        parse_logical(self.byte)?.ok_or_else(|| Error::Decode("Logical field is unknown".to_owned()))
     }
 
-    fn set(&mut self, value: &NaiveDate) {
-        self.bytes = Box::from((value.num_days_from_ce() as i64).to_le_bytes());
+    fn set(&mut self, value: &bool) -> Result<()> {
+        self.set(&Some(*value))
     }
 }
 
+/// A `L` field which is true, false or unknown.
+///
+/// `T`, `t`, `Y` and `y` is true. `F`, `f`, `N` and `n` is false.
+/// `?` and space, which is how a field that was never set look like, is unknown.
+/// Value is written as `T` or `F` by [RawBoolField](struct.RawBoolField.html).
 #[derive(Clone)]
-pub struct DateField<'a> {
+pub struct LogicalField<'a> {
     pub meta: Field,
-    content: NaiveDate,
+    content: Option<bool>,
     ready: Option<()>,
     record: &'a [u8]
 }
 
-impl<'a> FieldMeta for DateField<'a> {
+impl<'a> LogicalField<'a> {
+    /// Create a logical field that will read its content from given record
+    /// once [from_record_bytes](../trait.FieldOps.html#tymethod.from_record_bytes) is called.
+    pub fn new(meta: Field, record: &'a [u8]) -> LogicalField<'a> {
+        LogicalField {
+            meta,
+            content: None,
+            ready: None,
+            record
+        }
+    }
+
+    /// Value of this field. It is `None` if the value is unknown or the field isn't loaded yet.
+    pub fn value(&self) -> Option<bool> {
+        self.content
+    }
+}
+
+impl<'a> FieldMeta for LogicalField<'a> {
     fn nullable(&self) -> bool {
         self.meta.nullable()
     }
-    fn datatype_flag(&self) -> u8 {
-        b'D'
-    }
     fn autoincrement(&self) -> bool {
         self.meta.autoincrement()
     }
+    fn datatype_flag(&self) -> u8 {
+        b'L'
+    }
     fn name(&self) -> &str {
         self.meta.name()
     }
@@ -558,27 +1008,32 @@ impl<'a> FieldMeta for DateField<'a> {
     }
 }
 
-impl<'a> Display for DateField<'a> {
+/// Display `.T.` or `.F.`. Unknown value is displayed as empty string
+impl<'a> Display for LogicalField<'a> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.content)
+        match self.content {
+            Some(true) => write!(f, ".T."),
+            Some(false) => write!(f, ".F."),
+            None => Ok(())
+        }
     }
 }
 
-impl<'a> FieldOps for DateField<'a> {
+impl<'a> FieldOps for LogicalField<'a> {
 
-    fn from_record_bytes(&mut self) -> BoxFuture<()> {
-        Box::pin(
-            async move {
-                let field = &self.record[self.meta.rec_offset()..(self.meta.rec_offset() + self.meta.size())];
-                self.content = NaiveDate::from_num_days_from_ce(i64::from_le_bytes(field.try_into().unwrap()) as i32);
-            }
-        )
+    fn from_record_bytes(&mut self) -> BoxFuture<'_, Result<()>> {
+        Box::pin(async move {
+            let field = field_slice(self.record, &self.meta)?;
+            self.content = RawBoolField::new(field).get()?;
+            self.ready = Some(());
+            Ok(())
+        })
     }
 
-    fn to_bytes(&self) -> BoxFuture<&[u8]> {
+    fn to_bytes(&self) -> BoxFuture<'_, Result<&[u8]>> {
         Box::pin(
             async move {
-                &self.record[self.meta.rec_offset()..(self.meta.rec_offset() + self.meta.size())]
+                record_slice(self.record, self.meta.rec_offset(), self.meta.size())
             }
         )
     }
@@ -588,44 +1043,147 @@ impl<'a> FieldOps for DateField<'a> {
     }
 }
 
+/// Parse `D` field bytes. See [RawDateField](struct.RawDateField.html) for the layouts.
+pub(crate) fn parse_date(bytes: &[u8]) -> Result<Option<NaiveDate>> {
+    let bad_date = || Error::BadDate(bytes.to_vec());
+    if bytes.iter().all(|b| *b == b' ' || *b == 0) {
+        return Ok(None);
+    }
+    if is_ascii_date(bytes) {
+        return parse_ascii_date(bytes);
+    }
+    let days = match bytes.len() {
+        4 => i32::from_le_bytes(take_bytes(bytes)?) as i64,
+        8 => i64::from_le_bytes(take_bytes(bytes)?),
+        _ => return Err(bad_date())
+    };
+    i32::try_from(days).ok()
+        .and_then(NaiveDate::from_num_days_from_ce_opt)
+        .map(Some)
+        .ok_or_else(bad_date)
+}
+
+/// Parse date stored as `YYYYMMDD` digits only. Field that is all spaces, or all zero, is empty.
+pub(crate) fn parse_ascii_date(bytes: &[u8]) -> Result<Option<NaiveDate>> {
+    let bad_date = || Error::BadDate(bytes.to_vec());
+    if bytes.iter().all(|b| *b == b' ' || *b == 0) {
+        return Ok(None);
+    }
+    if !is_ascii_date(bytes) {
+        return Err(bad_date());
+    }
+    // bytes are all digits so they are valid UTF-8
+    let text = std::str::from_utf8(bytes).map_err(|_| bad_date())?;
+    NaiveDate::parse_from_str(text, "%Y%m%d").map(Some).map_err(|_| bad_date())
+}
+
+/// Return true if the bytes are `YYYYMMDD` digits
+fn is_ascii_date(bytes: &[u8]) -> bool {
+    bytes.len() == 8 && bytes.iter().all(u8::is_ascii_digit)
+}
+
+/// A `D` field.
+///
+/// FoxPro store date as 8 ASCII digits `YYYYMMDD`. Some files store it as little endian
+/// number of days since common era, where 0001-01-01 is day 1, instead. Bytes that are all
+/// digits are read as the former, otherwise the latter. Field that is all spaces, or all zero,
+/// is empty. Anything else is `Error::BadDate`.
+///
+/// Empty field is read as `None` by `Option<NaiveDate>` and is `Error::BadDate`
+/// when it is read as `NaiveDate`.
+///
+/// Value is written as `YYYYMMDD` unless the field already hold a date in binary layout,
+/// in which case the binary layout is kept. `None` is written as spaces.
 #[derive(Clone)]
-pub struct RawDateTimeField {
+pub struct RawDateField {
     bytes: MemReferer<[u8]>
 }
 
-impl ConversionField<NaiveDateTime> for RawDateTimeField {
-    fn get(&self) -> NaiveDateTime {
-        let date_part = NaiveDate::from_num_days_from_ce(i64::from_le_bytes(self.bytes[0..4].try_into().unwrap()) as i32);
-        let time_part = u32::from_le_bytes(self.bytes[4..8].try_into().unwrap());
-        date_part.and_hms((time_part / 3_600_000) % 24, (time_part / 60_000) % 60, (time_part / 1000) % 60)
+impl RawDateField {
+    /// Take the field bytes out of a record
+    pub fn new(bytes: &[u8]) -> RawDateField {
+        RawDateField {
+            bytes: MemReferer::from(bytes)
+        }
+    }
+
+    /// Field bytes to be put back into a record
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Return true if the field hold a date in binary layout
+    fn is_binary(&self) -> bool {
+        !is_ascii_date(&self.bytes) && self.bytes.iter().any(|b| *b != b' ' && *b != 0)
+    }
+}
+
+impl ConversionField<Option<NaiveDate>> for RawDateField {
+    fn get(&self) -> Result<Option<NaiveDate>> {
+        parse_date(&self.bytes)
+    }
+
+    fn set(&mut self, value: &Option<NaiveDate>) -> Result<()> {
+        let size = if self.bytes.is_empty() { 8 } else { self.bytes.len() };
+        let bytes = match value {
+            // binary layout is read back as either i32 or i64
+            Some(value) if self.is_binary() => match size {
+                4 => value.num_days_from_ce().to_le_bytes().to_vec(),
+                8 => (value.num_days_from_ce() as i64).to_le_bytes().to_vec(),
+                _ if size < 4 => return Err(Error::Overflow {size: 4, max: size}),
+                _ => return Err(Error::Encode(format!("binary date cannot take {} bytes", size)))
+            },
+            Some(_) if size < 8 => return Err(Error::Overflow {size: 8, max: size}),
+            Some(value) if (0..=9999).contains(&value.year()) => value.format("%Y%m%d").to_string().into_bytes(),
+            Some(value) => return Err(Error::Encode(format!("{} cannot be stored as YYYYMMDD", value))),
+            None => vec![b' '; size]
+        };
+        self.bytes = MemReferer::from(bytes);
+        Ok(())
+    }
+}
+
+impl ConversionField<NaiveDate> for RawDateField {
+    fn get(&self) -> Result<NaiveDate> {
+        parse_date(&self.bytes)?.ok_or_else(|| Error::BadDate(self.bytes.to_vec()))
     }
 
-    fn set(&mut self, value: &NaiveDateTime) {
-        let mut bytes = (&(value.num_days_from_ce() as u32).to_le_bytes()).to_vec();
-        let time_part = value.nanosecond() / 1_000;
-        bytes.extend(&time_part.to_le_bytes());
-        self.bytes = bytes.into_boxed_slice();
+    fn set(&mut self, value: &NaiveDate) -> Result<()> {
+        self.set(&Some(*value))
     }
 }
 
 #[derive(Clone)]
-pub struct DateTimeField<'a> {
+pub struct DateField<'a> {
     pub meta: Field,
+    content: Option<NaiveDate>,
     ready: Option<()>,
-    content: NaiveDateTime,
     record: &'a [u8]
 }
 
-impl<'a> FieldMeta for DateTimeField<'a> {
+impl<'a> DateField<'a> {
+    /// Create a date field that will read its content from given record
+    /// once [from_record_bytes](../trait.FieldOps.html#tymethod.from_record_bytes) is called.
+    pub fn new(meta: Field, record: &'a [u8]) -> DateField<'a> {
+        DateField {
+            meta,
+            content: None,
+            ready: None,
+            record
+        }
+    }
+}
+
+impl<'a> FieldMeta for DateField<'a> {
     fn nullable(&self) -> bool {
         self.meta.nullable()
     }
+    fn datatype_flag(&self) -> u8 {
+        b'D'
+    }
     fn autoincrement(&self) -> bool {
         self.meta.autoincrement()
     }
-    fn datatype_flag(&self) -> u8 {
-        b'T'
-    }
     fn name(&self) -> &str {
         self.meta.name()
     }
@@ -643,32 +1201,34 @@ impl<'a> FieldMeta for DateTimeField<'a> {
     }
 }
 
-impl<'a> Display for DateTimeField<'a> {
+/// Empty field is displayed as empty string
+impl<'a> Display for DateField<'a> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.content)
+        match self.content {
+            Some(ref content) => write!(f, "{}", content),
+            None => Ok(())
+        }
     }
 }
 
-impl<'a> FieldOps for DateTimeField<'a> {
+impl<'a> FieldOps for DateField<'a> {
 
-    fn from_record_bytes(&mut self) -> BoxFuture<()> {
+    fn from_record_bytes(&mut self) -> BoxFuture<'_, Result<()>> {
         Box::pin(
             async move {
-                let half : usize = self.meta.rec_offset() + self.meta.size() / 2;
-                let date_field = &self.record[self.meta.rec_offset()..half];
-                let time_field = &self.record[half..(self.meta.rec_offset() + self.meta.size())];
-                let naive_date = NaiveDate::from_num_days_from_ce(i32::from_le_bytes(date_field.try_into().unwrap()) - 1_721_426);
-                let milli_4_midnight = u32::from_le_bytes(time_field.try_into().unwrap());
-                self.content = naive_date.and_hms((milli_4_midnight / 3_600_000) % 24, (milli_4_midnight / 60_000) % 60, (milli_4_midnight / 1000) % 60);
+                let field = field_slice(self.record, &self.meta)?;
+                self.content = parse_date(field)?;
+                self.ready = Some(());
+                Ok(())
             }
         )
     }
 
-    fn to_bytes(&self) -> BoxFuture<&[u8]> {
+    fn to_bytes(&self) -> BoxFuture<'_, Result<&[u8]>> {
         Box::pin(
             async move {
-                &self.record[self.meta.rec_offset()..(self.meta.rec_offset() + self.meta.size())]
-            }    
+                record_slice(self.record, self.meta.rec_offset(), self.meta.size())
+            }
         )
     }
 
@@ -677,145 +1237,1461 @@ impl<'a> FieldOps for DateTimeField<'a> {
     }
 }
 
-/// A raw bytes that represent 32 bits float value as 20 characters or less.
-/// This field type require user to specify length of integer part and number 
-/// of precision. The length of integer and precision combined shall be less than
-/// 20 including "." symbol and "-" sign.
-/// So if this field contains only positive integer, it can have value of to 20 digits.
-/// If this field contains signed integer, the positive value can take up to 20 digits but
-/// negative value can only take up to 19 digits.
-/// If it contains decimal, the number of digits will be reduce by 1.
-/// If precision is 3, the max integer will be 16 for positive value and 15 for negative value.
-/// 
-/// Example of max number of digit:
-/// 
-/// 12345678901234567890
-/// 
-/// -1234567890123456789
-/// 
-/// 1234567890123456.123
-/// 
-/// -1234567890.12345678
+/// Julian day number of 0001-01-01, the first day of common era
+pub(crate) const JULIAN_DAY_OF_CE: i64 = 1_721_426;
+
+/// Milliseconds in a day
+pub(crate) const MILLIS_PER_DAY: u32 = 86_400_000;
+
+/// Parse `T` field bytes.
+/// First 4 bytes is Julian day number and last 4 bytes is milliseconds since midnight,
+/// both are little endian. Field that is all zero, or all spaces, is empty.
+fn parse_datetime(bytes: &[u8]) -> Result<Option<NaiveDateTime>> {
+    if bytes.iter().all(|b| *b == 0 || *b == b' ') {
+        return Ok(None);
+    }
+    let julian_day = i32::from_le_bytes(take_bytes(bytes)?);
+    let days = i32::try_from(julian_day as i64 - JULIAN_DAY_OF_CE + 1).map_err(|_| Error::Decode(format!("{} is not a valid Julian day", julian_day)))?;
+    let date = NaiveDate::from_num_days_from_ce_opt(days).ok_or_else(|| Error::Decode(format!("{} is not a valid Julian day", julian_day)))?;
+    let millis = u32::from_le_bytes(take_bytes(bytes.get(4..).unwrap_or(&[]))?);
+    let time = match millis < MILLIS_PER_DAY {
+        true => NaiveTime::from_num_seconds_from_midnight_opt(millis / 1000, (millis % 1000) * 1_000_000),
+        false => None
+    };
+    let time = time.ok_or_else(|| Error::Decode(format!("{} is not a valid millisecond of a day", millis)))?;
+    Ok(Some(date.and_time(time)))
+}
+
+/// Serialize `T` field. It is the inverse of `parse_datetime`.
+/// Sub-millisecond part of the time is dropped.
+fn datetime_bytes(value: Option<&NaiveDateTime>) -> Result<[u8; 8]> {
+    let mut bytes = [0u8; 8];
+    if let Some(value) = value {
+        let julian_day = i32::try_from(value.num_days_from_ce() as i64 - 1 + JULIAN_DAY_OF_CE).map_err(|_| Error::Encode(format!("{} is out of Julian day range", value)))?;
+        // leap second is kept in the last millisecond of the day
+        let millis = (value.num_seconds_from_midnight() * 1000 + value.nanosecond() / 1_000_000).min(MILLIS_PER_DAY - 1);
+        bytes[..4].copy_from_slice(&julian_day.to_le_bytes());
+        bytes[4..].copy_from_slice(&millis.to_le_bytes());
+    }
+    Ok(bytes)
+}
+
+/// A `T` field. Empty field, i.e. all zero, is read as `None` by `Option<NaiveDateTime>`
+/// and is an error when it is read as `NaiveDateTime`.
 #[derive(Clone)]
-pub struct RawFloatField {
-    bytes: MemReferer<[u8]>,
-    integer: u8,
-    precision: u8
+pub struct RawDateTimeField {
+    bytes: MemReferer<[u8]>
 }
 
-impl ConversionField<f32> for RawFloatField {
-    fn get(&self) -> f32 {
-        let mut buffer = String::with_capacity(self.bytes.len());
-        let (result, readed, _) = get_decoder("ISO-8859-1").decode_to_string((&*self.bytes).try_into().expect("Fail to read value from byte array"), &mut buffer, true);
-        if readed != self.bytes.len() {
-            match result {
-                CoderResult::InputEmpty => panic!("Insufficient input to read for Numeric/Float field. Please report an issue."),
-                CoderResult::OutputFull => panic!("Insufficient buffer to write for Numeric/Float field. Please report an issue.")
-            }
+impl RawDateTimeField {
+    /// Take the field bytes out of a record
+    pub fn new(bytes: &[u8]) -> RawDateTimeField {
+        RawDateTimeField {
+            bytes: MemReferer::from(bytes)
         }
-        buffer.parse().expect("Fail to convert buffered string to float. Please file an issue.")
     }
 
-    fn set(&mut self, value: &f32) {
-        let buffer = format!("{0:0>0decimal$.precision$}", *value, decimal=self.integer as usize, precision=self.precision as usize);
-
-        let mut bytes = Vec::with_capacity(buffer.len());
-        unsafe {
-            bytes.set_len(buffer.len());
-        }
-        let (result, read, write, _) = get_encoder("ISO-8859-1").encode_from_utf8(&buffer, bytes.as_mut_slice(), false);
-        if read != buffer.len() {
-            match result {
-                CoderResult::InputEmpty => {
-                    panic!("Insufficient input to filed buffer or input is emptied");
-                },
-                CoderResult::OutputFull => {
-                    panic!("Insufficient buffered output allocated");
-                }
-            }
-        }
-        bytes.truncate(write);
-        self.bytes = bytes.into_boxed_slice();
+    /// Field bytes to be put back into a record
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
     }
 }
 
-/// Alias of 32 bits float but represent as char on disk
-pub type RawNumericField = RawFloatField;
+impl ConversionField<Option<NaiveDateTime>> for RawDateTimeField {
+    fn get(&self) -> Result<Option<NaiveDateTime>> {
+        parse_datetime(&self.bytes)
+    }
+
+    fn set(&mut self, value: &Option<NaiveDateTime>) -> Result<()> {
+        self.bytes = MemReferer::from(datetime_bytes(value.as_ref())?);
+        Ok(())
+    }
+}
+
+impl ConversionField<NaiveDateTime> for RawDateTimeField {
+    fn get(&self) -> Result<NaiveDateTime> {
+        parse_datetime(&self.bytes)?.ok_or_else(|| Error::Decode("DateTime field is empty".to_owned()))
+    }
+
+    fn set(&mut self, value: &NaiveDateTime) -> Result<()> {
+        self.bytes = MemReferer::from(datetime_bytes(Some(value))?);
+        Ok(())
+    }
+}
+
+#[derive(Clone)]
+pub struct DateTimeField<'a> {
+    pub meta: Field,
+    ready: Option<()>,
+    content: Option<NaiveDateTime>,
+    record: &'a [u8]
+}
+
+impl<'a> DateTimeField<'a> {
+    /// Create a datetime field that will read its content from given record
+    /// once [from_record_bytes](../trait.FieldOps.html#tymethod.from_record_bytes) is called.
+    pub fn new(meta: Field, record: &'a [u8]) -> DateTimeField<'a> {
+        DateTimeField {
+            meta,
+            ready: None,
+            content: None,
+            record
+        }
+    }
+}
+
+impl<'a> FieldMeta for DateTimeField<'a> {
+    fn nullable(&self) -> bool {
+        self.meta.nullable()
+    }
+    fn autoincrement(&self) -> bool {
+        self.meta.autoincrement()
+    }
+    fn datatype_flag(&self) -> u8 {
+        b'T'
+    }
+    fn name(&self) -> &str {
+        self.meta.name()
+    }
+    fn rec_offset(&self) -> usize {
+        self.meta.rec_offset()
+    }
+    fn size(&self) -> usize {
+        self.meta.size()
+    }
+    fn precision(&self) -> usize {
+        self.meta.precision()
+    }
+    fn next_id(&mut self) -> u32 {
+        self.meta.next_id()
+    }
+}
+
+/// Empty field is displayed as empty string
+impl<'a> Display for DateTimeField<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.content {
+            Some(ref content) => write!(f, "{}", content),
+            None => Ok(())
+        }
+    }
+}
+
+impl<'a> FieldOps for DateTimeField<'a> {
+
+    fn from_record_bytes(&mut self) -> BoxFuture<'_, Result<()>> {
+        Box::pin(
+            async move {
+                let field = field_slice(self.record, &self.meta)?;
+                self.content = parse_datetime(field)?;
+                self.ready = Some(());
+                Ok(())
+            }
+        )
+    }
+
+    fn to_bytes(&self) -> BoxFuture<'_, Result<&[u8]>> {
+        Box::pin(
+            async move {
+                record_slice(self.record, self.meta.rec_offset(), self.meta.size())
+            }    
+        )
+    }
 
+    fn ready(&self) -> bool {
+        self.ready.is_some()
+    }
+}
+
+/// A `N` or `F` field which store a number as ASCII text of at most 20 characters,
+/// right-justified and padded with spaces, e.g. `"  -12.50"` of `N(8,2)`.
+/// The width include the "." symbol and "-" sign.
+/// 
+/// Example of max number of digit:
+/// 
+/// 12345678901234567890
+/// 
+/// -1234567890123456789
+/// 
+/// 1234567890123456.123
+/// 
+/// -1234567890.12345678
+/// 
+/// It can be read as [Decimal](struct.Decimal.html) without losing any digit.
+/// Field that is filled with spaces has no value. Field that is filled with `*` hold
+/// a value that was too large for the field when it was written.
+/// Reading either of them as `Option<Decimal>` give `None` while reading them as `Decimal`
+/// or `f32` is `Error::Decode`.
+/// 
+/// Writing round the value to `precision` digits after decimal point and return
+/// `Error::Overflow` if it doesn't fit the width of the field.
+#[derive(Clone)]
+pub struct RawFloatField {
+    bytes: MemReferer<[u8]>,
+    integer: u8,
+    precision: u8
+}
+
+impl RawFloatField {
+    /// Take the field bytes out of a record.
+    /// The whole bytes is the width of the number with `precision` digits after the decimal point.
+    pub fn new(bytes: &[u8], precision: usize) -> RawFloatField {
+        RawFloatField {
+            bytes: MemReferer::from(bytes),
+            integer: bytes.len() as u8,
+            precision: precision as u8
+        }
+    }
+
+    /// Return true if the field is filled with spaces
+    pub fn is_blank(&self) -> bool {
+        self.bytes.iter().all(|b| *b == b' ' || *b == 0)
+    }
+
+    /// Return true if the field is filled with `*`, i.e. the value was too large
+    pub fn is_overflow(&self) -> bool {
+        self.bytes.contains(&b'*') && self.bytes.iter().all(|b| *b == b'*' || *b == b' ')
+    }
+
+    /// Field bytes to be put back into a record
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Replace the field with given text right-justified
+    fn set_text(&mut self, text: &str) -> Result<()> {
+        let width = self.integer as usize;
+        if text.len() > width {
+            return Err(Error::Overflow {
+                size: text.len(),
+                max: width
+            });
+        }
+        self.bytes = format!("{:>width$}", text, width = width).into_bytes().into();
+        Ok(())
+    }
+}
+
+impl ConversionField<Option<Decimal>> for RawFloatField {
+    fn get(&self) -> Result<Option<Decimal>> {
+        if self.is_blank() || self.is_overflow() {
+            return Ok(None);
+        }
+        decode("ISO-8859-1", &self.bytes)?.trim_matches(|c| c == ' ' || c == '\0').parse().map(Some)
+    }
+
+    fn set(&mut self, value: &Option<Decimal>) -> Result<()> {
+        match value {
+            Some(value) => self.set_text(&value.rescale(self.precision as u32)?.to_string()),
+            None => self.set_text("")
+        }
+    }
+}
+
+impl ConversionField<Decimal> for RawFloatField {
+    fn get(&self) -> Result<Decimal> {
+        match ConversionField::<Option<Decimal>>::get(self)? {
+            Some(value) => Ok(value),
+            None if self.is_overflow() => Err(Error::Decode(format!("{:?} is a numeric overflow", decode("ISO-8859-1", &self.bytes)?))),
+            None => Err(Error::Decode("Numeric field is blank".to_owned()))
+        }
+    }
+
+    fn set(&mut self, value: &Decimal) -> Result<()> {
+        self.set(&Some(*value))
+    }
+}
+
+impl ConversionField<f32> for RawFloatField {
+    fn get(&self) -> Result<f32> {
+        let value: Decimal = self.get()?;
+        Ok(value.to_f64() as f32)
+    }
+
+    fn set(&mut self, value: &f32) -> Result<()> {
+        self.set(&Decimal::from_f64(*value as f64, self.precision as u32)?)
+    }
+}
+
+/// Alias of 32 bits float but represent as char on disk
+pub type RawNumericField = RawFloatField;
+
+/// Content of `V` or `Q` field without padding.
+///
+/// Content that fill the whole field is stored as is. Shorter content is padded with 0
+/// and its length is stored in the last byte of the field. Such field has its variable
+/// length bit in `_NullFlags` set. See [Schema](struct.Schema.html#null-flags).
+fn varlength_content(bytes: &[u8], varlength: bool) -> Result<&[u8]> {
+    if !varlength {
+        return Ok(bytes);
+    }
+    let (length, content) = bytes.split_last().ok_or(Error::Truncated {
+        expected: 1,
+        found: 0
+    })?;
+    content.get(..*length as usize).ok_or_else(|| Error::Decode(format!("Variable length {} is longer than field of {} bytes", length, content.len())))
+}
+
+/// Field bytes of `V` or `Q` field holding given content.
+/// It return the bytes along with whether the variable length bit shall be set.
+fn varlength_bytes(content: &[u8], max_length: usize) -> Result<(Vec<u8>, bool)> {
+    if content.len() > max_length {
+        return Err(Error::Overflow {
+            size: content.len(),
+            max: max_length
+        });
+    }
+    if content.len() == max_length {
+        return Ok((content.to_vec(), false));
+    }
+    let mut bytes = vec![0u8; max_length];
+    bytes[..content.len()].copy_from_slice(content);
+    bytes[max_length - 1] = content.len() as u8;
+    Ok((bytes, true))
+}
+
+/// A `V` field.
+///
+/// The bytes are the whole field. Whether the last byte is the length of the content
+/// is told by the variable length bit in `_NullFlags` which is given by
+/// [with_varlength](struct.RawVarCharField.html#method.with_varlength).
+/// After `set`, [is_varlength](struct.RawVarCharField.html#method.is_varlength) tell
+/// how the bit shall be written.
 #[derive(Clone)]
 pub struct RawVarCharField {
     bytes: MemReferer<[u8]>,
     encoding: String,
-    max_length: usize
+    max_length: usize,
+    varlength: bool
+}
+
+impl RawVarCharField {
+    /// Take the field bytes out of a record.
+    /// The bytes are decoded using given codepage and can't be longer than `max_length` bytes.
+    pub fn new(bytes: &[u8], encoding: &str, max_length: usize) -> RawVarCharField {
+        RawVarCharField {
+            bytes: MemReferer::from(bytes),
+            encoding: encoding.to_owned(),
+            max_length,
+            varlength: false
+        }
+    }
+
+    /// Tell whether variable length bit of this field is set
+    pub fn with_varlength(mut self, varlength: bool) -> RawVarCharField {
+        self.varlength = varlength;
+        self
+    }
+
+    /// Return true if the content is shorter than the field
+    pub fn is_varlength(&self) -> bool {
+        self.varlength
+    }
+
+    /// Field bytes to be put back into a record
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
 }
 
 impl ConversionField<String> for RawVarCharField {
-    fn get(&self) -> String {
-        let mut value = String::with_capacity(self.bytes.len());
-        let (coderesult, size, _) = get_decoder(self.encoding.as_str()).decode_to_string(&*self.bytes, &mut value, false);
-
-        if size != self.bytes.len() {
-            match coderesult {
-                CoderResult::InputEmpty => {
-                    panic!("Insufficient input to read or input is emptied");
-                },
-                CoderResult::OutputFull => {
-                    panic!("Insufficient output buffer for filling");
-                }
+    fn get(&self) -> Result<String> {
+        decode(self.encoding.as_str(), varlength_content(&self.bytes, self.varlength)?)
+    }
+
+    fn set(&mut self, value: &String) -> Result<()> {
+        let (bytes, varlength) = varlength_bytes(&encode(self.encoding.as_str(), value)?, self.max_length)?;
+        self.bytes = MemReferer::from(bytes);
+        self.varlength = varlength;
+        Ok(())
+    }
+}
+
+/// A `Q` field. It stores the content the same way as [RawVarCharField](struct.RawVarCharField.html).
+#[derive(Clone)]
+pub struct RawVarBinField {
+    bytes: MemReferer<[u8]>,
+    max_length: usize,
+    varlength: bool
+}
+
+impl<'a> RawVarBinField {
+    /// Take the field bytes out of a record.
+    /// The content can't be longer than `max_length` bytes.
+    pub fn new(bytes: &[u8], max_length: usize) -> RawVarBinField {
+        RawVarBinField {
+            bytes: MemReferer::from(bytes),
+            max_length,
+            varlength: false
+        }
+    }
+
+    /// Tell whether variable length bit of this field is set
+    pub fn with_varlength(mut self, varlength: bool) -> RawVarBinField {
+        self.varlength = varlength;
+        self
+    }
+
+    /// Return true if the content is shorter than the field
+    pub fn is_varlength(&self) -> bool {
+        self.varlength
+    }
+
+    pub fn get(&'a self) -> Result<&'a [u8]> {
+        varlength_content(&self.bytes, self.varlength)
+    }
+
+    pub fn set(&mut self, value: &[u8]) -> Result<()> {
+        let (bytes, varlength) = varlength_bytes(value, self.max_length)?;
+        self.bytes = MemReferer::from(bytes);
+        self.varlength = varlength;
+        Ok(())
+    }
+
+    /// Field bytes to be put back into a record
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+/// A FoxPro table read from a DBF file.
+/// 
+/// It keeps the [Header](../struct.Header.html) and every [Field](struct.Field.html) of
+/// the file along with all the records parsed by `T`.
+/// Each record is handed to [RecordOps::from_bytes_with_schema](../trait.RecordOps.html#method.from_bytes_with_schema)
+/// as a whole, including the deletion flag at byte 0, so field offset stored in
+/// `Field::offset` can be used as is.
+/// 
+/// It implements [TableOps](../trait.TableOps.html) so it can be queried using `select`,
+/// `join`, `aggregate` and `update` the same way as `InMemoryTable`.
+/// 
+/// A table that is opened from file write every change made by `update` and `insert_owned`
+/// back to the file. Changes made by directly indexing into the table stay in memory.
+/// If the table has a structural index, every tag of it is kept up to date with those changes
+/// and [pack](struct.Table.html#method.pack).
+/// 
+/// Tag that has FOR clause or a key expression that [KeyExpr](index/struct.KeyExpr.html) doesn't
+/// support, e.g. `DELETED()` or `ALLTRIM(name)`, cannot be kept up to date. If the structural index
+/// has any of them, `insert_owned`, `update`, `pack` and `reindex` fail with `Error::Unsupported`
+/// before anything is written, so the index never goes out of sync with the table. Such table
+/// can still be read, deleted from and recalled.
+/// 
+/// The table keeps track of deletion flag of each record by itself. Deleted rows are kept
+/// until [pack](struct.Table.html#method.pack) is called but they are skipped by `iter`.
+pub struct Table<T> where T: RecordOps {
+    pub header: Header,
+    pub fields: Vec<Field>,
+    schema: Schema,
+    path: Option<std::path::PathBuf>,
+    rows: Vec<T>,
+    deleted: Vec<bool>
+}
+
+impl<T> Table<T> where T: RecordOps {
+    /// Open a FoxPro table from given path.
+    /// 
+    /// It read the header, all the field subrecords then every record in the file.
+    pub async fn open<P: AsRef<std::path::Path> + Display>(path: P) -> Result<Table<T>> {
+        let header = read_header(&path, cp_mapper).await?;
+        let mut f = File::open(&path)?;
+        let fields = read_fields(&mut f, &header).await?;
+        f.seek(SeekFrom::Start(header.first_record_position as u64))?;
+
+        let schema = Schema::new(fields.clone(), header.codepage);
+        let mut buffer = vec![0u8; header.record_len];
+        let mut rows = Vec::with_capacity(header.records_count);
+        let mut deleted = Vec::with_capacity(header.records_count);
+        for _ in 0..header.records_count {
+            read_full(&mut f, &mut buffer)?;
+            rows.push(T::from_bytes_with_schema(&buffer, &schema)?);
+            deleted.push(buffer.first() == Some(&b'*'));
+        }
+
+        Ok(Table {
+            header,
+            fields,
+            schema,
+            path: Some(path.as_ref().to_path_buf()),
+            rows,
+            deleted
+        })
+    }
+
+    /// Fields of this table which can be looked up by name
+    pub fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
+    /// Open the memo file that come along with this table.
+    /// The memo file has the same name as the table but with `.fpt` extension.
+    /// It return `None` if the table isn't bound to any file or there's no memo file.
+    pub fn open_memo(&self) -> Result<Option<MemoFile>> {
+        match self.memo_path() {
+            Some(p) => Ok(Some(MemoFile::open(p, self.header.codepage)?)),
+            None => Ok(None)
+        }
+    }
+
+    /// Same as [open_memo](struct.Table.html#method.open_memo) but the memo file
+    /// is opened for writing as well.
+    pub fn open_memo_for_write(&self) -> Result<Option<MemoFile>> {
+        match self.memo_path() {
+            Some(p) => Ok(Some(MemoFile::open_rw(p, self.header.codepage)?)),
+            None => Ok(None)
+        }
+    }
+
+    /// Rewrite the memo file so that it contains only memo referenced by records.
+    /// 
+    /// Memo blocks that are no longer referenced, e.g. replaced by a bigger memo, are dropped.
+    /// Every `M`, `G`, `P` and `W` field of every record, including deleted one, is pointed to
+    /// its new block and each row is parsed again by `T` so it see the new block number.
+    /// It does nothing if the table isn't bound to any file or there's no memo file.
+    ///
+    /// Both the memo and the records are written into new files which replace the old ones
+    /// only once everything is written. The old memo is kept aside until the new table is in
+    /// place and is put back if the table cannot be replaced, so that records never point to
+    /// blocks of other memo.
+    pub fn pack_memo(&mut self) -> Result<()> {
+        let (memo_path, table_path, mut f) = match (self.memo_path(), self.path.clone(), self.open_for_write()?) {
+            (Some(m), Some(t), Some(f)) => (m, t, f),
+            _ => return Ok(())
+        };
+        // nothing is written into the table until both packed files are complete
+        let packed_path = memo_path.with_extension("fpt.pack");
+        let mut packed_table_path = table_path.clone().into_os_string();
+        packed_table_path.push(".pack");
+        let packed_table_path = std::path::PathBuf::from(packed_table_path);
+        let last_update = self.header.last_update;
+        let records = self.packed_records(&memo_path, &packed_path, &mut f);
+        // the table is closed so that it can be replaced
+        drop(f);
+        let packed = records.and_then(|records| {
+            std::fs::copy(&table_path, &packed_table_path)?;
+            let mut packed_table = std::fs::OpenOptions::new().read(true).write(true).open(&packed_table_path)?;
+            let mut rows = Vec::with_capacity(records.len());
+            for (i, (bytes, row)) in records.into_iter().enumerate() {
+                self.write_record(&mut packed_table, i, &bytes)?;
+                rows.push(row);
+            }
+            self.touch_header(&mut packed_table)?;
+            replace_packed(&packed_path, &memo_path, &packed_table_path, &table_path)?;
+            Ok(rows)
+        });
+        match packed {
+            Ok(rows) => {
+                self.rows = rows;
+                Ok(())
+            },
+            Err(err) => {
+                self.header.last_update = last_update;
+                let _ = std::fs::remove_file(&packed_path);
+                let _ = std::fs::remove_file(&packed_table_path);
+                Err(err)
+            }
+        }
+    }
+
+    /// Copy memo of every record into a new memo file at `packed_path`.
+    /// It return bytes of every record pointed to its new blocks, along with the row parsed from it.
+    fn packed_records(&self, memo_path: &std::path::Path, packed_path: &std::path::Path, f: &mut File) -> Result<Vec<(Vec<u8>, T)>> {
+        let codepage = self.header.codepage;
+        let mut old_memo = MemoFile::open(memo_path, codepage)?;
+        // block size is read from 2 bytes field of memo header so it always fit in u16
+        let block_size = old_memo.block_size() as u16;
+        let mut new_memo = MemoFile::create(packed_path, block_size, codepage)?;
+        let memo_fields: Vec<&Field> = self.fields.iter().filter(|field| matches!(field.datatype, b'M' | b'G' | b'P' | b'W')).collect();
+
+        let mut records = Vec::with_capacity(self.rows.len());
+        for i in 0..self.rows.len() {
+            let mut buffer = vec![0u8; self.header.record_len];
+            f.seek(SeekFrom::Start(self.record_position(i)))?;
+            read_full(f, &mut buffer)?;
+            for field in memo_fields.iter() {
+                let block_bytes = field_slice(&buffer, field)?;
+                let block = memo::parse_block_number(block_bytes)?;
+                let new_block = match old_memo.read_block(block)? {
+                    Some(MemoBlock {memo_type, content}) => new_memo.replace_block(0, memo_type, &content)?,
+                    None => 0
+                };
+                let new_bytes = memo::block_number_bytes(new_block, block_bytes.len())?;
+                buffer[field.offset..field.offset + field.size].copy_from_slice(&new_bytes);
+            }
+            let row = T::from_bytes_with_schema(&buffer, &self.schema)?;
+            records.push((buffer, row));
+        }
+        Ok(records)
+    }
+
+    /// Path to existing memo file of this table
+    fn memo_path(&self) -> Option<std::path::PathBuf> {
+        self.sibling_path(&["fpt", "FPT"])
+    }
+
+    /// Open the structural compound index of this table.
+    /// The index file has the same name as the table but with `.cdx` extension.
+    /// It return `None` if the table isn't bound to any file or the header doesn't
+    /// mark the table as having structural index.
+    pub fn open_cdx(&self) -> Result<Option<index::Cdx>> {
+        match self.cdx_path()? {
+            Some(p) => Ok(Some(index::Cdx::open(p, &self.schema)?)),
+            None => Ok(None)
+        }
+    }
+
+    /// Same as [open_cdx](struct.Table.html#method.open_cdx) but the index is opened
+    /// for writing as well.
+    pub fn open_cdx_for_write(&self) -> Result<Option<index::Cdx>> {
+        match self.cdx_path()? {
+            Some(p) => Ok(Some(index::Cdx::open_rw(p, &self.schema)?)),
+            None => Ok(None)
+        }
+    }
+
+    /// Open single index file of given name that is in the same directory as this table,
+    /// e.g. `custno` open `custno.idx`. Upper case name and extension are looked for as well.
+    /// It return `None` if the table isn't bound to any file or there's no such file.
+    pub fn open_idx(&self, name: &str) -> Result<Option<index::Idx>> {
+        match self.idx_path(name) {
+            Some(p) => Ok(Some(index::Idx::open(p, &self.schema)?)),
+            None => Ok(None)
+        }
+    }
+
+    /// Name of every single index file in the same directory as this table whose key
+    /// expression refers to fields of this table, in order of name. The name is the file name
+    /// without extension, as [open_idx](struct.Table.html#method.open_idx) takes it.
+    /// File that cannot be read as index is skipped.
+    /// It is empty if the table isn't bound to any file.
+    pub fn idx_names(&self) -> Result<Vec<String>> {
+        let dir = match self.path.as_ref().and_then(|p| p.parent()) {
+            Some(dir) if dir.as_os_str().is_empty() => std::path::Path::new("."),
+            Some(dir) => dir,
+            None => return Ok(Vec::new())
+        };
+        let mut names = Vec::new();
+        for entry in std::fs::read_dir(dir)? {
+            let path = entry?.path();
+            if !path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("idx")) || !path.is_file() {
+                continue;
+            }
+            let idx = match index::Idx::open(&path, &self.schema) {
+                Ok(idx) => idx,
+                Err(_) => continue
+            };
+            if index::KeyExpr::refers_to(&idx.tag().expression, &self.schema) {
+                names.extend(path.file_stem().map(|stem| stem.to_string_lossy().into_owned()));
             }
         }
-        value.truncate(size);
+        names.sort();
+        Ok(names)
+    }
+
+    /// Same as [open_idx](struct.Table.html#method.open_idx) but the index is opened
+    /// for writing as well.
+    pub fn open_idx_for_write(&self, name: &str) -> Result<Option<index::Idx>> {
+        match self.idx_path(name) {
+            Some(p) => Ok(Some(index::Idx::open_rw(p, &self.schema)?)),
+            None => Ok(None)
+        }
+    }
+
+    /// Rebuild single index file of given name from scratch out of the records in the file.
+    ///
+    /// Only structural index is kept up to date as the table is written, like FoxPro which
+    /// update `.idx` file only while it is opened by `SET INDEX TO`.
+    pub fn reindex_idx(&self, name: &str) -> Result<()> {
+        let mut idx = self.open_idx_for_write(name)?.ok_or_else(|| Error::TagNotFound(name.to_owned()))?;
+        idx.reindex(self.records_in_file()?, &self.schema)
+    }
+
+    /// Rebuild every tag of the structural index from scratch out of the records in the file,
+    /// including deleted one.
+    /// It does nothing if the table isn't bound to any file or has no structural index.
+    /// It fails with `Error::Unsupported` if any tag has FOR clause or a key expression that
+    /// isn't supported.
+    pub fn reindex(&self) -> Result<()> {
+        match self.maintained_cdx()? {
+            Some(mut cdx) => self.rebuild_index(&mut cdx),
+            None => Ok(())
+        }
+    }
+
+    /// Path to structural index of this table
+    fn cdx_path(&self) -> Result<Option<std::path::PathBuf>> {
+        if !self.header.has_structural_cdx() {
+            return Ok(None);
+        }
+        match (self.sibling_path(&["cdx", "CDX"]), &self.path) {
+            (Some(p), _) => Ok(Some(p)),
+            (None, Some(p)) => Err(Error::Io(std::io::Error::new(std::io::ErrorKind::NotFound, format!("{} has no structural index", p.display())))),
+            (None, None) => Ok(None)
+        }
+    }
+
+    /// Structural index that is opened for writing after checking that every tag of it
+    /// can be kept up to date
+    fn maintained_cdx(&self) -> Result<Option<index::Cdx>> {
+        let cdx = self.open_cdx_for_write()?;
+        if let Some(ref cdx) = cdx {
+            cdx.check_writable()?;
+        }
+        Ok(cdx)
+    }
 
-        value
+    fn rebuild_index(&self, cdx: &mut index::Cdx) -> Result<()> {
+        cdx.reindex(self.records_in_file()?, &self.schema)
     }
 
-    fn set(&mut self, value: &String) {
-        // Just in case for DBCS
-        let mut bytes = Vec::with_capacity(value.len() * 2);
-        unsafe {
-            // This is safe and cheapest way to initialize byte vec that will be truncated to the content later.
-            bytes.set_len(value.len());
+    /// Every record in the file along with its record number
+    fn records_in_file(&self) -> Result<Vec<(u32, Vec<u8>)>> {
+        let mut f = File::open(self.path.as_ref().ok_or(Error::Unsupported("reindex table that isn't bound to a file"))?)?;
+        let mut records = Vec::with_capacity(self.rows.len());
+        for i in 0..self.rows.len() {
+            let mut buffer = vec![0u8; self.header.record_len];
+            f.seek(SeekFrom::Start(self.record_position(i)))?;
+            read_full(&mut f, &mut buffer)?;
+            records.push((record_number(i)?, buffer));
+        }
+        Ok(records)
+    }
+
+    /// Path to existing single index file of given name
+    fn idx_path(&self, name: &str) -> Option<std::path::PathBuf> {
+        let dir = self.path.as_ref()?.parent()?;
+        let name = name.trim();
+        let names = [name.to_owned(), name.to_lowercase(), name.to_uppercase()];
+        names.iter()
+            .flat_map(|name| ["idx", "IDX"].iter().map(move |ext| dir.join(format!("{}.{}", name, ext))))
+            .find(|p| p.exists())
+    }
+
+    /// Write `new` bytes of row `i`, whose record in the file is `old`, and move it to its new
+    /// keys in given index. Keys are built before the file is touched so that a bad key changes
+    /// nothing. If the index cannot take the new keys, the index and the file are put back the
+    /// way they were as far as they can be.
+    fn write_changed_row(&self, f: &mut File, cdx: Option<&mut index::Cdx>, i: usize, old: &[u8], new: &[u8]) -> Result<()> {
+        if let Some(ref cdx) = cdx {
+            cdx.record_keys(new, &self.schema)?;
+        }
+        if let Err(e) = self.write_record(f, i, new) {
+            let _ = self.write_record(f, i, old);
+            return Err(e);
+        }
+        let cdx = match cdx {
+            Some(cdx) => cdx,
+            None => return Ok(())
+        };
+        let record = record_number(i)?;
+        let indexed = match cdx.update_record(record, old, new, &self.schema) {
+            Ok(orphans) => match self.adopt_orphans(cdx, orphans) {
+                Ok(()) => Ok(()),
+                Err(e) => {
+                    let _ = cdx.update_record(record, new, old, &self.schema);
+                    Err(e)
+                }
+            },
+            Err(e) => Err(e)
+        };
+        if indexed.is_err() {
+            let _ = self.write_record(f, i, old);
+        }
+        indexed
+    }
+
+    /// Put the first record that has given key into each unique tag that lost its record
+    /// of the key, see [update_record](index/struct.Cdx.html#method.update_record).
+    fn adopt_orphans(&self, cdx: &mut index::Cdx, orphans: Vec<(String, Vec<u8>)>) -> Result<()> {
+        for (tag, key) in orphans {
+            let expr = match cdx.tag(&tag).and_then(|tag| tag.key()) {
+                Some(expr) => expr.clone(),
+                None => continue
+            };
+            for (record, bytes) in self.records_in_file()? {
+                if expr.key(&bytes, &self.schema)? == key {
+                    cdx.insert(&tag, &key, record)?;
+                    break;
+                }
+            }
         }
-        let (result, read, write, _) = get_encoder(self.encoding.as_str()).encode_from_utf8(value, bytes.as_mut_slice(), false);
-        if read != value.len() {
-            match result {
-                CoderResult::InputEmpty => {
-                    panic!("Insufficient input to filed buffer or input is emptied");
+        Ok(())
+    }
+
+    /// Rows whose key of given index tag starts with given value, in index order.
+    ///
+    /// The tag is looked up in structural index then, if there's no such tag, the single index
+    /// file of the same name is used, see [open_idx](struct.Table.html#method.open_idx).
+    ///
+    /// The value is turned into key by [KeyType](index/enum.KeyType.html#method.search_key)
+    /// of the tag so character value match every key that starts with it, like `SEEK` with
+    /// `SET EXACT OFF`. Rows are read from the file, not taken from this table.
+    /// Deleted rows are skipped.
+    pub fn seek(&self, tag: &str, key: &Value) -> Result<Vec<T>> {
+        self.range(tag, key..=key)?.collect()
+    }
+
+    /// Rows whose key of given index tag lies within given range, in index order.
+    ///
+    /// Bounds are turned into keys the same way as [seek](struct.Table.html#method.seek) so
+    /// `Value::Char("A".to_owned())..=Value::Char("B".to_owned())` include every key that
+    /// starts with `B`. Rows of descending tag are ordered from the end of range.
+    /// The index is opened right away while its keys and rows are read as the result is iterated.
+    pub fn range<B: RangeBounds<Value>>(&self, tag: &str, range: B) -> Result<IndexedRecords<T>> {
+        let path = self.path.as_ref().ok_or(Error::Unsupported("index lookup on table that isn't bound to a file"))?;
+        let found = match self.open_cdx()? {
+            Some(cdx) => match cdx.tag(tag) {
+                Some(found) => {
+                    let bounds = self.key_bounds(found, &range)?;
+                    Some(cdx.into_range(tag, bounds)?)
+                },
+                None => None
+            },
+            None => None
+        };
+        // single index file is only opened when structural index has no such tag
+        let entries = match found {
+            Some(entries) => entries,
+            None => match self.open_idx(tag)? {
+                Some(idx) => {
+                    let bounds = self.key_bounds(idx.tag(), &range)?;
+                    idx.into_range(bounds)?
                 },
-                CoderResult::OutputFull => {
-                    panic!("Insufficient buffered output allocated");
+                None => return Err(Error::TagNotFound(tag.to_owned()))
+            }
+        };
+        Ok(IndexedRecords::new(File::open(path)?, entries, &self.header, self.schema.clone()))
+    }
+
+    /// Turn bounds of value into bounds of key of given tag
+    fn key_bounds<B: RangeBounds<Value>>(&self, tag: &index::Tag, range: &B) -> Result<KeyBounds> {
+        let key_type = tag.key().ok_or(Error::Unsupported("index lookup on tag whose key expression isn't supported"))?.key_type();
+        let (key_len, codepage) = (tag.key_len(), self.header.codepage);
+        let to_key = |bound: Bound<&Value>| -> Result<Bound<Vec<u8>>> {
+            Ok(match bound {
+                Bound::Included(value) => Bound::Included(key_type.search_key(value, key_len, codepage)?),
+                Bound::Excluded(value) => Bound::Excluded(key_type.search_key(value, key_len, codepage)?),
+                Bound::Unbounded => Bound::Unbounded
+            })
+        };
+        Ok((to_key(range.start_bound())?, to_key(range.end_bound())?))
+    }
+
+    /// Path to existing file that has the same name as this table but with one of given extensions
+    fn sibling_path(&self, extensions: &[&str]) -> Option<std::path::PathBuf> {
+        let path = self.path.as_ref()?;
+        extensions.iter()
+            .map(|ext| path.with_extension(ext))
+            .find(|p| p.exists())
+    }
+
+    /// Mark record `i` as deleted.
+    /// The record stay in the table until [pack](struct.Table.html#method.pack) is called.
+    /// Like FoxPro, index keeps keys of deleted record until then.
+    pub fn delete(&mut self, i: usize) -> Result<()> {
+        self.set_deleted(i, true)
+    }
+
+    /// Remove deletion mark from record `i`.
+    pub fn recall(&mut self, i: usize) -> Result<()> {
+        self.set_deleted(i, false)
+    }
+
+    fn set_deleted(&mut self, i: usize, deleted: bool) -> Result<()> {
+        if i >= self.rows.len() {
+            return Err(Error::RecordNotFound(i));
+        }
+
+        if let Some(mut f) = self.open_for_write()? {
+            f.seek(SeekFrom::Start(self.record_position(i)))?;
+            f.write_all(&[deletion_flag(deleted)])?;
+            self.touch_header(&mut f)?;
+        }
+        self.deleted[i] = deleted;
+        Ok(())
+    }
+
+    /// Permanently remove every record that is marked as deleted.
+    /// 
+    /// If the table is bound to a file, the remaining records are moved toward the
+    /// beginning of the record area, the file is truncated right after the EOF marker and
+    /// number of records in the header is updated. Structural index is rebuilt by
+    /// [reindex](struct.Table.html#method.reindex) as records are numbered anew, so it fails
+    /// the same way as `reindex` before anything is written.
+    pub fn pack(&mut self) -> Result<()> {
+        let mut cdx = self.maintained_cdx()?;
+        if let Some(mut f) = self.open_for_write()? {
+            let mut buffer = vec![0u8; self.header.record_len];
+            let mut kept = 0;
+            for i in 0..self.rows.len() {
+                if self.deleted[i] {
+                    continue;
+                }
+                if kept != i {
+                    f.seek(SeekFrom::Start(self.record_position(i)))?;
+                    read_full(&mut f, &mut buffer)?;
+                    f.seek(SeekFrom::Start(self.record_position(kept)))?;
+                    f.write_all(&buffer)?;
                 }
+                kept += 1;
             }
+            f.seek(SeekFrom::Start(self.record_position(kept)))?;
+            f.write_all(&[0x1A])?;
+            f.set_len(self.record_position(kept) + 1)?;
+            self.header.records_count = kept;
+            self.touch_header(&mut f)?;
+        }
+
+        let mut deleted = std::mem::take(&mut self.deleted).into_iter();
+        self.rows.retain(|_| !deleted.next().unwrap_or(false));
+        self.deleted = vec![false; self.rows.len()];
+        self.header.records_count = self.rows.len();
+        match cdx {
+            Some(ref mut cdx) => self.rebuild_index(cdx),
+            None => Ok(())
+        }
+    }
+
+    /// Open the underlying file for writing.
+    /// It return `None` if this table isn't bound to any file.
+    fn open_for_write(&self) -> Result<Option<File>> {
+        match self.path {
+            Some(ref p) => Ok(Some(std::fs::OpenOptions::new().read(true).write(true).open(p)?)),
+            None => Ok(None)
         }
-        bytes.truncate(write);
+    }
 
-        if bytes.len() > self.max_length {
-            panic!("Total bytes of value is {} bytes but the field max length is {} bytes.", bytes.len(), self.max_length)
-        } else {
-            self.bytes = bytes.into_boxed_slice();
+    /// Position of record `i` in the underlying file
+    fn record_position(&self, i: usize) -> u64 {
+        (self.header.first_record_position + i * self.header.record_len) as u64
+    }
+
+    /// Serialize row `i` as it is written to the file, with deletion flag taken from the table.
+    fn row_bytes(&self, i: usize) -> Result<Vec<u8>> {
+        let mut bytes = self.rows[i].to_bytes()?;
+        check_record_len(&bytes, self.header.record_len)?;
+        if let Some(flag) = bytes.first_mut() {
+            *flag = deletion_flag(self.deleted[i]);
+        }
+        Ok(bytes)
+    }
+
+    /// Read record `i` as it is in given file
+    fn read_record(&self, f: &mut File, i: usize) -> Result<Vec<u8>> {
+        let mut bytes = vec![0u8; self.header.record_len];
+        f.seek(SeekFrom::Start(self.record_position(i)))?;
+        read_full(f, &mut bytes)?;
+        Ok(bytes)
+    }
+
+    /// Write given bytes as record `i` in given file
+    fn write_record(&self, f: &mut File, i: usize, bytes: &[u8]) -> Result<()> {
+        f.seek(SeekFrom::Start(self.record_position(i)))?;
+        f.write_all(bytes)?;
+        Ok(())
+    }
+
+    /// Set last update date to today and write it along with number of records into given file.
+    fn touch_header(&mut self, f: &mut File) -> Result<()> {
+        self.header.last_update = chrono::Local::now().date_naive();
+        self.write_header(f)
+    }
+
+    /// Write last update date and number of records into given file.
+    fn write_header(&self, f: &mut File) -> Result<()> {
+        let bytes = header_bytes(&self.header, 0, 0)?;
+        f.seek(SeekFrom::Start(1))?;
+        f.write_all(&bytes[1..8])?;
+        Ok(())
+    }
+
+    /// Write a new record after the last one, then the end of file marker, next value of
+    /// autoincrement fields and the header.
+    fn append_record(&mut self, f: &mut File, bytes: &[u8], fields: &[Field]) -> Result<()> {
+        f.seek(SeekFrom::Start(self.record_position(self.rows.len())))?;
+        f.write_all(bytes)?;
+        f.write_all(&[0x1A])?;
+        write_next_ids(f, fields)?;
+        self.header.records_count = self.rows.len() + 1;
+        self.touch_header(f)
+    }
+
+    /// Append the row to given file and its keys to given structural index,
+    /// see [insert_owned](#method.insert_owned).
+    fn append_row(&mut self, f: &mut File, mut cdx: Option<&mut index::Cdx>, mut row: T) -> Result<()> {
+        let mut bytes = row.to_bytes()?;
+        check_record_len(&bytes, self.header.record_len)?;
+        let deleted = row.is_deleted() || bytes.first() == Some(&b'*');
+        let mut fields = self.fields.clone();
+        if assign_autoincrement(&mut fields, &mut bytes)? {
+            row = T::from_bytes_with_schema(&bytes, &self.schema)?;
         }
+        // keys are built before the file is touched so that a bad key changes nothing
+        let keys = match cdx {
+            Some(ref cdx) => cdx.record_keys(&bytes, &self.schema)?,
+            None => Vec::new()
+        };
+        let mut tail = Vec::new();
+        f.seek(SeekFrom::Start(self.record_position(self.rows.len())))?;
+        f.read_to_end(&mut tail)?;
+        let stamp = (self.header.records_count, self.header.last_update);
+        let appended = self.append_record(f, &bytes, &fields).and_then(|_| match cdx {
+            Some(ref mut cdx) => cdx.insert_keys(record_number(self.rows.len())?, &keys),
+            None => Ok(())
+        });
+        if let Err(e) = appended {
+            (self.header.records_count, self.header.last_update) = stamp;
+            self.undo_append(f, &tail)?;
+            return Err(e);
+        }
+        self.fields = fields;
+        self.rows.push(row);
+        self.deleted.push(deleted);
+        Ok(())
+    }
+
+    /// Put the file back the way it was before [append_record](#method.append_record).
+    /// The tail is whatever the file had after the last record, usually the end of file marker.
+    fn undo_append(&self, f: &mut File, tail: &[u8]) -> Result<()> {
+        let end = self.record_position(self.rows.len());
+        f.set_len(end)?;
+        f.seek(SeekFrom::Start(end))?;
+        f.write_all(tail)?;
+        write_next_ids(f, &self.fields)?;
+        self.write_header(f)
     }
 }
 
-#[derive(Clone)]
-pub struct RawVarBinField {
-    bytes: MemReferer<[u8]>,
-    max_length: usize
+/// Lower and upper bound of index key
+type KeyBounds = (Bound<Vec<u8>>, Bound<Vec<u8>>);
+
+/// Put packed memo and packed table in place of the memo and table.
+///
+/// The old memo is moved aside first and removed only once both files are replaced.
+/// If the table cannot be replaced, the old memo is put back so that the table and
+/// its memo still agree.
+fn replace_packed(packed_memo: &std::path::Path, memo: &std::path::Path, packed_table: &std::path::Path, table: &std::path::Path) -> Result<()> {
+    let backup = memo.with_extension("fpt.old");
+    std::fs::rename(memo, &backup)?;
+    let replaced = std::fs::rename(packed_memo, memo).and_then(|_| std::fs::rename(packed_table, table));
+    if let Err(err) = replaced {
+        std::fs::rename(&backup, memo)?;
+        return Err(err.into());
+    }
+    // both files are already replaced so a backup that is left behind does no harm
+    let _ = std::fs::remove_file(&backup);
+    Ok(())
 }
 
-impl<'a> RawVarBinField {
-    pub fn get(&'a self) -> &'a [u8] {
-        return &self.bytes
+/// Record number of row `i`, like `RECNO()` it starts at 1
+fn record_number(i: usize) -> Result<u32> {
+    u32::try_from(i + 1).map_err(|_| Error::RecordNotFound(i))
+}
+
+/// Read header and fields of a FoxPro table from given async source then return
+/// a [RecordStream](../struct.RecordStream.html) over its records.
+/// 
+/// Unlike [Table::open](struct.Table.html#method.open), records are read one by one
+/// as the stream is polled instead of being loaded into memory all at once.
+/// Every read goes through the source so it is as non-blocking as the source is.
+/// This crate doesn't depend on any async runtime so it doesn't provide an async file type.
+/// Use the one from your runtime, such as `async_std::fs::File`, or `tokio::fs::File`
+/// through `tokio_util::compat`.
+pub async fn stream_table<T, R>(mut source: R) -> Result<RecordStream<R, T>> 
+    where T: RecordOps, R: AsyncRead + AsyncSeek + Unpin 
+{
+    let (header, area) = read_header_area(&mut source, cp_mapper).await?;
+    let fields = parse_fields(&area, header.codepage)?;
+    Ok(RecordStream::new(source, &header).with_schema(Schema::new(fields, header.codepage)))
+}
+
+/// Rows of a table file read in the order of record numbers found in an index.
+///
+/// Record number `n`, starting at 1, is read from `first_record_position + (n - 1) * record_len`
+/// of the file as each row is taken. Deleted rows are skipped unless
+/// [include_deleted](struct.IndexedRecords.html#method.include_deleted) is set.
+/// Keys are read from the index as rows are taken, one leaf at a time.
+/// Both files are read with blocking IO so it is an `Iterator` rather than `Stream`.
+pub struct IndexedRecords<T> where T: RecordOps {
+    source: File,
+    records: index::RangeEntries<File>,
+    first_record_position: u64,
+    record_len: usize,
+    records_count: usize,
+    include_deleted: bool,
+    buffer: Vec<u8>,
+    schema: Schema,
+    _row: PhantomData<fn() -> T>
+}
+
+impl<T> IndexedRecords<T> where T: RecordOps {
+    fn new(source: File, records: index::RangeEntries<File>, header: &Header, schema: Schema) -> IndexedRecords<T> {
+        IndexedRecords {
+            source,
+            records,
+            first_record_position: header.first_record_position as u64,
+            record_len: header.record_len,
+            records_count: header.records_count,
+            include_deleted: false,
+            buffer: vec![0; header.record_len],
+            schema,
+            _row: PhantomData
+        }
+    }
+
+    /// By default, records that are marked as deleted are skipped.
+    /// Set `include` to true to also yield deleted records.
+    pub fn include_deleted(mut self, include: bool) -> IndexedRecords<T> {
+        self.include_deleted = include;
+        self
+    }
+
+    /// Read record of given number into the buffer
+    fn read_record(&mut self, record: u32) -> Result<()> {
+        if record == 0 || record as usize > self.records_count {
+            return Err(Error::RecordNotFound(record as usize));
+        }
+        let position = self.first_record_position + (record as u64 - 1) * self.record_len as u64;
+        self.source.seek(SeekFrom::Start(position))?;
+        read_full(&mut self.source, &mut self.buffer)
+    }
+}
+
+impl<T> Iterator for IndexedRecords<T> where T: RecordOps {
+    type Item = Result<T>;
+
+    fn next(&mut self) -> Option<Result<T>> {
+        loop {
+            let record = match self.records.next()? {
+                Ok(entry) => entry.record,
+                Err(err) => return Some(Err(err))
+            };
+            if let Err(err) = self.read_record(record) {
+                return Some(Err(err));
+            }
+            if self.include_deleted || self.buffer.first() != Some(&b'*') {
+                return Some(T::from_bytes_with_schema(&self.buffer, &self.schema));
+            }
+        }
+    }
+}
+
+impl<T> std::fmt::Debug for Table<T> where T: std::fmt::Debug + RecordOps {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{:?}", self.rows)
     }
+}
+
+/// Consume the table and yield every row that isn't marked as deleted.
+impl<T> IntoIterator for Table<T> where T: RecordOps {
+    type Item=T;
+    #[allow(clippy::type_complexity)]
+    type IntoIter=std::iter::FilterMap<std::iter::Zip<std::vec::IntoIter<T>, std::vec::IntoIter<bool>>, fn((T, bool)) -> Option<T>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.rows.into_iter().zip(self.deleted).filter_map(|(row, deleted)| if deleted { None } else { Some(row) })
+    }
+}
 
-    pub fn set(&mut self, value: &[u8]) {
-        if value.len() > self.max_length {
-            panic!("Given value is {} bytes where max length is {} bytes", value.len(), self.max_length)
-        } else {
-            self.bytes = Box::from(value)
+/// Build a table that is not bound to any file.
+/// The header is a default one with only `records_count` set and there is no field.
+impl<T> FromIterator<T> for Table<T> where T: RecordOps {
+    fn from_iter<I: IntoIterator<Item=T>>(iter: I) -> Self {
+        let rows: Vec<T> = iter.into_iter().collect();
+        let deleted = rows.iter().map(|r| r.is_deleted()).collect();
+        Table {
+            header: Header {
+                records_count: rows.len(),
+                ..Header::default()
+            },
+            fields: vec![],
+            schema: Schema::default(),
+            path: None,
+            rows,
+            deleted
         }
     }
 }
+
+impl<T> Index<usize> for Table<T> where T: RecordOps {
+    type Output=T;
+
+    fn index(&self, i: usize) -> &Self::Output {
+        &self.rows[i]
+    }
+}
+
+impl<T> IndexMut<usize> for Table<T> where T: RecordOps {
+    fn index_mut(&mut self, i: usize) -> &mut Self::Output {
+        &mut self.rows[i]
+    }
+}
+
+impl<T> TableOps for Table<T> where T: RecordOps {
+    type Row=T;
+
+    /// Append the row to this table.
+    /// If the table is bound to a file, the record is written at the end of file
+    /// followed by EOF marker and the header is updated with new number of records
+    /// and last update date.
+    /// 
+    /// Autoincrement `I` field is assigned its next value, replacing whatever the row has,
+    /// and the row is parsed again from the written record so it sees the assigned value.
+    /// The next value is written back to the field subrecord.
+    /// Key of the record is added to every tag of structural index. It fails with
+    /// `Error::Unsupported` if any tag cannot be kept up to date, see [Table](struct.Table.html).
+    /// If the index cannot be written, the record is taken out of the file again so that
+    /// the table, its file and the index still agree.
+    fn insert_owned(&mut self, row: Self::Row) -> Result<()> {
+        match self.open_for_write()? {
+            Some(mut f) => {
+                let mut cdx = self.maintained_cdx()?;
+                self.append_row(&mut f, cdx.as_mut(), row)
+            },
+            None => {
+                self.header.records_count = self.rows.len() + 1;
+                self.deleted.push(row.is_deleted());
+                self.rows.push(row);
+                Ok(())
+            }
+        }
+    }
+
+    /// Same as [insert_owned](#method.insert_owned) for each row but the file and its
+    /// structural index are opened only once. Rows before the one that fails are kept.
+    fn insert(&mut self, rows: &[Self::Row]) -> Result<()> where Self::Row: Clone {
+        match self.open_for_write()? {
+            Some(mut f) => {
+                let mut cdx = self.maintained_cdx()?;
+                rows.iter().try_for_each(|row| self.append_row(&mut f, cdx.as_mut(), row.clone()))
+            },
+            None => rows.iter().try_for_each(|row| self.insert_owned(row.clone()))
+        }
+    }
+
+    fn is_deleted(&self, i: usize) -> bool {
+        self.deleted[i]
+    }
+
+    /// Update each row using `op`.
+    /// If the table is bound to a file, only the rows whose bytes differ from their record
+    /// in the file after `op` are written back to their place in the file. Tags of structural
+    /// index whose key expression reads any changed field are updated along with them. It fails with
+    /// `Error::Unsupported` before `op` is called if any tag cannot be kept up to date.
+    /// If a row cannot be encoded or the index cannot take its new keys, the row is put back
+    /// the way it is in the file and the rest of rows are left as is.
+    fn update<F>(&mut self, mut op: F) -> Result<()> where for<'r> F: FnMut(&'r mut Self::Row) {
+        let mut f = self.open_for_write()?;
+        let mut cdx = match f {
+            Some(_) => self.maintained_cdx()?,
+            None => None
+        };
+        let mut changed = false;
+
+        for i in 0..self.rows.len() {
+            match f {
+                Some(ref mut f) => {
+                    // record as it is in the file, which the row is put back to if it cannot be written
+                    let old = self.read_record(f, i)?;
+                    op(&mut self.rows[i]);
+                    let written = match self.row_bytes(i) {
+                        Ok(new) if new == old => continue,
+                        Ok(new) => self.write_changed_row(f, cdx.as_mut(), i, &old, &new),
+                        Err(e) => Err(e)
+                    };
+                    if let Err(e) = written {
+                        if let Ok(row) = T::from_bytes_with_schema(&old, &self.schema) {
+                            self.rows[i] = row;
+                        }
+                        if changed {
+                            let _ = self.touch_header(f);
+                        }
+                        return Err(e);
+                    }
+                    changed = true;
+                },
+                None => op(&mut self.rows[i])
+            }
+        }
+
+        match f {
+            Some(ref mut f) if changed => self.touch_header(f),
+            _ => Ok(())
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.rows.len()
+    }
+}
+
+/// Write a new DBF file.
+/// 
+/// It write the header and field subrecords on [create](struct.TableWriter.html#method.create),
+/// append each record on [append](struct.TableWriter.html#method.append) then
+/// update the number of records and put the EOF marker on [finish](struct.TableWriter.html#method.finish).
+/// A file that isn't finished is reported as empty table.
+pub struct TableWriter {
+    f: File,
+    header: Header,
+    fields: Vec<Field>
+}
+
+impl TableWriter {
+    /// Create a new DBF file at given path, overwriting any existing file.
+    /// 
+    /// `header` is used as a template. Only `db_type`, `table_flag` and `codepage` are taken from it.
+    /// Last update date, number of records, position of first record and record length
+    /// are computed by the writer. Bit 0x01 of `table_flag` is cleared since the writer
+    /// doesn't write structural index.
+    /// 
+    /// Fields are laid out in given order right after the deletion flag so `Field::offset`
+    /// of given fields is ignored.
+    /// 
+    /// If a Visual FoxPro table has nullable, `V` or `Q` field but no `_NullFlags`,
+    /// the `_NullFlags` system field is added after the last field.
+    /// Visual FoxPro table that has `V` or `Q` field is written as `VisualFoxProVarBLOB`.
+    /// Otherwise, Visual FoxPro table that has autoincrement field is written as `VisualFoxProAutoInc`.
+    pub async fn create<P: AsRef<std::path::Path>>(path: P, header: &Header, fields: &[Field]) -> Result<TableWriter> {
+        let version = header.db_type.flag().ok_or_else(|| Error::BadHeader(format!("Cannot write table of type {:?}", header.db_type)))?;
+        let mark = cp_mark(header.codepage)?;
+
+        let mut fields = fields.to_vec();
+        let null_bits = fields.iter()
+            .map(|field| field.nullable.is_some() as usize + matches!(field.datatype, b'V' | b'Q') as usize)
+            .sum::<usize>();
+        if header.db_type.is_visual_foxpro() && null_bits > 0 && !fields.iter().any(Field::is_null_flags) {
+            fields.push(Field::null_flags(null_bits));
+        }
+        // Visual FoxPro 9 mark table that has V, Q or autoincrement field with its own type
+        let version = match header.db_type.is_visual_foxpro() {
+            true if fields.iter().any(|field| matches!(field.datatype, b'V' | b'Q')) => DBFType::VisualFoxProVarBLOB.flag().unwrap_or(version),
+            true if fields.iter().any(|field| field.autoincrement.is_some()) => DBFType::VisualFoxProAutoInc.flag().unwrap_or(version),
+            _ => version
+        };
+
+        let mut offset = 1;
+        let fields: Vec<Field> = fields.into_iter().map(|mut field| {
+            field.offset = offset;
+            offset += field.size;
+            field
+        }).collect();
+        let backlink = if header.db_type.is_visual_foxpro() { 263 } else { 0 };
+        let header = Header {
+            db_type: DBFType::parse_type(version),
+            last_update: chrono::Local::now().date_naive(),
+            records_count: 0,
+            first_record_position: 32 + fields.len() * 32 + 1 + backlink,
+            record_len: offset,
+            table_flag: header.table_flag & !0x01,
+            codepage: header.codepage
+        };
+        if header.first_record_position > u16::MAX as usize || header.record_len > u16::MAX as usize {
+            return Err(Error::Overflow {
+                size: std::cmp::max(header.first_record_position, header.record_len),
+                max: u16::MAX as usize
+            });
+        }
+
+        let mut bytes = Vec::with_capacity(header.first_record_position);
+        bytes.extend(&header_bytes(&header, version, mark)?);
+        for field in fields.iter() {
+            bytes.extend(&write_field_meta(field, header.codepage)?);
+        }
+        bytes.push(0x0D);
+        bytes.resize(header.first_record_position, 0);
+
+        let mut f = File::create(path)?;
+        f.write_all(&bytes)?;
+
+        Ok(TableWriter {
+            f,
+            header,
+            fields
+        })
+    }
+
+    /// Header of the file being written
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+
+    /// Fields of the file being written with offset assigned
+    pub fn fields(&self) -> &[Field] {
+        &self.fields
+    }
+
+    /// Append a record to the end of file.
+    /// The bytes from `RecordOps::to_bytes` shall include the deletion flag
+    /// and have exactly the same length as record length of this table.
+    /// Autoincrement `I` field is assigned its next value, replacing whatever the row has.
+    pub async fn append<T: RecordOps>(&mut self, row: &T) -> Result<()> {
+        let mut bytes = row.to_bytes()?;
+        check_record_len(&bytes, self.header.record_len)?;
+        assign_autoincrement(&mut self.fields, &mut bytes)?;
+        self.f.write_all(&bytes)?;
+        self.header.records_count += 1;
+        Ok(())
+    }
+
+    /// Write the EOF marker, the number of records and next value of autoincrement fields
+    /// then close the file.
+    pub async fn finish(mut self) -> Result<()> {
+        self.f.write_all(&[0x1A])?;
+        self.f.seek(SeekFrom::Start(4))?;
+        self.f.write_all(&(self.header.records_count as u32).to_le_bytes())?;
+        write_next_ids(&mut self.f, &self.fields)?;
+        self.f.sync_all()?;
+        Ok(())
+    }
+}
+
+/// Serialize first 32 bytes of DBF header.
+/// See [read_header](../fn.read_header.html) for the layout.
+/// Last update is stored as years since 1900 so it shall be within 1900 and 2155.
+fn header_bytes(header: &Header, version: u8, codepage_mark: u8) -> Result<[u8; 32]> {
+    let year = header.last_update.year();
+    let mut bytes = [0u8; 32];
+    bytes[0] = version;
+    bytes[1] = u8::try_from(year - 1900).map_err(|_| Error::Overflow {
+        size: year.max(0) as usize,
+        max: 1900 + u8::MAX as usize
+    })?;
+    bytes[2] = header.last_update.month() as u8;
+    bytes[3] = header.last_update.day() as u8;
+    bytes[4..8].copy_from_slice(&(header.records_count as u32).to_le_bytes());
+    bytes[8..10].copy_from_slice(&(header.first_record_position as u16).to_le_bytes());
+    bytes[10..12].copy_from_slice(&(header.record_len as u16).to_le_bytes());
+    bytes[28] = header.table_flag;
+    bytes[29] = codepage_mark;
+    Ok(bytes)
+}
+
+/// Byte that mark a record as deleted or not.
+fn deletion_flag(deleted: bool) -> u8 {
+    if deleted {
+        b'*'
+    } else {
+        b' '
+    }
+}
+
+/// Make sure that serialized record has exactly the length of table record.
+fn check_record_len(bytes: &[u8], record_len: usize) -> Result<()> {
+    if bytes.len() > record_len {
+        Err(Error::Overflow {
+            size: bytes.len(),
+            max: record_len
+        })
+    } else if bytes.len() < record_len {
+        Err(Error::Truncated {
+            expected: record_len,
+            found: bytes.len()
+        })
+    } else {
+        Ok(())
+    }
+}
+
 // impl<T> RecordOps<T> for Record where T: FieldOps {
 
 // }
\ No newline at end of file
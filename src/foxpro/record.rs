@@ -0,0 +1,611 @@
+use std::{
+    convert::TryFrom,
+    fmt
+};
+
+use super::*;
+
+/// A value of a single field of [DynamicRecord](struct.DynamicRecord.html).
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    /// Nullable field whose bit in `_NullFlags` is set.
+    /// Field that isn't nullable is written blank.
+    Null,
+    /// Field that is empty but isn't null, i.e. blank `N` and `F` field, unknown `L` field
+    /// and empty `D` and `T` field
+    Blank,
+    /// `C` and `V` field. Padding at the end of `C` field is removed.
+    Char(String),
+    /// `Y` field
+    Currency(Currency),
+    /// `N` and `F` field
+    Numeric(Decimal),
+    /// `B` field
+    Double(f64),
+    /// `I` field
+    Integer(i32),
+    /// `L` field
+    Logical(bool),
+    /// `D` field
+    Date(NaiveDate),
+    /// `T` field
+    DateTime(NaiveDateTime),
+    /// Block number inside memo file of `M`, `G`, `P` and `W` field
+    Memo(u32),
+    /// `Q` field, binary `C` field and system field such as `_NullFlags`
+    Binary(Vec<u8>)
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Null => write!(f, ".NULL."),
+            Value::Blank => Ok(()),
+            Value::Char(v) => write!(f, "{}", v),
+            Value::Currency(v) => write!(f, "{}", v),
+            Value::Numeric(v) => write!(f, "{}", v),
+            Value::Double(v) => write!(f, "{}", v),
+            Value::Integer(v) => write!(f, "{}", v),
+            Value::Logical(v) => write!(f, "{}", if *v { ".T." } else { ".F." }),
+            Value::Date(v) => write!(f, "{}", v),
+            Value::DateTime(v) => write!(f, "{}", v),
+            Value::Memo(v) => write!(f, "memo@{}", v),
+            Value::Binary(v) => write!(f, "{:02X?}", v)
+        }
+    }
+}
+
+/// A record whose fields are known only when the table is opened.
+///
+/// Each field is parsed into a [Value](enum.Value.html) according to its type in
+/// the table so it can be used to process any table, e.g.
+/// `Table::<DynamicRecord>::open(path)` or `stream_table::<DynamicRecord, _>(source)`.
+/// The [Schema](struct.Schema.html) is shared by every record of the same table.
+///
+/// Value can be accessed by column index using `value` and `set_value`, by case-insensitive
+/// column name using `get` and `set` or through [DynamicRecordOps](../trait.DynamicRecordOps.html).
+/// Setting a value that doesn't match the field type result in `Error::Encode`.
+/// Field that isn't in the schema is `Error::FieldNotFound`.
+///
+/// Nullable field whose bit in `_NullFlags` is set is read as `Value::Null`.
+/// Writing `Value::Null` into nullable field blank the field and set its bit.
+/// Field that is blank without the bit is read as `Value::Blank` and written back
+/// without the bit.
+/// The `_NullFlags` itself is computed from the values when the record is written.
+///
+/// Value that is too long for `C` field is cut to the field size when the record is written
+/// unless the record is [strict](struct.DynamicRecord.html#method.set_strict).
+///
+/// Record that is read from a table is written over the bytes it was read from so that
+/// layout of each field is kept, e.g. `D` field that hold a date in binary layout.
+#[derive(Clone, Debug)]
+pub struct DynamicRecord {
+    schema: Schema,
+    values: Vec<Value>,
+    deleted: bool,
+    strict: bool,
+    /// Bytes the record was read from, empty for new record
+    bytes: Vec<u8>
+}
+
+impl DynamicRecord {
+    /// Create a record of given schema where every value is `Value::Null`.
+    pub fn new(schema: Schema) -> DynamicRecord {
+        DynamicRecord {
+            values: vec![Value::Null; schema.len()],
+            schema,
+            deleted: false,
+            strict: false,
+            bytes: Vec::new()
+        }
+    }
+
+    /// Schema of this record
+    pub fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
+    /// Number of fields in this record
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Return true if this record has no field
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// All values of this record in the same order as fields
+    pub fn values(&self) -> &[Value] {
+        &self.values
+    }
+
+    /// Value of field at index `i`
+    pub fn value(&self, i: usize) -> Option<&Value> {
+        self.values.get(i)
+    }
+
+    /// Value of field with given name. The name is case-insensitive.
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.schema.index_of(name).and_then(|i| self.value(i))
+    }
+
+    /// Return true if value of field at index `i` is `Value::Null`
+    pub fn is_null(&self, i: usize) -> bool {
+        self.values.get(i) == Some(&Value::Null)
+    }
+
+    /// Replace value of field at index `i`.
+    /// The value shall match the field type.
+    pub fn set_value(&mut self, i: usize, value: Value) -> Result<()> {
+        let field = self.field(i)?;
+        if !accepts(field.datatype, &value) {
+            return Err(Error::Encode(format!("{:?} cannot be stored in {} field {}", value, field.datatype as char, field.name)));
+        }
+        self.values[i] = value;
+        Ok(())
+    }
+
+    /// Replace value of field with given name. The name is case-insensitive.
+    pub fn set(&mut self, name: &str, value: Value) -> Result<()> {
+        let i = self.schema.index_of(name).ok_or_else(|| Error::FieldNotFound(name.to_owned()))?;
+        self.set_value(i, value)
+    }
+
+    /// Mark or unmark this record as deleted
+    pub fn set_deleted(&mut self, deleted: bool) {
+        self.deleted = deleted;
+    }
+
+    /// In strict mode, writing this record fail with `Error::Overflow`
+    /// if a `C` value is too long instead of cutting it.
+    pub fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+    }
+
+    fn field(&self, i: usize) -> Result<&Field> {
+        self.schema.fields().get(i).ok_or_else(|| Error::FieldNotFound(i.to_string()))
+    }
+
+    fn mismatch(&self, i: usize, expected: &str) -> Error {
+        match self.values.get(i) {
+            Some(value) => Error::Decode(format!("Field {} is {:?}, not {}", i, value, expected)),
+            None => Error::FieldNotFound(i.to_string())
+        }
+    }
+}
+
+/// Return true if value can be stored in field of given type
+fn accepts(datatype: u8, value: &Value) -> bool {
+    match value {
+        Value::Null => true,
+        Value::Blank => matches!(datatype, b'N' | b'F' | b'L' | b'D' | b'T'),
+        Value::Char(_) => matches!(datatype, b'C' | b'V'),
+        Value::Currency(_) => datatype == b'Y',
+        Value::Numeric(_) => matches!(datatype, b'N' | b'F'),
+        Value::Double(_) => datatype == b'B',
+        Value::Integer(_) => datatype == b'I',
+        Value::Logical(_) => datatype == b'L',
+        Value::Date(_) => datatype == b'D',
+        Value::DateTime(_) => datatype == b'T',
+        Value::Memo(_) => matches!(datatype, b'M' | b'G' | b'P' | b'W'),
+        Value::Binary(_) => matches!(datatype, b'Q' | b'0' | b'C')
+    }
+}
+
+/// Parse a field. `varlength` is the variable length bit of `V` and `Q` field.
+pub(crate) fn read_value(bytes: &[u8], field: &Field, codepage: &'static str, varlength: bool) -> Result<Value> {
+    let value = match field.datatype {
+        b'C' if field.binary.is_some() => Value::Binary(bytes.to_vec()),
+        b'C' => Value::Char(RawCharField::new(bytes, codepage).get()?),
+        b'V' => Value::Char(RawVarCharField::new(bytes, codepage, field.size).with_varlength(varlength).get()?),
+        b'Y' => Value::Currency(RawCurrencyField::new(bytes).get()?),
+        // blank and overflow field have no value
+        b'N' | b'F' => match RawFloatField::new(bytes, field.precision).get()? {
+            Some(v) => Value::Numeric(v),
+            None => Value::Blank
+        },
+        b'B' => Value::Double(RawDoubleField::new(bytes).get()?),
+        b'I' => Value::Integer(RawIntegerField::new(bytes).get()?),
+        // unknown logical has no value
+        b'L' => match RawBoolField::new(bytes).get()? {
+            Some(v) => Value::Logical(v),
+            None => Value::Blank
+        },
+        b'D' => match RawDateField::new(bytes).get()? {
+            Some(v) => Value::Date(v),
+            None => Value::Blank
+        },
+        b'T' => match RawDateTimeField::new(bytes).get()? {
+            Some(v) => Value::DateTime(v),
+            None => Value::Blank
+        },
+        b'M' | b'W' => Value::Memo(RawMemoField::new(bytes).get()?),
+        b'G' | b'P' => Value::Memo(RawGeneralField::new(bytes).get()?),
+        b'Q' => Value::Binary(RawVarBinField::new(bytes, field.size).with_varlength(varlength).get()?.to_vec()),
+        b'0' => Value::Binary(bytes.to_vec()),
+        flag => return Err(Error::BadFieldType(flag))
+    };
+    Ok(value)
+}
+
+/// Put a value into the record.
+/// It return the variable length bit of `V` and `Q` field which is false for other fields.
+/// Without variable length bit, i.e. the table has no `_NullFlags`, `V` and `Q` field
+/// is padded like `C` field. Too long `C` value is cut unless `strict` is true.
+fn write_value(record: &mut [u8], field: &Field, value: &Value, codepage: &'static str, has_varlength_bit: bool, strict: bool) -> Result<bool> {
+    let bytes = record_slice(record, field.offset, field.size)?;
+    let mut varlength = false;
+    let (encoded, pad) = match value {
+        Value::Null | Value::Blank if matches!(field.datatype, b'N' | b'F' | b'C' | b'V' | b'D' | b'L') => (Vec::new(), b' '),
+        // empty datetime is all zero
+        Value::Null | Value::Blank if field.datatype == b'T' => (Vec::new(), 0),
+        // the bit in _NullFlags tell that it is null
+        Value::Null if field.nullable.is_some() => (Vec::new(), 0),
+        // assigned by the table when the record is inserted
+        Value::Null if field.autoincrement.is_some() => (Vec::new(), 0),
+        Value::Null => return Err(Error::Encode(format!("{} field {} cannot be null", field.datatype as char, field.name))),
+        Value::Blank => return Err(Error::Encode(format!("{} field {} cannot be blank", field.datatype as char, field.name))),
+        Value::Char(v) if field.datatype == b'V' && has_varlength_bit => {
+            let mut raw = RawVarCharField::new(bytes, codepage, field.size);
+            raw.set(v)?;
+            varlength = raw.is_varlength();
+            (raw.as_bytes().to_vec(), 0)
+        },
+        Value::Char(v) if field.datatype == b'V' => (encode(codepage, v)?, b' '),
+        Value::Char(v) => {
+            let mut raw = RawCharField::new(bytes, codepage).with_strict(strict);
+            raw.set(v)?;
+            (raw.as_bytes().to_vec(), b' ')
+        },
+        Value::Currency(v) => {
+            let mut raw = RawCurrencyField::new(bytes);
+            raw.set(v)?;
+            (raw.as_bytes().to_vec(), 0)
+        },
+        Value::Numeric(v) => {
+            let mut raw = RawFloatField::new(bytes, field.precision);
+            raw.set(v)?;
+            (raw.as_bytes().to_vec(), b' ')
+        },
+        Value::Double(v) => {
+            let mut raw = RawDoubleField::new(bytes);
+            raw.set(v)?;
+            (raw.as_bytes().to_vec(), 0)
+        },
+        Value::Integer(v) => {
+            let mut raw = RawIntegerField::new(bytes);
+            raw.set(v)?;
+            (raw.as_bytes().to_vec(), 0)
+        },
+        Value::Logical(v) => {
+            let mut raw = RawBoolField::new(bytes);
+            raw.set(v)?;
+            (raw.as_bytes().to_vec(), 0)
+        },
+        Value::Date(v) => {
+            let mut raw = RawDateField::new(bytes);
+            raw.set(v)?;
+            (raw.as_bytes().to_vec(), b' ')
+        },
+        Value::DateTime(v) => {
+            let mut raw = RawDateTimeField::new(bytes);
+            raw.set(v)?;
+            (raw.as_bytes().to_vec(), 0)
+        },
+        Value::Memo(v) => {
+            let mut raw = RawMemoField::new(bytes);
+            raw.set(v)?;
+            (raw.as_bytes().to_vec(), b' ')
+        },
+        Value::Binary(v) if field.datatype == b'Q' && has_varlength_bit => {
+            let mut raw = RawVarBinField::new(bytes, field.size);
+            raw.set(v)?;
+            varlength = raw.is_varlength();
+            (raw.as_bytes().to_vec(), 0)
+        },
+        Value::Binary(v) if field.datatype == b'C' => {
+            let mut raw = RawCharField::new(bytes, codepage).with_binary(true).with_strict(strict);
+            raw.set(v)?;
+            (raw.as_bytes().to_vec(), b' ')
+        },
+        Value::Binary(v) => (v.clone(), 0)
+    };
+    put_field(record, field.offset, field.size, &encoded, pad)?;
+    Ok(varlength)
+}
+
+/// Parse field at index `i` of given record, taking `_NullFlags` into account.
+fn read_field(record: &[u8], schema: &Schema, i: usize) -> Result<Value> {
+    let field = &schema[i];
+    match schema.is_null(record, i)? {
+        true => Ok(Value::Null),
+        false => read_value(record_slice(record, field.offset, field.size)?, field, schema.codepage(), schema.is_varlength(record, i)?)
+    }
+}
+
+/// A field of any type whose content is parsed into [Value](enum.Value.html) the same way
+/// as [DynamicRecord](struct.DynamicRecord.html) does.
+/// [Record::from_schema](../struct.Record.html#method.from_schema) uses it for field types
+/// that have no field of their own, e.g. `N`, `I` and `M`.
+pub struct ValueField<'a> {
+    pub meta: Field,
+    schema: Schema,
+    index: usize,
+    content: Value,
+    ready: Option<()>,
+    record: &'a [u8]
+}
+
+impl<'a> ValueField<'a> {
+    /// Create a field of column `i` of given schema that will read its content from given record
+    /// once [from_record_bytes](../trait.FieldOps.html#tymethod.from_record_bytes) is called.
+    pub fn new(schema: &Schema, i: usize, record: &'a [u8]) -> Result<ValueField<'a>> {
+        let meta = schema.fields().get(i).ok_or_else(|| Error::FieldNotFound(i.to_string()))?.clone();
+        Ok(ValueField {
+            meta,
+            schema: schema.clone(),
+            index: i,
+            content: Value::Null,
+            ready: None,
+            record
+        })
+    }
+
+    /// Value of this field. It is `Value::Null` until the field is loaded.
+    pub fn value(&self) -> &Value {
+        &self.content
+    }
+}
+
+impl<'a> FieldMeta for ValueField<'a> {
+    fn nullable(&self) -> bool {
+        self.meta.nullable()
+    }
+    fn autoincrement(&self) -> bool {
+        self.meta.autoincrement()
+    }
+    fn datatype_flag(&self) -> u8 {
+        self.meta.datatype_flag()
+    }
+    fn name(&self) -> &str {
+        self.meta.name()
+    }
+    fn rec_offset(&self) -> usize {
+        self.meta.rec_offset()
+    }
+    fn size(&self) -> usize {
+        self.meta.size()
+    }
+    fn precision(&self) -> usize {
+        self.meta.precision()
+    }
+    fn next_id(&mut self) -> u32 {
+        self.meta.next_id()
+    }
+}
+
+impl<'a> fmt::Display for ValueField<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.content)
+    }
+}
+
+impl<'a> FieldOps for ValueField<'a> {
+
+    fn from_record_bytes(&mut self) -> BoxFuture<'_, Result<()>> {
+        Box::pin(async move {
+            self.content = read_field(self.record, &self.schema, self.index)?;
+            self.ready = Some(());
+            Ok(())
+        })
+    }
+
+    fn to_bytes(&self) -> BoxFuture<'_, Result<&[u8]>> {
+        Box::pin(
+            async move {
+                record_slice(self.record, self.meta.rec_offset(), self.meta.size())
+            }
+        )
+    }
+
+    fn ready(&self) -> bool {
+        self.ready.is_some()
+    }
+}
+
+impl RecordOps for DynamicRecord {
+    /// Dynamic record cannot be parsed without fields of the table.
+    /// It always return `Error::Unsupported`.
+    fn from_bytes(_record: &[u8]) -> Result<Self> {
+        Err(Error::Unsupported("DynamicRecord::from_bytes without table fields"))
+    }
+
+    fn from_bytes_with_schema(record: &[u8], schema: &Schema) -> Result<Self> {
+        let values = (0..schema.len())
+            .map(|i| read_field(record, schema, i))
+            .collect::<Result<Vec<Value>>>()?;
+        Ok(DynamicRecord {
+            schema: schema.clone(),
+            values,
+            deleted: record.first() == Some(&b'*'),
+            strict: false,
+            bytes: record.to_vec()
+        })
+    }
+
+    fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut record = match self.bytes.len() == self.schema.record_len() {
+            true => self.bytes.clone(),
+            false => vec![b' '; self.schema.record_len()]
+        };
+        record[0] = deletion_flag(self.deleted);
+        let mut flags = Vec::new();
+        for (i, (field, value)) in self.schema.fields().iter().zip(self.values.iter()).enumerate() {
+            if field.is_null_flags() {
+                continue;
+            }
+            let varlength_bit = self.schema.varlength_bit(i).filter(|_| self.schema.null_flags().is_some());
+            if write_value(&mut record, field, value, self.schema.codepage(), varlength_bit.is_some(), self.strict)? {
+                flags.push(varlength_bit);
+            }
+            if *value == Value::Null {
+                flags.push(self.schema.null_bit(i));
+            }
+        }
+        if let Some(null_flags) = self.schema.null_flags() {
+            put_field(&mut record, null_flags.offset, null_flags.size, &[], 0)?;
+            for bit in flags.into_iter().flatten() {
+                self.schema.set_flag(&mut record, bit, true)?;
+            }
+        }
+        Ok(record)
+    }
+
+    fn is_deleted(&self) -> bool {
+        self.deleted
+    }
+}
+
+/// Implement getter and setter of integer type using i128 as an intermediate.
+macro_rules! integer_ops {
+    ($($get: ident, $set: ident, $t: ty);*) => {
+        $(
+            fn $get(&self, i: usize) -> Result<$t> {
+                let value = self.get_i128(i)?;
+                <$t>::try_from(value).map_err(|_| Error::Decode(format!("{} doesn't fit in {}", value, stringify!($t))))
+            }
+
+            fn $set(&mut self, i: usize, value: $t) -> Result<()> {
+                self.set_i128(i, value as i128)
+            }
+        )*
+    };
+}
+
+impl DynamicRecordOps for DynamicRecord {
+    fn get_string(&self, i: usize) -> Result<&String> {
+        match self.values.get(i) {
+            Some(Value::Char(v)) => Ok(v),
+            _ => Err(self.mismatch(i, "Char"))
+        }
+    }
+
+    fn set_string(&mut self, i: usize, value: &String) -> Result<()> {
+        self.set_value(i, Value::Char(value.clone()))
+    }
+
+    fn get_str(&self, i: usize) -> Result<&str> {
+        self.get_string(i).map(|v| v.as_str())
+    }
+
+    fn set_str(&mut self, i: usize, value: &str) -> Result<()> {
+        self.set_value(i, Value::Char(value.to_owned()))
+    }
+
+    integer_ops!(
+        get_u8, set_u8, u8;
+        get_u16, set_u16, u16;
+        get_u32, set_u32, u32;
+        get_u64, set_u64, u64;
+        get_i8, set_i8, i8;
+        get_i16, set_i16, i16;
+        get_i32, set_i32, i32;
+        get_i64, set_i64, i64
+    );
+
+    fn get_u128(&self, i: usize) -> Result<u128> {
+        let value = self.get_i128(i)?;
+        u128::try_from(value).map_err(|_| Error::Decode(format!("{} doesn't fit in u128", value)))
+    }
+
+    fn set_u128(&mut self, i: usize, value: u128) -> Result<()> {
+        let value = i128::try_from(value).map_err(|_| Error::Encode(format!("{} doesn't fit in i128", value)))?;
+        self.set_i128(i, value)
+    }
+
+    /// Integer field is returned as is. Numeric, double and currency field
+    /// is returned only when it has no fraction.
+    fn get_i128(&self, i: usize) -> Result<i128> {
+        match self.values.get(i) {
+            Some(Value::Integer(v)) => Ok(*v as i128),
+            Some(Value::Numeric(v)) => v.to_i128().ok_or_else(|| self.mismatch(i, "integer")),
+            Some(Value::Currency(v)) => v.to_i64().map(i128::from).ok_or_else(|| self.mismatch(i, "integer")),
+            Some(Value::Double(v)) if v.fract() == 0.0 => Ok(*v as i128),
+            _ => Err(self.mismatch(i, "integer"))
+        }
+    }
+
+    fn set_i128(&mut self, i: usize, value: i128) -> Result<()> {
+        let value = match self.field(i)?.datatype {
+            b'I' => Value::Integer(i32::try_from(value).map_err(|_| Error::Encode(format!("{} doesn't fit in integer field", value)))?),
+            b'N' | b'F' => Value::Numeric(Decimal::new(value, 0)),
+            b'Y' => Value::Currency(Currency::try_from(Decimal::new(value, 0)).map_err(|_| Error::Encode(format!("{} doesn't fit in currency", value)))?),
+            _ => return self.set_f64(i, value as f64)
+        };
+        self.set_value(i, value)
+    }
+
+    fn get_f32(&self, i: usize) -> Result<f32> {
+        self.get_f64(i).map(|v| v as f32)
+    }
+
+    fn set_f32(&mut self, i: usize, value: f32) -> Result<()> {
+        self.set_f64(i, value as f64)
+    }
+
+    fn get_f64(&self, i: usize) -> Result<f64> {
+        match self.values.get(i) {
+            Some(Value::Numeric(v)) => Ok(v.to_f64()),
+            Some(Value::Currency(v)) => Ok(v.to_f64()),
+            Some(Value::Double(v)) => Ok(*v),
+            Some(Value::Integer(v)) => Ok(*v as f64),
+            _ => Err(self.mismatch(i, "number"))
+        }
+    }
+
+    fn set_f64(&mut self, i: usize, value: f64) -> Result<()> {
+        let field = self.field(i)?;
+        let value = match field.datatype {
+            b'N' | b'F' => Value::Numeric(Decimal::from_f64(value, field.precision as u32)?),
+            b'B' => Value::Double(value),
+            b'Y' => Value::Currency(Currency::from_f64(value)?),
+            b'I' if value.fract() == 0.0 && value >= i32::MIN as f64 && value <= i32::MAX as f64 => Value::Integer(value as i32),
+            _ => return Err(Error::Encode(format!("{} cannot be stored in field {}", value, i)))
+        };
+        self.set_value(i, value)
+    }
+
+    fn get_date(&self, i: usize) -> Result<NaiveDate> {
+        match self.values.get(i) {
+            Some(Value::Date(v)) => Ok(*v),
+            _ => Err(self.mismatch(i, "Date"))
+        }
+    }
+
+    fn set_date(&mut self, i: usize, value: &NaiveDate) -> Result<()> {
+        self.set_value(i, Value::Date(*value))
+    }
+
+    fn get_datetime(&self, i: usize) -> Result<NaiveDateTime> {
+        match self.values.get(i) {
+            Some(Value::DateTime(v)) => Ok(*v),
+            _ => Err(self.mismatch(i, "DateTime"))
+        }
+    }
+
+    fn set_datetime(&mut self, i: usize, value: &NaiveDateTime) -> Result<()> {
+        self.set_value(i, Value::DateTime(*value))
+    }
+
+    fn get_bool(&self, i: usize) -> Result<bool> {
+        match self.values.get(i) {
+            Some(Value::Logical(v)) => Ok(*v),
+            _ => Err(self.mismatch(i, "Logical"))
+        }
+    }
+
+    fn set_bool(&mut self, i: usize, value: bool) -> Result<()> {
+        self.set_value(i, Value::Logical(value))
+    }
+}
@@ -0,0 +1,257 @@
+use std::{
+    collections::HashMap,
+    sync::Arc
+};
+
+use super::*;
+
+/// Bits inside `_NullFlags` that belong to a field
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct FieldBits {
+    /// Bit that is set when the field has variable length content shorter than field size
+    varlength: Option<usize>,
+    /// Bit that is set when the field is null
+    null: Option<usize>
+}
+
+/// Fields of a table along with its codepage.
+///
+/// It is built once per table, from fields returned by [read_fields](fn.read_fields.html),
+/// and shared by every record of the table so cloning it is cheap.
+/// Field can be looked up by name regardless of letter case, e.g. `"custno"` find `CUSTNO`.
+///
+/// ## Null flags
+/// ---
+/// Visual FoxPro keep null and variable length bits of every field in a hidden system
+/// field named `_NullFlags`. Bits are assigned from the least significant bit of the first
+/// byte in field order. `V` and `Q` field take one bit which is set when the content is
+/// shorter than field size. Nullable field take one bit, after the variable length bit,
+/// which is set when the field is null.
+///
+/// System fields, such as `_NullFlags`, are hidden from `fields`, `len` and name lookup
+/// unless the schema is created by [with_system_fields](struct.Schema.html#method.with_system_fields).
+#[derive(Clone, Debug)]
+pub struct Schema {
+    fields: Arc<[Field]>,
+    bits: Arc<[FieldBits]>,
+    names: Arc<HashMap<String, usize>>,
+    null_flags: Option<Field>,
+    record_len: usize,
+    codepage: &'static str
+}
+
+impl Schema {
+    /// Create a schema of given fields where system fields are hidden.
+    /// If more than one field has the same name, the first one is found by name.
+    pub fn new(fields: Vec<Field>, codepage: &'static str) -> Schema {
+        Schema::build(fields, codepage, false)
+    }
+
+    /// Create a schema of given fields including system fields.
+    pub fn with_system_fields(fields: Vec<Field>, codepage: &'static str) -> Schema {
+        Schema::build(fields, codepage, true)
+    }
+
+    fn build(fields: Vec<Field>, codepage: &'static str, include_system: bool) -> Schema {
+        let record_len = fields.iter().map(|field| field.offset + field.size).max().unwrap_or(1);
+        let null_flags = fields.iter().find(|field| field.is_null_flags()).cloned();
+        let mut next_bit = 0;
+        let mut visible = Vec::with_capacity(fields.len());
+        let mut bits = Vec::with_capacity(fields.len());
+        for field in fields {
+            let mut field_bits = FieldBits::default();
+            if matches!(field.datatype, b'V' | b'Q') {
+                field_bits.varlength = Some(next_bit);
+                next_bit += 1;
+            }
+            if field.nullable.is_some() {
+                field_bits.null = Some(next_bit);
+                next_bit += 1;
+            }
+            if include_system || field.system.is_none() {
+                visible.push(field);
+                bits.push(field_bits);
+            }
+        }
+
+        let mut names = HashMap::with_capacity(visible.len());
+        for (i, field) in visible.iter().enumerate() {
+            names.entry(normalize_name(&field.name)).or_insert(i);
+        }
+
+        Schema {
+            fields: Arc::from(visible),
+            bits: Arc::from(bits),
+            names: Arc::new(names),
+            null_flags,
+            record_len,
+            codepage
+        }
+    }
+
+    /// All visible fields in the same order as in the table
+    pub fn fields(&self) -> &[Field] {
+        &self.fields
+    }
+
+    /// Codepage of character fields
+    pub fn codepage(&self) -> &'static str {
+        self.codepage
+    }
+
+    /// Number of visible fields
+    pub fn len(&self) -> usize {
+        self.fields.len()
+    }
+
+    /// Return true if there's no visible field
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    /// Index of field with given name. The name is case-insensitive.
+    pub fn index_of(&self, name: &str) -> Option<usize> {
+        self.names.get(&normalize_name(name)).copied()
+    }
+
+    /// Field with given name. The name is case-insensitive.
+    pub fn field(&self, name: &str) -> Option<&Field> {
+        self.index_of(name).map(|i| &self.fields[i])
+    }
+
+    /// Offset in a record of field with given name. The name is case-insensitive.
+    pub fn offset_of(&self, name: &str) -> Option<usize> {
+        self.field(name).map(|field| field.offset)
+    }
+
+    /// Length of record described by this schema, including the deletion flag
+    /// and hidden system fields
+    pub fn record_len(&self) -> usize {
+        self.record_len
+    }
+
+    /// The `_NullFlags` system field, if the table has one
+    pub fn null_flags(&self) -> Option<&Field> {
+        self.null_flags.as_ref()
+    }
+
+    /// Bit inside `_NullFlags` that mark field `i` as null.
+    /// It is `None` if the field isn't nullable.
+    pub fn null_bit(&self, i: usize) -> Option<usize> {
+        self.bits.get(i).and_then(|bits| bits.null)
+    }
+
+    /// Bit inside `_NullFlags` that mark `V` or `Q` field `i` as shorter than its size.
+    /// It is `None` for other field types.
+    pub fn varlength_bit(&self, i: usize) -> Option<usize> {
+        self.bits.get(i).and_then(|bits| bits.varlength)
+    }
+
+    /// Return true if field `i` of given record is null.
+    /// Field that isn't nullable is never null.
+    pub fn is_null(&self, record: &[u8], i: usize) -> Result<bool> {
+        self.flag(record, self.null_bit(i))
+    }
+
+    /// Set or clear null bit of field `i` in given record.
+    /// It return `Error::Encode` if the field isn't nullable.
+    pub fn set_null(&self, record: &mut [u8], i: usize, null: bool) -> Result<()> {
+        match self.null_bit(i) {
+            Some(bit) => self.set_flag(record, bit, null),
+            None if !null => Ok(()),
+            None => Err(Error::Encode(format!("Field {} is not nullable", i)))
+        }
+    }
+
+    /// Return true if content of `V` or `Q` field `i` of given record is shorter than the field
+    /// so its length is stored in the last byte of the field.
+    pub fn is_varlength(&self, record: &[u8], i: usize) -> Result<bool> {
+        self.flag(record, self.varlength_bit(i))
+    }
+
+    /// Set or clear variable length bit of field `i` in given record.
+    /// It return `Error::Encode` if the field isn't `V` or `Q` field.
+    pub fn set_varlength(&self, record: &mut [u8], i: usize, varlength: bool) -> Result<()> {
+        match self.varlength_bit(i) {
+            Some(bit) => self.set_flag(record, bit, varlength),
+            None if !varlength => Ok(()),
+            None => Err(Error::Encode(format!("Field {} has no variable length", i)))
+        }
+    }
+
+    /// Read given bit of `_NullFlags` out of a record.
+    pub(crate) fn flag(&self, record: &[u8], bit: Option<usize>) -> Result<bool> {
+        match (bit, &self.null_flags) {
+            (Some(bit), Some(null_flags)) => Ok(NullFlags::new(field_slice(record, null_flags)?).get(bit)),
+            _ => Ok(false)
+        }
+    }
+
+    /// Set given bit of `_NullFlags` in a record.
+    pub(crate) fn set_flag(&self, record: &mut [u8], bit: usize, value: bool) -> Result<()> {
+        let null_flags = self.null_flags.as_ref().ok_or_else(|| Error::Encode("Table has no _NullFlags field".to_owned()))?;
+        let mut flags = NullFlags::new(field_slice(record, null_flags)?);
+        flags.set(bit, value)?;
+        put_field(record, null_flags.offset, null_flags.size, flags.as_bytes(), 0)
+    }
+}
+
+impl Index<usize> for Schema {
+    type Output = Field;
+
+    fn index(&self, i: usize) -> &Field {
+        &self.fields[i]
+    }
+}
+
+impl Default for Schema {
+    fn default() -> Schema {
+        Schema::new(Vec::new(), "cp1252")
+    }
+}
+
+/// Content of `_NullFlags` system field.
+/// Bit `n` is bit `n % 8` of byte `n / 8`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NullFlags {
+    bytes: Vec<u8>
+}
+
+impl NullFlags {
+    /// Take the field bytes out of a record
+    pub fn new(bytes: &[u8]) -> NullFlags {
+        NullFlags {
+            bytes: bytes.to_vec()
+        }
+    }
+
+    /// Return true if bit `bit` is set. Bit beyond the field is never set.
+    pub fn get(&self, bit: usize) -> bool {
+        self.bytes.get(bit / 8).map(|b| b & (1 << (bit % 8)) != 0).unwrap_or(false)
+    }
+
+    /// Set or clear bit `bit`.
+    pub fn set(&mut self, bit: usize, value: bool) -> Result<()> {
+        let max = self.bytes.len() * 8;
+        let byte = self.bytes.get_mut(bit / 8).ok_or(Error::Overflow {
+            size: bit + 1,
+            max
+        })?;
+        if value {
+            *byte |= 1 << (bit % 8);
+        } else {
+            *byte &= !(1 << (bit % 8));
+        }
+        Ok(())
+    }
+
+    /// Field bytes to be put back into a record
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+/// Field name is stored in upper case and padded with NUL
+fn normalize_name(name: &str) -> String {
+    name.trim_end_matches('\0').trim().to_uppercase()
+}
@@ -1,6 +1,7 @@
 use super::*;
 
 use futures::executor::block_on;
+use index::KeyType;
 
 #[test]
 fn test_create_records() {
@@ -8,7 +9,7 @@ fn test_create_records() {
         b'a', b'b', 
          1u8,    0,    0, 0,    0,    0,    0, 0,
         0xCC, 0x40, 0x0B, 0,    0,    0,    0, 0, // Feb 29, 2020
-        0x1E, 0x85, 0x25, 0, 0x80, 0x1C, 0xCA, 2]; // Feb 29, 2020: 13:00:00
+        0x1D, 0x85, 0x25, 0, 0x80, 0x1C, 0xCA, 2]; // Julian day 2458909 is Feb 29, 2020: 13:00:00
     let expected = [
         "ab",
         "0.0001",
@@ -33,7 +34,7 @@ fn test_create_records() {
             codepage: "tis-620",
             content: String::with_capacity(2 * 4),
             ready: None,
-            record: record
+            record
         }),
         Box::new(CurrencyField {
             meta: Field {
@@ -49,9 +50,9 @@ fn test_create_records() {
                 step: 1u32,
                 system: None
             },
-            content: String::with_capacity(256),
+            content: Currency::default(),
             ready: None,
-            record: record
+            record
         }),
         Box::new(DateField {
             meta: Field {
@@ -67,9 +68,9 @@ fn test_create_records() {
                 step: 1u32,
                 system: None
             },
-            content: NaiveDate::from_num_days_from_ce(0),
+            content: None,
             ready: None,
-            record: record
+            record
         }),
         Box::new(DateTimeField {
             meta: Field {
@@ -85,16 +86,1962 @@ fn test_create_records() {
                 step: 1u32,
                 system: None
             },
-            content: NaiveDate::from_num_days_from_ce(0).and_hms(0, 0, 0),
+            content: None,
             ready: None,
-            record: record
+            record
         })
     ]);
 
     for (i, f) in r.iter_mut().enumerate() {
         block_on(async {
-            f.from_record_bytes().await;
+            f.from_record_bytes().await.unwrap();
             assert_eq!(expected[i], format!("{}", f));
         });
     }
-}
\ No newline at end of file
+    assert_eq!(block_on(r[3].to_bytes()).unwrap(), &record[18..]);
+
+    let short = CharField {
+        meta: Field::new("NAME", b'C', 4, 0),
+        codepage: "cp1252",
+        content: String::new(),
+        ready: None,
+        record: &record[..2]
+    };
+    assert!(matches!(block_on(short.to_bytes()), Err(Error::Truncated {expected: 4, found: 2})));
+}
+/// Field subrecord of a test table
+fn field_bytes(name: &str, datatype: u8, offset: u32, size: u8, precision: u8) -> Vec<u8> {
+    let mut bytes = vec![0u8; 32];
+    bytes[..name.len()].copy_from_slice(name.as_bytes());
+    bytes[11] = datatype;
+    bytes[12..16].copy_from_slice(&offset.to_le_bytes());
+    bytes[16] = size;
+    bytes[17] = precision;
+    bytes
+}
+
+/// Build a Visual FoxPro table with fields NAME C(2), COST Y and QTY I
+/// that contains given records.
+fn orders_dbf(records: &[(&str, i64, i32)]) -> Vec<u8> {
+    let fields = [
+        field_bytes("NAME", b'C', 1, 2, 0),
+        field_bytes("COST", b'Y', 3, 8, 4),
+        field_bytes("QTY", b'I', 11, 4, 0)
+    ];
+    let header_len = 32 + fields.len() * 32 + 1 + 263;
+    let mut bytes = vec![0u8; 32];
+    bytes[0] = 0x30;
+    bytes[1..4].copy_from_slice(&[120, 2, 29]);
+    bytes[4..8].copy_from_slice(&(records.len() as u32).to_le_bytes());
+    bytes[8..10].copy_from_slice(&(header_len as u16).to_le_bytes());
+    bytes[10..12].copy_from_slice(&15u16.to_le_bytes());
+    bytes[29] = 3;
+    fields.iter().for_each(|f| bytes.extend(f));
+    bytes.push(0x0D);
+    bytes.extend(&[0u8; 263]);
+    for (name, cost, qty) in records {
+        bytes.push(b' ');
+        bytes.extend(name.as_bytes());
+        bytes.extend(&cost.to_le_bytes());
+        bytes.extend(&qty.to_le_bytes());
+    }
+    bytes.push(0x1A);
+    bytes
+}
+
+/// Write given bytes into a file inside temp directory and return its path.
+fn temp_file(name: &str, bytes: &[u8]) -> String {
+    let path = std::env::temp_dir().join(format!("adbf_rs_{}", name));
+    std::fs::write(&path, bytes).unwrap();
+    path.to_str().unwrap().to_owned()
+}
+
+/// Content of given file as an async source
+fn async_source(path: &str) -> futures::io::Cursor<Vec<u8>> {
+    futures::io::Cursor::new(std::fs::read(path).unwrap())
+}
+
+#[derive(Debug, PartialEq)]
+struct Order {
+    name: String,
+    cost: i64,
+    qty: i32
+}
+
+impl RecordOps for Order {
+    fn from_bytes(record: &[u8]) -> Result<Self> {
+        Ok(Order {
+            name: decode("cp1252", &record[1..3])?,
+            cost: i64::from_le_bytes(take_bytes(&record[3..11])?),
+            qty: i32::from_le_bytes(take_bytes(&record[11..15])?)
+        })
+    }
+
+    fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut bytes = vec![b' '];
+        bytes.extend(encode("cp1252", &self.name)?);
+        bytes.extend(&self.cost.to_le_bytes());
+        bytes.extend(&self.qty.to_le_bytes());
+        Ok(bytes)
+    }
+}
+
+#[test]
+fn test_open_table() {
+    let path = temp_file("open_table.dbf", &orders_dbf(&[("ab", 10_000, 1), ("cd", 25_000, 3), ("ef", 5_000, 2)]));
+    let table = block_on(Table::<Order>::open(&path)).unwrap();
+
+    assert_eq!(table.header.records_count, 3);
+    assert_eq!(table.header.last_update, NaiveDate::from_ymd_opt(2020, 2, 29).unwrap());
+    assert_eq!(table.fields.len(), 3);
+    assert_eq!(table.fields[1].offset, 3);
+    assert_eq!(table.fields[2].datatype, b'I');
+    assert_eq!(table.len(), 3);
+
+    let selected: InMemoryTable<Order> = table.select(|r| {
+        if r.qty > 1 {
+            Some(Order {name: r.name.clone(), cost: r.cost, qty: r.qty})
+        } else {
+            None
+        }
+    });
+    assert_eq!(selected.len(), 2);
+    assert_eq!(selected[0].name, "cd");
+    assert_eq!(table.aggregate(0, |sum, r| sum + r.cost), 40_000);
+}
+
+#[test]
+fn test_stream_table() {
+    use futures::stream::TryStreamExt;
+
+    let path = temp_file("stream_table.dbf", &orders_dbf(&[("ab", 10_000, 1), ("cd", 25_000, 3)]));
+    let orders: Vec<Order> = block_on(async {
+        stream_table(async_source(&path)).await.unwrap().try_collect().await
+    }).unwrap();
+
+    assert_eq!(orders, vec![
+        Order {name: "ab".to_owned(), cost: 10_000, qty: 1},
+        Order {name: "cd".to_owned(), cost: 25_000, qty: 3}
+    ]);
+}
+
+#[test]
+fn test_stream_truncated_source() {
+    use futures::stream::StreamExt;
+
+    let mut bytes = orders_dbf(&[("ab", 10_000, 1), ("cd", 25_000, 3)]);
+    bytes.truncate(bytes.len() - 5);
+    let header = Header {
+        first_record_position: 392,
+        record_len: 15,
+        records_count: 2,
+        ..Header::default()
+    };
+    let mut stream = RecordStream::<_, Order>::new(futures::io::Cursor::new(bytes), &header);
+
+    block_on(async {
+        assert_eq!(stream.next().await.unwrap().unwrap().name, "ab");
+        match stream.next().await.unwrap() {
+            Err(Error::Truncated { expected, found }) => assert_eq!((expected, found), (15, 11)),
+            _ => panic!("Expect truncated error")
+        }
+        assert!(stream.next().await.is_none());
+    });
+
+    let header_only = orders_dbf(&[])[..100].to_vec();
+    match block_on(stream_table::<Order, _>(futures::io::Cursor::new(header_only))) {
+        Err(Error::Truncated { .. }) => (),
+        _ => panic!("Expect truncated error")
+    }
+}
+
+#[test]
+fn test_open_bad_table() {
+    let mut unknown_cp = orders_dbf(&[("ab", 10_000, 1)]);
+    unknown_cp[29] = 0xEE;
+    let path = temp_file("unknown_cp.dbf", &unknown_cp);
+    match block_on(Table::<Order>::open(&path)) {
+        Err(Error::UnknownCodepage(_)) => (),
+        other => panic!("Expect unknown codepage but found {:?}", other)
+    }
+
+    let mut bad_date = orders_dbf(&[("ab", 10_000, 1)]);
+    bad_date[2] = 13;
+    let path = temp_file("bad_date.dbf", &bad_date);
+    match block_on(Table::<Order>::open(&path)) {
+        Err(Error::BadHeader(_)) => (),
+        other => panic!("Expect bad header but found {:?}", other)
+    }
+
+    let mut truncated = orders_dbf(&[("ab", 10_000, 1), ("cd", 25_000, 3)]);
+    truncated.truncate(400);
+    let path = temp_file("truncated.dbf", &truncated);
+    match block_on(Table::<Order>::open(&path)) {
+        Err(Error::Truncated { expected: 15, found: 8 }) => (),
+        other => panic!("Expect truncated but found {:?}", other)
+    }
+}
+
+#[test]
+fn test_raw_field_errors() {
+    let mut varchar = RawVarCharField {
+        bytes: MemReferer::from(&b"abc"[..]),
+        encoding: "cp1252".to_owned(),
+        max_length: 3,
+        varlength: false
+    };
+    assert_eq!(varchar.get().unwrap(), "abc");
+    match varchar.set(&"abcd".to_owned()) {
+        Err(Error::Overflow { size: 4, max: 3 }) => (),
+        other => panic!("Expect overflow but found {:?}", other)
+    }
+
+    let numeric = RawFloatField {
+        bytes: MemReferer::from(&b"  12.5x"[..]),
+        integer: 4,
+        precision: 2
+    };
+    match ConversionField::<f32>::get(&numeric) {
+        Err(Error::Decode(_)) => (),
+        other => panic!("Expect decode error but found {:?}", other)
+    }
+
+    let integer = RawIntegerField {
+        bytes: MemReferer::from(&[1u8, 0][..])
+    };
+    match integer.get() {
+        Err(Error::Truncated { expected: 4, found: 2 }) => (),
+        other => panic!("Expect truncated but found {:?}", other)
+    }
+
+    assert!(get_encoding("tis620").is_ok());
+    match get_encoding("cp0") {
+        Err(Error::UnknownCodepage(cp)) => assert_eq!(cp, "cp0"),
+        other => panic!("Expect unknown codepage but found {:?}", other.map(|e| e.name()))
+    }
+}
+
+/// Schema of orders table built by `orders_dbf`
+fn orders_fields() -> Vec<Field> {
+    vec![
+        Field::new("NAME", b'C', 2, 0),
+        Field::new("COST", b'Y', 8, 4),
+        Field::new("QTY", b'I', 4, 0)
+    ]
+}
+
+fn orders_header() -> Header {
+    Header {
+        db_type: DBFType::VisualFoxPro,
+        codepage: "cp1252",
+        ..Header::default()
+    }
+}
+
+#[test]
+fn test_write_table() {
+    let path = std::env::temp_dir().join("adbf_rs_write_table.dbf");
+    let path = path.to_str().unwrap();
+    let orders = vec![
+        Order {name: "ab".to_owned(), cost: 10_000, qty: 1},
+        Order {name: "cd".to_owned(), cost: 25_000, qty: 3}
+    ];
+
+    block_on(async {
+        let mut writer = TableWriter::create(path, &orders_header(), &orders_fields()).await.unwrap();
+        assert_eq!(writer.header().first_record_position, 392);
+        assert_eq!(writer.header().record_len, 15);
+        assert_eq!(writer.fields()[2].offset, 11);
+        for order in orders.iter() {
+            writer.append(order).await.unwrap();
+        }
+        match writer.append(&Order {name: "abc".to_owned(), cost: 0, qty: 0}).await {
+            Err(Error::Overflow { size: 16, max: 15 }) => (),
+            other => panic!("Expect overflow but found {:?}", other)
+        }
+        writer.finish().await.unwrap();
+    });
+
+    let bytes = std::fs::read(path).unwrap();
+    assert_eq!(bytes.len(), 392 + 2 * 15 + 1);
+    assert_eq!(bytes[0], 0x30);
+    assert_eq!(bytes[29], 3);
+    assert_eq!(&bytes[32..36], b"NAME");
+    assert_eq!(bytes[32 + 3 * 32], 0x0D);
+    assert_eq!(bytes[bytes.len() - 1], 0x1A);
+
+    let table = block_on(Table::<Order>::open(path)).unwrap();
+    assert_eq!(table.header.records_count, 2);
+    assert!(table.header.last_update.year() >= 2020);
+    assert_eq!(table.fields[1].datatype, b'Y');
+    assert_eq!(table.fields[1].precision, 4);
+    assert_eq!(table.into_iter().collect::<Vec<Order>>(), orders);
+
+    // size, precision and year that don't fit in a byte of header
+    let long_path = std::env::temp_dir().join("adbf_rs_write_long.dbf");
+    for field in [Field::new("NOTE", b'C', 300, 0), Field::new("QTY", b'N', 20, 256)].iter() {
+        match block_on(TableWriter::create(&long_path, &orders_header(), std::slice::from_ref(field))) {
+            Err(Error::Overflow { max: 255, .. }) => (),
+            other => panic!("Expect overflow but found {:?}", other.map(|_| ()))
+        }
+    }
+    assert!(!long_path.exists());
+    let future = Header {
+        last_update: NaiveDate::from_ymd_opt(2156, 1, 1).unwrap(),
+        ..orders_header()
+    };
+    match header_bytes(&future, 0x30, 3) {
+        Err(Error::Overflow { size: 2156, max: 2155 }) => (),
+        other => panic!("Expect overflow but found {:?}", other)
+    }
+
+    // structural index of the template isn't written so the new table doesn't claim it
+    let indexed = Header {
+        table_flag: 0x03,
+        ..orders_header()
+    };
+    block_on(async {
+        let writer = TableWriter::create(path, &indexed, &orders_fields()).await.unwrap();
+        assert_eq!(writer.header().table_flag, 0x02);
+        writer.finish().await.unwrap();
+    });
+    assert_eq!(std::fs::read(path).unwrap()[28], 0x02);
+    let mut table = block_on(Table::<Order>::open(path)).unwrap();
+    assert!(table.open_cdx().unwrap().is_none());
+    table.insert_owned(Order {name: "ef".to_owned(), cost: 0, qty: 0}).unwrap();
+}
+
+#[test]
+fn test_update_and_append_file() {
+    let mut bytes = orders_dbf(&[("ab", 10_000, 1), ("cd", 25_000, 3)]);
+    bytes[1..4].copy_from_slice(&[99, 12, 31]);
+    let path = temp_file("update_append.dbf", &bytes);
+    let mut table = block_on(Table::<Order>::open(&path)).unwrap();
+
+    table.update(|r| {
+        if r.qty > 1 {
+            r.qty = 10;
+        }
+    }).unwrap();
+    let updated = std::fs::read(&path).unwrap();
+    assert_eq!(&updated[392..407], &bytes[392..407]);
+    assert_eq!(&updated[407 + 11..407 + 15], &10i32.to_le_bytes());
+    assert!(table.header.last_update.year() >= 2020);
+
+    table.insert_owned(Order {name: "ef".to_owned(), cost: 5_000, qty: 2}).unwrap();
+    let appended = std::fs::read(&path).unwrap();
+    assert_eq!(appended.len(), 392 + 3 * 15 + 1);
+    assert_eq!(appended[appended.len() - 1], 0x1A);
+    assert_eq!(&appended[4..8], &3u32.to_le_bytes());
+
+    let reopened = block_on(Table::<Order>::open(&path)).unwrap();
+    assert_eq!(reopened.header.records_count, 3);
+    assert_eq!(reopened.header.last_update, table.header.last_update);
+    assert_eq!(reopened.into_iter().collect::<Vec<Order>>(), vec![
+        Order {name: "ab".to_owned(), cost: 10_000, qty: 1},
+        Order {name: "cd".to_owned(), cost: 25_000, qty: 10},
+        Order {name: "ef".to_owned(), cost: 5_000, qty: 2}
+    ]);
+}
+
+#[test]
+fn test_delete_recall_pack() {
+    let mut bytes = orders_dbf(&[("ab", 10_000, 1), ("cd", 25_000, 3), ("ef", 5_000, 2), ("gh", 1_000, 4)]);
+    bytes[392 + 15] = b'*';
+    let path = temp_file("delete_pack.dbf", &bytes);
+    let mut table = block_on(Table::<Order>::open(&path)).unwrap();
+
+    assert!(table.is_deleted(1));
+    assert_eq!(table.len(), 4);
+    assert_eq!(table.iter().map(|r| r.name.as_str()).collect::<Vec<&str>>(), vec!["ab", "ef", "gh"]);
+    // deleted rows are only known once they are reached
+    let mut rows = table.iter();
+    assert_eq!(rows.size_hint(), (0, Some(4)));
+    rows.next();
+    assert_eq!(rows.size_hint(), (0, Some(3)));
+    assert_eq!(rows.nth(1).map(|r| r.name.as_str()), Some("gh"));
+    assert_eq!(rows.size_hint(), (0, Some(0)));
+    assert_eq!(table.iter_with_deleted().size_hint(), (4, Some(4)));
+    assert_eq!(table.iter_with_deleted().count(), 4);
+    assert_eq!(table.aggregate(0, |sum, r| sum + r.qty), 7);
+
+    table.delete(3).unwrap();
+    table.recall(1).unwrap();
+    match table.delete(4) {
+        Err(Error::RecordNotFound(4)) => (),
+        other => panic!("Expect record not found but found {:?}", other)
+    }
+    let updated = std::fs::read(&path).unwrap();
+    assert_eq!(updated[392 + 15], b' ');
+    assert_eq!(updated[392 + 3 * 15], b'*');
+
+    table.delete(0).unwrap();
+    table.pack().unwrap();
+    assert_eq!(table.len(), 2);
+    assert_eq!(table.header.records_count, 2);
+
+    let packed = std::fs::read(&path).unwrap();
+    assert_eq!(packed.len(), 392 + 2 * 15 + 1);
+    assert_eq!(packed[packed.len() - 1], 0x1A);
+
+    let reopened = block_on(Table::<Order>::open(&path)).unwrap();
+    assert_eq!(reopened.into_iter().map(|r| r.name).collect::<Vec<String>>(), vec!["cd", "ef"]);
+}
+
+#[test]
+fn test_stream_skip_deleted() {
+    use futures::stream::TryStreamExt;
+
+    let mut bytes = orders_dbf(&[("ab", 10_000, 1), ("cd", 25_000, 3), ("ef", 5_000, 2)]);
+    bytes[392] = b'*';
+    bytes[392 + 30] = b'*';
+    let path = temp_file("stream_deleted.dbf", &bytes);
+    let names: Vec<String> = block_on(async {
+        stream_table::<Order, _>(async_source(&path)).await.unwrap().map_ok(|r| r.name).try_collect().await
+    }).unwrap();
+    assert_eq!(names, vec!["cd"]);
+
+    let all: Vec<Order> = block_on(async {
+        stream_table(async_source(&path)).await.unwrap().include_deleted(true).try_collect().await
+    }).unwrap();
+    assert_eq!(all.len(), 3);
+}
+
+/// Build a memo file with block size of 64 bytes that contains given memo
+/// starting at block 8 which is right after the 512 bytes header.
+fn memo_fpt(memos: &[(u32, &[u8])]) -> Vec<u8> {
+    let mut bytes = vec![0u8; 512];
+    for (memo_type, content) in memos {
+        let mut block = memo_type.to_be_bytes().to_vec();
+        block.extend(&(content.len() as u32).to_be_bytes());
+        block.extend(*content);
+        block.resize(block.len().div_ceil(64) * 64, 0);
+        bytes.extend(block);
+    }
+    let next_free = (bytes.len() / 64) as u32;
+    bytes[0..4].copy_from_slice(&next_free.to_be_bytes());
+    bytes[6..8].copy_from_slice(&64u16.to_be_bytes());
+    bytes
+}
+
+#[test]
+fn test_read_memo() {
+    let long_text = "ก".repeat(70);
+    let encoded_long_text = encode("tis-620", &long_text).unwrap();
+    let fpt = memo_fpt(&[(1, b"hello"), (0, &[1, 2, 3]), (1, &encoded_long_text)]);
+    let mut memo = MemoFile::new(std::io::Cursor::new(fpt), "tis-620").unwrap();
+
+    assert_eq!(memo.block_size(), 64);
+    assert_eq!(memo.next_free_block(), 12);
+    assert_eq!(memo.read_block(9).unwrap().unwrap(), MemoBlock {memo_type: MemoType::Picture, content: vec![1, 2, 3]});
+    assert_eq!(memo.read_text(0).unwrap(), None);
+
+    let vfp_memo = RawMemoField::new(&10u32.to_le_bytes());
+    assert_eq!(vfp_memo.load(&mut memo).unwrap(), Some(long_text));
+    let fox2_memo = RawMemoField::new(b"         8");
+    assert_eq!(fox2_memo.get().unwrap(), 8);
+    assert_eq!(fox2_memo.load(&mut memo).unwrap(), Some("hello".to_owned()));
+    let blank_memo = RawMemoField::new(b"          ");
+    assert_eq!(blank_memo.load(&mut memo).unwrap(), None);
+    let picture = RawGeneralField::new(&9u32.to_le_bytes());
+    assert_eq!(picture.load(&mut memo).unwrap(), Some(vec![1, 2, 3]));
+
+    match memo.read_block(12) {
+        Err(Error::Decode(_)) => (),
+        other => panic!("Expect decode error but found {:?}", other)
+    }
+
+    let mut corrupt = memo.into_inner().into_inner();
+    corrupt[516..520].copy_from_slice(&u32::MAX.to_be_bytes());
+    let mut memo = MemoFile::new(std::io::Cursor::new(corrupt), "tis-620").unwrap();
+    match memo.read_block(8) {
+        Err(Error::Truncated { expected, found }) => assert_eq!((expected, found), (u32::MAX as usize, 248)),
+        other => panic!("Expect truncated error but found {:?}", other)
+    }
+}
+
+#[test]
+fn test_open_table_memo() {
+    let path = temp_file("table_memo.dbf", &orders_dbf(&[("ab", 10_000, 1)]));
+    let _ = std::fs::remove_file(std::env::temp_dir().join("adbf_rs_table_memo.fpt"));
+    let table = block_on(Table::<Order>::open(&path)).unwrap();
+    assert!(table.open_memo().unwrap().is_none());
+
+    temp_file("table_memo.fpt", &memo_fpt(&[(1, b"note")]));
+    let mut memo = table.open_memo().unwrap().unwrap();
+    assert_eq!(memo.codepage(), "cp1252");
+    assert_eq!(memo.read_text(8).unwrap(), Some("note".to_owned()));
+}
+
+#[test]
+fn test_write_memo() {
+    let mut memo = MemoFile::create_in(std::io::Cursor::new(Vec::new()), 64, "cp1252").unwrap();
+    assert_eq!(memo.next_free_block(), 8);
+
+    let block = memo.write_text(0, "hello").unwrap();
+    assert_eq!(block, 8);
+    assert_eq!(memo.next_free_block(), 9);
+    // shorter text reuse the same block
+    assert_eq!(memo.write_text(block, "hi").unwrap(), 8);
+    assert_eq!(memo.read_text(8).unwrap(), Some("hi".to_owned()));
+    // longer text doesn't fit so it is appended
+    let long_text = "x".repeat(100);
+    assert_eq!(memo.write_text(block, &long_text).unwrap(), 9);
+    assert_eq!(memo.next_free_block(), 11);
+    assert_eq!(memo.write_binary(0, &[]).unwrap(), 0);
+
+    let mut field = RawMemoField::new(b"          ");
+    field.store(&mut memo, "note").unwrap();
+    assert_eq!(field.as_bytes(), b"        11");
+
+    // header written by the writer can be read back
+    let bytes = memo.into_inner().into_inner();
+    assert_eq!(bytes.len(), 12 * 64);
+    let mut memo = MemoFile::new(std::io::Cursor::new(bytes), "cp1252").unwrap();
+    assert_eq!(memo.next_free_block(), 12);
+    assert_eq!(memo.read_text(9).unwrap(), Some(long_text));
+    assert_eq!(memo.read_text(11).unwrap(), Some("note".to_owned()));
+
+    // corrupt length is refused instead of reusing blocks beyond the memo file
+    let mut corrupt = memo.into_inner().into_inner();
+    corrupt[9 * 64 + 4..9 * 64 + 8].copy_from_slice(&u32::MAX.to_be_bytes());
+    let mut memo = MemoFile::new(std::io::Cursor::new(corrupt), "cp1252").unwrap();
+    match memo.write_text(9, "a") {
+        Err(Error::Truncated {expected, found}) => assert_eq!((expected, found), (u32::MAX as usize, 3 * 64 - 8)),
+        other => panic!("Expected truncated memo but got {:?}", other)
+    }
+    assert_eq!(memo.into_inner().into_inner().len(), 12 * 64);
+}
+
+struct Note {
+    note: RawMemoField
+}
+
+impl RecordOps for Note {
+    fn from_bytes(record: &[u8]) -> Result<Self> {
+        Ok(Note {
+            note: RawMemoField::new(&record[1..5])
+        })
+    }
+
+    fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut bytes = vec![b' '];
+        bytes.extend(self.note.as_bytes());
+        Ok(bytes)
+    }
+}
+
+#[test]
+fn test_pack_memo() {
+    let path = std::env::temp_dir().join("adbf_rs_pack_memo.dbf");
+    let memo_path = path.with_extension("fpt");
+    let mut memo = MemoFile::create(&memo_path, 64, "cp1252").unwrap();
+    let mut first = RawMemoField::new(&[0; 4]);
+    first.store(&mut memo, "first").unwrap();
+    // replacing with longer text leaves block 8 as garbage
+    first.store(&mut memo, &"1".repeat(100)).unwrap();
+    let mut second = RawMemoField::new(&[0; 4]);
+    second.store(&mut memo, "second").unwrap();
+    let empty = RawMemoField::new(&[0; 4]);
+    assert_eq!(memo.next_free_block(), 12);
+    drop(memo);
+
+    block_on(async {
+        let mut writer = TableWriter::create(&path, &orders_header(), &[Field::new("NOTE", b'M', 4, 0)]).await.unwrap();
+        for note in [first, second, empty] {
+            writer.append(&Note {note}).await.unwrap();
+        }
+        writer.finish().await.unwrap();
+    });
+
+    // pack that fail on a corrupt memo leaves both files as they are
+    let memo_bytes = std::fs::read(&memo_path).unwrap();
+    let mut corrupt = memo_bytes.clone();
+    corrupt[11 * 64 + 4..11 * 64 + 8].copy_from_slice(&u32::MAX.to_be_bytes());
+    std::fs::write(&memo_path, &corrupt).unwrap();
+    let mut table = block_on(Table::<Note>::open(path.to_str().unwrap())).unwrap();
+    assert!(table.pack_memo().is_err());
+    assert_eq!(std::fs::read(&memo_path).unwrap(), corrupt);
+    assert!(!memo_path.with_extension("fpt.pack").exists());
+    let table = block_on(Table::<Note>::open(path.to_str().unwrap())).unwrap();
+    assert_eq!(table[0].note.get().unwrap(), 9);
+    std::fs::write(&memo_path, &memo_bytes).unwrap();
+
+    // pack that cannot write the records leaves the memo file as it is too
+    let table_bytes = std::fs::read(&path).unwrap();
+    let packed_table_path = std::env::temp_dir().join("adbf_rs_pack_memo.dbf.pack");
+    std::fs::create_dir_all(&packed_table_path).unwrap();
+    let mut table = block_on(Table::<Note>::open(path.to_str().unwrap())).unwrap();
+    assert!(table.pack_memo().is_err());
+    std::fs::remove_dir(&packed_table_path).unwrap();
+    assert_eq!(std::fs::read(&memo_path).unwrap(), memo_bytes);
+    assert_eq!(std::fs::read(&path).unwrap(), table_bytes);
+    assert!(!memo_path.with_extension("fpt.pack").exists());
+    assert_eq!(table[0].note.get().unwrap(), 9);
+
+    let mut table = block_on(Table::<Note>::open(path.to_str().unwrap())).unwrap();
+    table.pack_memo().unwrap();
+    assert_eq!(table[0].note.get().unwrap(), 8);
+    assert_eq!(table[1].note.get().unwrap(), 10);
+    assert_eq!(table[2].note.get().unwrap(), 0);
+
+    let mut memo = table.open_memo().unwrap().unwrap();
+    assert_eq!(memo.next_free_block(), 11);
+    assert_eq!(table[0].note.load(&mut memo).unwrap(), Some("1".repeat(100)));
+    assert_eq!(table[1].note.load(&mut memo).unwrap(), Some("second".to_owned()));
+
+    // records in file are rewritten too
+    let table = block_on(Table::<Note>::open(path.to_str().unwrap())).unwrap();
+    assert_eq!(table[1].note.get().unwrap(), 10);
+    assert!(!memo_path.with_extension("fpt.pack").exists());
+    assert!(!packed_table_path.exists());
+    assert!(!memo_path.with_extension("fpt.old").exists());
+}
+
+#[test]
+fn test_replace_packed() {
+    let dir = std::env::temp_dir().join("adbf_rs_replace_packed");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let (memo, packed_memo) = (dir.join("notes.fpt"), dir.join("notes.fpt.pack"));
+    let (table, packed_table) = (dir.join("notes.dbf"), dir.join("notes.dbf.pack"));
+    std::fs::write(&memo, b"old memo").unwrap();
+    std::fs::write(&packed_memo, b"new memo").unwrap();
+    std::fs::write(&packed_table, b"new table").unwrap();
+
+    // table cannot be replaced so the old memo is put back
+    std::fs::create_dir_all(&table).unwrap();
+    assert!(replace_packed(&packed_memo, &memo, &packed_table, &table).is_err());
+    assert_eq!(std::fs::read(&memo).unwrap(), b"old memo");
+    assert!(!memo.with_extension("fpt.old").exists());
+
+    std::fs::remove_dir(&table).unwrap();
+    std::fs::write(&table, b"old table").unwrap();
+    std::fs::write(&packed_memo, b"new memo").unwrap();
+    replace_packed(&packed_memo, &memo, &packed_table, &table).unwrap();
+    assert_eq!(std::fs::read(&memo).unwrap(), b"new memo");
+    assert_eq!(std::fs::read(&table).unwrap(), b"new table");
+    assert!(!memo.with_extension("fpt.old").exists());
+    assert!(!packed_memo.exists() && !packed_table.exists());
+}
+
+#[test]
+fn test_dynamic_record() {
+    use futures::stream::TryStreamExt;
+
+    let path = temp_file("dynamic_record.dbf", &orders_dbf(&[("ab", 10_000, 1), ("cd", 25_000, 3)]));
+    let mut table = block_on(Table::<DynamicRecord>::open(&path)).unwrap();
+    let row = &table[1];
+    assert_eq!(row.len(), 3);
+    assert_eq!(row.values(), &[Value::Char("cd".to_owned()), Value::Currency(Currency::from_raw(25_000)), Value::Integer(3)]);
+    assert_eq!(row.get("QTY"), Some(&Value::Integer(3)));
+    assert_eq!(row.get("NOPE"), None);
+    assert_eq!(row.get_str(0).unwrap(), "cd");
+    assert_eq!(row.get_i64(2).unwrap(), 3);
+    assert_eq!(row.get_u8(2).unwrap(), 3);
+    assert_eq!(row.get_f64(1).unwrap(), 2.5);
+    assert!(row.get_i32(1).is_err());
+    assert!(row.get_date(0).is_err());
+    assert_eq!(row.value(1).unwrap().to_string(), "2.5000");
+
+    table.update(|row| {
+        let qty = row.get_i32(2).unwrap();
+        row.set_i32(2, qty * 10).unwrap();
+        row.set_str(0, "zz").unwrap();
+    }).unwrap();
+    match table[0].set("QTY", Value::Char("x".to_owned())) {
+        Err(Error::Encode(_)) => (),
+        other => panic!("Expect encode error but found {:?}", other)
+    }
+    assert!(table[0].set_i64(2, i64::MAX).is_err());
+    match table[0].set("NOPE", Value::Integer(1)) {
+        Err(Error::FieldNotFound(name)) => assert_eq!(name, "NOPE"),
+        other => panic!("Expect field not found but found {:?}", other)
+    }
+    match table[0].set_value(3, Value::Integer(1)) {
+        Err(Error::FieldNotFound(name)) => assert_eq!(name, "3"),
+        other => panic!("Expect field not found but found {:?}", other)
+    }
+    match table[0].get_i32(3) {
+        Err(Error::FieldNotFound(_)) => (),
+        other => panic!("Expect field not found but found {:?}", other)
+    }
+
+    let orders = block_on(Table::<Order>::open(&path)).unwrap();
+    assert_eq!(orders[0], Order {name: "zz".to_owned(), cost: 10_000, qty: 10});
+    assert_eq!(orders[1].qty, 30);
+
+    let streamed: Vec<DynamicRecord> = block_on(async {
+        stream_table(async_source(&path)).await.unwrap().try_collect().await
+    }).unwrap();
+    assert_eq!(streamed[1].value(2), Some(&Value::Integer(30)));
+
+    match DynamicRecord::from_bytes(&[b' '; 15]) {
+        Err(Error::Unsupported(_)) => (),
+        other => panic!("Expect unsupported but found {:?}", other)
+    }
+}
+
+#[test]
+fn test_schema_lookup() {
+    let mut bytes = [0u8; 32];
+    bytes.copy_from_slice(&field_bytes("CUSTNO\0\0ab", b'C', 1, 6, 0));
+    let field = read_field_meta(bytes, "cp1252").unwrap();
+    assert_eq!(field.name, "CUSTNO");
+
+    let path = temp_file("schema_lookup.dbf", &orders_dbf(&[("ab", 10_000, 1)]));
+    let table = block_on(Table::<DynamicRecord>::open(&path)).unwrap();
+    let schema = table.schema();
+    assert_eq!(schema.len(), 3);
+    assert_eq!(schema.codepage(), "cp1252");
+    assert_eq!(schema.index_of("qty"), Some(2));
+    assert_eq!(schema.index_of(" Cost "), Some(1));
+    assert_eq!(schema.index_of("NAME\0\0"), Some(0));
+    assert_eq!(schema.index_of("CUSTNO"), None);
+    assert_eq!(schema.offset_of("Qty"), Some(11));
+    assert_eq!(schema.field("cost").unwrap().datatype, b'Y');
+    assert_eq!(schema[0].name, "NAME");
+    assert_eq!(schema.record_len(), 15);
+
+    assert_eq!(table[0].get("name"), Some(&Value::Char("ab".to_owned())));
+    let mut blank = DynamicRecord::new(schema.clone());
+    blank.set("qty", Value::Integer(7)).unwrap();
+    assert_eq!(blank.value(2), Some(&Value::Integer(7)));
+    assert_eq!(blank.get("NAME"), Some(&Value::Null));
+}
+
+#[test]
+fn test_null_flags() {
+    let path = std::env::temp_dir().join("adbf_rs_null_flags.dbf");
+    let path = path.to_str().unwrap();
+    let mut name = Field::new("NAME", b'C', 4, 0);
+    name.nullable = Some(());
+    let mut qty = Field::new("QTY", b'I', 4, 0);
+    qty.nullable = Some(());
+    let fields = [name, Field::new("CODE", b'I', 4, 0), qty];
+
+    block_on(async {
+        let mut writer = TableWriter::create(path, &orders_header(), &fields).await.unwrap();
+        assert_eq!(writer.fields().len(), 4);
+        assert!(writer.fields()[3].is_null_flags());
+        assert_eq!(writer.header().record_len, 14);
+
+        let schema = Schema::new(writer.fields().to_vec(), "cp1252");
+        assert_eq!(schema.len(), 3);
+        assert_eq!(schema.index_of("_NullFlags"), None);
+        assert_eq!(schema.null_bit(0), Some(0));
+        assert_eq!(schema.null_bit(1), None);
+        assert_eq!(schema.null_bit(2), Some(1));
+        assert_eq!(Schema::with_system_fields(writer.fields().to_vec(), "cp1252").len(), 4);
+
+        let mut row = DynamicRecord::new(schema.clone());
+        row.set("code", Value::Integer(1)).unwrap();
+        row.set("qty", Value::Integer(5)).unwrap();
+        let bytes = row.to_bytes().unwrap();
+        assert_eq!(bytes[13], 0b01);
+        assert!(schema.is_null(&bytes, 0).unwrap());
+        assert!(!schema.is_null(&bytes, 2).unwrap());
+        writer.append(&row).await.unwrap();
+
+        row.set("name", Value::Char("abcd".to_owned())).unwrap();
+        row.set("qty", Value::Null).unwrap();
+        writer.append(&row).await.unwrap();
+
+        // CODE isn't nullable
+        let mut no_code = DynamicRecord::new(schema.clone());
+        no_code.set("name", Value::Char("cd".to_owned())).unwrap();
+        assert!(no_code.to_bytes().is_err());
+        writer.finish().await.unwrap();
+    });
+
+    let table = block_on(Table::<DynamicRecord>::open(path)).unwrap();
+    assert_eq!(table.schema().len(), 3);
+    assert_eq!(table[0].values(), &[Value::Null, Value::Integer(1), Value::Integer(5)]);
+    assert_eq!(table[1].values(), &[Value::Char("abcd".to_owned()), Value::Integer(1), Value::Null]);
+    assert!(table[1].is_null(2));
+
+    let mut record = table[1].to_bytes().unwrap();
+    let schema = table.schema();
+    schema.set_null(&mut record, 2, false).unwrap();
+    schema.set_null(&mut record, 0, true).unwrap();
+    assert_eq!(record[13], 0b01);
+    assert!(schema.set_null(&mut record, 1, true).is_err());
+
+    let mut flags = NullFlags::new(&[0]);
+    flags.set(7, true).unwrap();
+    assert!(flags.get(7));
+    assert!(!flags.get(8));
+    assert!(flags.set(8, true).is_err());
+    assert_eq!(flags.as_bytes(), &[0x80]);
+}
+
+#[test]
+fn test_nullable_blank_numeric() {
+    let path = std::env::temp_dir().join("adbf_rs_nullable_blank.dbf");
+    let path = path.to_str().unwrap();
+    let mut cost = Field::new("COST", b'N', 5, 0);
+    cost.nullable = Some(());
+    let fields = [Field::new("NAME", b'C', 4, 0), cost];
+
+    block_on(async {
+        let mut writer = TableWriter::create(path, &orders_header(), &fields).await.unwrap();
+        let schema = Schema::new(writer.fields().to_vec(), "cp1252");
+        for (name, cost) in [("ab", Value::Blank), ("cd", Value::Null)].iter() {
+            let mut row = DynamicRecord::new(schema.clone());
+            row.set("name", Value::Char(name.to_string())).unwrap();
+            row.set("cost", cost.clone()).unwrap();
+            writer.append(&row).await.unwrap();
+        }
+        writer.finish().await.unwrap();
+    });
+
+    // rewriting a row keeps blank value apart from null
+    let mut table = block_on(Table::<DynamicRecord>::open(path)).unwrap();
+    assert_eq!(table[0].get("cost"), Some(&Value::Blank));
+    assert!(!table[0].is_null(1));
+    table.update(|row| row.set("name", Value::Char("ef".to_owned())).unwrap()).unwrap();
+    let table = block_on(Table::<DynamicRecord>::open(path)).unwrap();
+    assert_eq!(table[0].values(), &[Value::Char("ef".to_owned()), Value::Blank]);
+    assert_eq!(table[1].values(), &[Value::Char("ef".to_owned()), Value::Null]);
+    let bytes = table[0].to_bytes().unwrap();
+    assert_eq!(&bytes[5..10], b"     ");
+    assert!(!table.schema().is_null(&bytes, 1).unwrap());
+    assert!(table.schema().is_null(&table[1].to_bytes().unwrap(), 1).unwrap());
+}
+
+#[test]
+fn test_varlength_fields() {
+    let path = std::env::temp_dir().join("adbf_rs_varlength.dbf");
+    let path = path.to_str().unwrap();
+    let mut note = Field::new("NOTE", b'V', 6, 0);
+    note.nullable = Some(());
+    let fields = [note, Field::new("DATA", b'Q', 4, 0)];
+
+    block_on(async {
+        let mut writer = TableWriter::create(path, &orders_header(), &fields).await.unwrap();
+        assert_eq!(writer.header().db_type.flag(), Some(0x32));
+        assert_eq!(writer.fields()[2].size, 1);
+        let schema = Schema::new(writer.fields().to_vec(), "cp1252");
+        assert_eq!(schema.varlength_bit(0), Some(0));
+        assert_eq!(schema.null_bit(0), Some(1));
+        assert_eq!(schema.varlength_bit(1), Some(2));
+        assert_eq!(schema.null_bit(1), None);
+
+        let mut row = DynamicRecord::new(schema.clone());
+        row.set("note", Value::Char("ab ".to_owned())).unwrap();
+        row.set("data", Value::Binary(vec![1, 2, 3, 4])).unwrap();
+        let bytes = row.to_bytes().unwrap();
+        assert_eq!(&bytes[1..7], b"ab \0\0\x03");
+        assert_eq!(&bytes[7..11], &[1, 2, 3, 4]);
+        assert_eq!(bytes[11], 0b001);
+        writer.append(&row).await.unwrap();
+
+        row.set("note", Value::Char("abcdef".to_owned())).unwrap();
+        row.set("data", Value::Binary(vec![])).unwrap();
+        let bytes = row.to_bytes().unwrap();
+        assert_eq!(&bytes[1..7], b"abcdef");
+        assert_eq!(&bytes[7..11], &[0, 0, 0, 0]);
+        assert_eq!(bytes[11], 0b100);
+        writer.append(&row).await.unwrap();
+
+        row.set("note", Value::Null).unwrap();
+        writer.append(&row).await.unwrap();
+
+        row.set("note", Value::Char("abcdefg".to_owned())).unwrap();
+        match row.to_bytes() {
+            Err(Error::Overflow { size: 7, max: 6 }) => (),
+            other => panic!("Expect overflow but found {:?}", other)
+        }
+        writer.finish().await.unwrap();
+    });
+
+    let table = block_on(Table::<DynamicRecord>::open(path)).unwrap();
+    assert_eq!(table.header.db_type.flag(), Some(0x32));
+    assert_eq!(table[0].values(), &[Value::Char("ab ".to_owned()), Value::Binary(vec![1, 2, 3, 4])]);
+    assert_eq!(table[1].values(), &[Value::Char("abcdef".to_owned()), Value::Binary(vec![])]);
+    assert_eq!(table[2].values(), &[Value::Null, Value::Binary(vec![])]);
+
+    let mut varbin = RawVarBinField::new(&[9, 9, 9, 9], 4).with_varlength(true);
+    match varbin.get() {
+        Err(Error::Decode(_)) => (),
+        other => panic!("Expect decode error but found {:?}", other)
+    }
+    varbin.set(&[7]).unwrap();
+    assert!(varbin.is_varlength());
+    assert_eq!(varbin.as_bytes(), &[7, 0, 0, 1]);
+    assert_eq!(varbin.get().unwrap(), &[7]);
+}
+
+#[test]
+fn test_autoincrement() {
+    let mut bytes = [0u8; 32];
+    bytes.copy_from_slice(&field_bytes("ID", b'I', 1, 4, 0));
+    bytes[18] = 0x0C;
+    bytes[19..23].copy_from_slice(&7u32.to_le_bytes());
+    bytes[23] = 5;
+    let mut field = read_field_meta(bytes, "cp1252").unwrap();
+    assert_eq!((field.next_id, field.step, field.id_step()), (7, 5, 5));
+    assert_eq!(field.take_next_id(), Some(7));
+    assert_eq!(field.take_next_id(), Some(12));
+    assert_eq!(write_field_meta(&field, "cp1252").unwrap()[19..24], [17, 0, 0, 0, 5]);
+    assert_eq!(Field::new("QTY", b'I', 4, 0).take_next_id(), None);
+
+    let path = std::env::temp_dir().join("adbf_rs_autoincrement.dbf");
+    let path = path.to_str().unwrap();
+    let fields = [Field::new("ID", b'I', 4, 0).with_autoincrement(1, 2), Field::new("QTY", b'I', 4, 0)];
+    block_on(async {
+        let mut writer = TableWriter::create(path, &orders_header(), &fields).await.unwrap();
+        assert_eq!(writer.header().db_type.flag(), Some(0x31));
+        let schema = Schema::new(writer.fields().to_vec(), "cp1252");
+        let mut row = DynamicRecord::new(schema);
+        row.set("qty", Value::Integer(10)).unwrap();
+        writer.append(&row).await.unwrap();
+        // value given by the row is replaced
+        row.set("id", Value::Integer(100)).unwrap();
+        writer.append(&row).await.unwrap();
+        writer.finish().await.unwrap();
+    });
+
+    let mut table = block_on(Table::<DynamicRecord>::open(path)).unwrap();
+    assert_eq!(table.fields[0].next_id, 5);
+    assert_eq!(table.fields[0].step, 2);
+    assert_eq!(table[0].get("id"), Some(&Value::Integer(1)));
+    assert_eq!(table[1].get("id"), Some(&Value::Integer(3)));
+
+    let mut row = DynamicRecord::new(table.schema().clone());
+    row.set("qty", Value::Integer(30)).unwrap();
+    table.insert_owned(row).unwrap();
+    assert_eq!(table[2].get("id"), Some(&Value::Integer(5)));
+    assert_eq!(table.fields[0].next_id, 7);
+
+    let table = block_on(Table::<DynamicRecord>::open(path)).unwrap();
+    assert_eq!(table.fields[0].next_id, 7);
+    assert_eq!(table[2].values(), &[Value::Integer(5), Value::Integer(30)]);
+}
+
+#[test]
+fn test_numeric_decimal() {
+    let total = RawFloatField::new(b"  1234567890123.45", 2);
+    let value: Decimal = total.get().unwrap();
+    assert_eq!(value, Decimal::new(123456789012345, 2));
+    assert_eq!(value.to_string(), "1234567890123.45");
+    assert_eq!(ConversionField::<Option<Decimal>>::get(&RawFloatField::new(b"      ", 2)).unwrap(), None);
+    assert_eq!(ConversionField::<Option<Decimal>>::get(&RawFloatField::new(b"******", 2)).unwrap(), None);
+    assert!(ConversionField::<Decimal>::get(&RawFloatField::new(b"******", 2)).is_err());
+    assert!(ConversionField::<Decimal>::get(&RawFloatField::new(b"      ", 2)).is_err());
+    assert_eq!(ConversionField::<Decimal>::get(&RawFloatField::new(b"  -.5", 2)).unwrap(), Decimal::new(-5, 1));
+
+    let mut field = RawFloatField::new(b"        ", 2);
+    field.set(&"-12.345".parse::<Decimal>().unwrap()).unwrap();
+    assert_eq!(field.as_bytes(), b"  -12.35");
+    field.set(&Decimal::from(7)).unwrap();
+    assert_eq!(field.as_bytes(), b"    7.00");
+    field.set(&None::<Decimal>).unwrap();
+    assert_eq!(field.as_bytes(), b"        ");
+    match field.set(&Decimal::from(123_456_i64)) {
+        Err(Error::Overflow { size: 9, max: 8 }) => (),
+        other => panic!("Expect overflow but found {:?}", other)
+    }
+    field.set(&1.5f32).unwrap();
+    assert_eq!(field.as_bytes(), b"    1.50");
+
+    assert_eq!(Decimal::new(150, 2), Decimal::new(15, 1));
+    assert!(Decimal::new(-1, 0) < Decimal::new(1, 3));
+    assert_eq!(Decimal::new(-125, 2).rescale(1).unwrap(), Decimal::new(-13, 1));
+    assert_eq!(Decimal::new(5, 3).to_string(), "0.005");
+    assert_eq!(Decimal::new(120, 1).to_i128(), Some(12));
+    assert_eq!(Decimal::new(125, 1).to_i128(), None);
+    assert!("1.2.3".parse::<Decimal>().is_err());
+    assert!("-".parse::<Decimal>().is_err());
+
+    let mut row = DynamicRecord::new(Schema::new(vec![{
+        let mut total = Field::new("TOTAL", b'N', 18, 2);
+        total.offset = 1;
+        total
+    }], "cp1252"));
+    row.set_i64(0, 1_234_567_890_123).unwrap();
+    assert_eq!(row.to_bytes().unwrap(), b"   1234567890123.00");
+    row.set("total", Value::Numeric("9999999999999.99".parse().unwrap())).unwrap();
+    let row = DynamicRecord::from_bytes_with_schema(&row.to_bytes().unwrap(), row.schema()).unwrap();
+    assert_eq!(row.value(0).unwrap().to_string(), "9999999999999.99");
+    assert!(row.get_i64(0).is_err());
+}
+
+#[test]
+fn test_currency() {
+    let bytes = (-15_001i64).to_le_bytes();
+    let mut raw = RawCurrencyField::new(&bytes);
+    let value: Currency = raw.get().unwrap();
+    assert_eq!(value.raw(), -15_001);
+    assert_eq!(value.to_string(), "-1.5001");
+    raw.set(&value).unwrap();
+    assert_eq!(raw.as_bytes(), &bytes);
+
+    raw.set(&0.1f64).unwrap();
+    assert_eq!(raw.as_bytes(), &1_000i64.to_le_bytes());
+    assert_eq!(ConversionField::<f64>::get(&raw).unwrap(), 0.1);
+
+    let price: Currency = "19.99".parse().unwrap();
+    let mut total = price * 3 - Currency::from(1);
+    total += "0.0003".parse().unwrap();
+    assert_eq!(total.to_string(), "58.9703");
+    assert_eq!(-total, Currency::from_raw(-589_703));
+    assert_eq!(Decimal::from(total), "58.9703".parse::<Decimal>().unwrap());
+    assert_eq!(Currency::try_from(Decimal::new(123_456, 5)).unwrap(), Currency::from_raw(12_346));
+    assert!(Currency::try_from(Decimal::new(i128::MAX / 10, 0)).is_err());
+    assert_eq!(Currency::from_raw(i64::MAX).checked_add(Currency::from_raw(1)), None);
+    assert_eq!(Currency::from(7).to_i64(), Some(7));
+    assert!(Currency::from_f64(f64::NAN).is_err());
+
+    let mut cost = Field::new("COST", b'Y', 8, 4);
+    cost.offset = 1;
+    let schema = Schema::new(vec![cost], "cp1252");
+    let mut record = vec![b' '];
+    record.extend(&bytes);
+    let mut r = Record::from_schema(&schema, &record).unwrap();
+    assert!(!r[0].ready());
+    for f in r.load_all() {
+        block_on(f).unwrap();
+    }
+    assert!(r[0].ready());
+    assert_eq!(r[0].to_string(), "-1.5001");
+}
+
+#[test]
+fn test_datetime_codec() {
+    // 2000-01-01 is Julian day 2451545
+    let mut bytes = 2_451_545i32.to_le_bytes().to_vec();
+    bytes.extend(&45_296_789u32.to_le_bytes());
+    let raw = RawDateTimeField::new(&bytes);
+    let value: NaiveDateTime = raw.get().unwrap();
+    assert_eq!(value, NaiveDate::from_ymd_opt(2000, 1, 1).unwrap().and_hms_milli_opt(12, 34, 56, 789).unwrap());
+
+    let mut written = RawDateTimeField::new(&[0; 8]);
+    written.set(&value).unwrap();
+    assert_eq!(written.as_bytes(), &bytes[..]);
+    written.set(&NaiveDate::from_ymd_opt(1582, 10, 15).unwrap().and_hms_nano_opt(0, 0, 0, 999_999).unwrap()).unwrap();
+    assert_eq!(written.as_bytes(), &[0x19, 0x15, 0x23, 0, 0, 0, 0, 0]);
+
+    let empty = RawDateTimeField::new(&[0; 8]);
+    assert_eq!(ConversionField::<Option<NaiveDateTime>>::get(&empty).unwrap(), None);
+    assert!(ConversionField::<NaiveDateTime>::get(&empty).is_err());
+    written.set(&None).unwrap();
+    assert_eq!(written.as_bytes(), &[0; 8]);
+
+    let mut bad_time = bytes.clone();
+    bad_time[4..].copy_from_slice(&86_400_000u32.to_le_bytes());
+    assert!(ConversionField::<NaiveDateTime>::get(&RawDateTimeField::new(&bad_time)).is_err());
+
+    let mut stamp = Field::new("STAMP", b'T', 8, 0);
+    stamp.offset = 1;
+    let schema = Schema::new(vec![stamp], "cp1252");
+    let mut record = vec![b' '];
+    record.extend(&[0; 8]);
+    let row = DynamicRecord::from_bytes_with_schema(&record, &schema).unwrap();
+    assert_eq!(row.value(0), Some(&Value::Blank));
+    assert_eq!(row.to_bytes().unwrap(), record);
+
+    record[1..].copy_from_slice(&bytes);
+    let mut r = Record::from_schema(&schema, &record).unwrap();
+    assert!(!r[0].ready());
+    for f in r.load_all() {
+        block_on(f).unwrap();
+    }
+    assert!(r[0].ready());
+    assert_eq!(r[0].to_string(), value.to_string());
+}
+
+#[test]
+fn test_date_layouts() {
+    let ascii = RawDateField::new(b"20200229");
+    assert_eq!(ConversionField::<NaiveDate>::get(&ascii).unwrap(), NaiveDate::from_ymd_opt(2020, 2, 29).unwrap());
+    let binary = RawDateField::new(&737_484i64.to_le_bytes());
+    assert_eq!(ConversionField::<NaiveDate>::get(&binary).unwrap(), NaiveDate::from_ymd_opt(2020, 2, 29).unwrap());
+    for blank in [&b"        "[..], &[0u8; 8][..]].iter() {
+        assert_eq!(ConversionField::<Option<NaiveDate>>::get(&RawDateField::new(blank)).unwrap(), None);
+        match ConversionField::<NaiveDate>::get(&RawDateField::new(blank)) {
+            Err(Error::BadDate(_)) => (),
+            other => panic!("Expect bad date but found {:?}", other)
+        }
+    }
+    for bad in [&b"2020023 "[..], &b"20201301"[..], &b"2020"[..], &i64::MAX.to_le_bytes()[..]].iter() {
+        match ConversionField::<Option<NaiveDate>>::get(&RawDateField::new(bad)) {
+            Err(Error::BadDate(bytes)) => assert_eq!(&bytes[..], *bad),
+            other => panic!("Expect bad date of {:?} but found {:?}", bad, other)
+        }
+    }
+
+    let date = NaiveDate::from_ymd_opt(1999, 12, 31).unwrap();
+    let mut field = RawDateField::new(b"        ");
+    field.set(&date).unwrap();
+    assert_eq!(field.as_bytes(), b"19991231");
+    field.set(&None).unwrap();
+    assert_eq!(field.as_bytes(), b"        ");
+    let mut field = binary.clone();
+    field.set(&date).unwrap();
+    assert_eq!(field.as_bytes(), &(date.num_days_from_ce() as i64).to_le_bytes());
+    let mut field = RawDateField::new(&737_484i32.to_le_bytes());
+    field.set(&date).unwrap();
+    assert_eq!(field.as_bytes(), &date.num_days_from_ce().to_le_bytes());
+    // value that doesn't fit the field is refused and the field is left as it is
+    for (bytes, size) in [(&b"    "[..], 8), (&[1u8, 2][..], 4)].iter() {
+        let mut field = RawDateField::new(bytes);
+        match field.set(&date) {
+            Err(Error::Overflow {size: s, max}) => assert_eq!((s, max), (*size, bytes.len())),
+            other => panic!("Expect overflow but found {:?}", other)
+        }
+        assert_eq!(field.as_bytes(), *bytes);
+    }
+
+    let mut born = Field::new("BORN", b'D', 8, 0);
+    born.offset = 1;
+    let schema = Schema::new(vec![born], "cp1252");
+    let row = DynamicRecord::from_bytes_with_schema(b"         ", &schema).unwrap();
+    assert_eq!(row.value(0), Some(&Value::Blank));
+    assert_eq!(row.to_bytes().unwrap(), b"         ");
+    match DynamicRecord::from_bytes_with_schema(b" 2020ab01", &schema) {
+        Err(Error::BadDate(_)) => (),
+        other => panic!("Expect bad date but found {:?}", other)
+    }
+
+    // binary layout of a record that is read is kept when it is written back
+    let mut record = vec![b' '];
+    record.extend(&737_484i64.to_le_bytes());
+    let mut row = DynamicRecord::from_bytes_with_schema(&record, &schema).unwrap();
+    row.set_date(0, &date).unwrap();
+    let mut expected = vec![b' '];
+    expected.extend(&(date.num_days_from_ce() as i64).to_le_bytes());
+    assert_eq!(row.to_bytes().unwrap(), expected);
+    let mut row = DynamicRecord::new(schema.clone());
+    row.set_date(0, &date).unwrap();
+    assert_eq!(row.to_bytes().unwrap(), b" 19991231");
+
+    let mut r = Record::from_schema(&schema, &record).unwrap();
+    assert!(!r[0].ready());
+    for f in r.load_all() {
+        block_on(f).unwrap();
+    }
+    assert!(r[0].ready());
+    assert_eq!(r[0].to_string(), "2020-02-29");
+}
+
+#[test]
+fn test_logical_field() {
+    for (byte, expected) in [(b'T', Some(true)), (b't', Some(true)), (b'Y', Some(true)), (b'y', Some(true)),
+                             (b'F', Some(false)), (b'f', Some(false)), (b'N', Some(false)), (b'n', Some(false)),
+                             (b'?', None), (b' ', None)].iter() {
+        assert_eq!(ConversionField::<Option<bool>>::get(&RawBoolField::new(&[*byte])).unwrap(), *expected);
+    }
+    assert!(ConversionField::<bool>::get(&RawBoolField::new(b"?")).is_err());
+    assert!(ConversionField::<Option<bool>>::get(&RawBoolField::new(b"X")).is_err());
+    let mut raw = RawBoolField::new(b" ");
+    raw.set(&true).unwrap();
+    assert_eq!(raw.as_bytes(), b"T");
+    raw.set(&false).unwrap();
+    assert_eq!(raw.as_bytes(), b"F");
+    raw.set(&None).unwrap();
+    assert_eq!(raw.as_bytes(), b" ");
+
+    let mut name = Field::new("NAME", b'C', 2, 0);
+    name.offset = 1;
+    let mut paid = Field::new("PAID", b'L', 1, 0);
+    paid.offset = 3;
+    let schema = Schema::new(vec![name, paid], "cp1252");
+    for (record, expected) in [(b" abN", ".F."), (b" aby", ".T."), (b" ab?", "")].iter() {
+        let mut r = Record::from_schema(&schema, *record).unwrap();
+        for f in r.load_all() {
+            block_on(f).unwrap();
+        }
+        assert_eq!(r[0].to_string(), "ab");
+        assert_eq!(r[1].to_string(), *expected);
+        assert!(r[1].ready());
+    }
+
+    let mut row = DynamicRecord::from_bytes_with_schema(b" ab ", &schema).unwrap();
+    assert_eq!(row.value(1), Some(&Value::Blank));
+    assert_eq!(row.to_bytes().unwrap(), b" ab ");
+    row.set_bool(1, true).unwrap();
+    assert!(row.get_bool(1).unwrap());
+    assert_eq!(row.to_bytes().unwrap(), b" abT");
+
+    // field type that has no field of its own is parsed into Value
+    let mut paid = Field::new("PAID", b'L', 1, 0);
+    paid.offset = 1;
+    let mut qty = Field::new("QTY", b'N', 4, 0);
+    qty.offset = 2;
+    let mut code = Field::new("CODE", b'I', 4, 0);
+    code.offset = 6;
+    let mut note = Field::new("NOTE", b'M', 4, 0);
+    note.offset = 10;
+    let schema = Schema::new(vec![paid, qty, code, note], "cp1252");
+    let mut record = b" T  12".to_vec();
+    record.extend(&7i32.to_le_bytes());
+    record.extend(&3u32.to_le_bytes());
+    let mut r = Record::from_schema(&schema, &record).unwrap();
+    assert!(!r[2].ready());
+    for f in r.load_all() {
+        block_on(f).unwrap();
+    }
+    assert!(r.iter().all(|field| field.ready()));
+    let shown = r.iter().map(|field| field.to_string()).collect::<Vec<_>>();
+    assert_eq!(shown, vec![".T.", "12", "7", "memo@3"]);
+    assert_eq!(r[1].datatype_flag(), b'N');
+    assert_eq!(block_on(r[2].to_bytes()).unwrap(), &7i32.to_le_bytes());
+}
+
+#[test]
+fn test_char_field() {
+    let raw = RawCharField::new(b"ab  \0", "cp1252");
+    assert_eq!(ConversionField::<String>::get(&raw).unwrap(), "ab");
+    assert_eq!(raw.untrimmed().unwrap(), "ab  \0");
+
+    // "กขค" is 3 bytes in tis-620 and 9 bytes in utf-8
+    let mut raw = RawCharField::new(b"    ", "tis-620");
+    raw.set(&"กขคง1".to_owned()).unwrap();
+    assert_eq!(raw.as_bytes(), &[0xA1, 0xA2, 0xA4, 0xA7]);
+    raw.set(&"ก".to_owned()).unwrap();
+    assert_eq!(raw.as_bytes(), &[0xA1, b' ', b' ', b' ']);
+    let mut raw = RawCharField::new(b"    ", "utf-8");
+    raw.set(&"aกข".to_owned()).unwrap();
+    assert_eq!(raw.as_bytes(), "aก".as_bytes());
+    raw.set(&"abกข".to_owned()).unwrap();
+    assert_eq!(raw.as_bytes(), b"ab  ");
+    let mut strict = RawCharField::new(b"    ", "utf-8").with_strict(true);
+    match strict.set(&"abกข".to_owned()) {
+        Err(Error::Overflow { size: 8, max: 4 }) => (),
+        other => panic!("Expect overflow but found {:?}", other)
+    }
+    assert_eq!(strict.as_bytes(), b"    ");
+
+    let mut binary = RawCharField::new(&[0xFF, 0, b' '], "utf-8").with_binary(true);
+    assert!(ConversionField::<String>::get(&binary).is_err());
+    assert_eq!(ConversionField::<Vec<u8>>::get(&binary).unwrap(), vec![0xFF, 0, b' ']);
+    binary.set(&vec![1]).unwrap();
+    assert_eq!(binary.as_bytes(), &[1, b' ', b' ']);
+
+    let mut name = Field::new("NAME", b'C', 4, 0);
+    name.offset = 1;
+    let mut code = Field::new("CODE", b'C', 2, 0);
+    code.offset = 5;
+    code.binary = Some(());
+    let schema = Schema::new(vec![name, code], "cp1252");
+    let record = b" ab  \xFF\x00";
+    let mut r = Record::from_schema(&schema, record).unwrap();
+    for f in r.load_all() {
+        block_on(f).unwrap();
+    }
+    assert_eq!(r[0].to_string(), "ab");
+    assert_eq!(r[1].to_string(), "[FF, 00]");
+    assert!(r[1].ready());
+
+    let mut row = DynamicRecord::from_bytes_with_schema(record, &schema).unwrap();
+    assert_eq!(row.values(), &[Value::Char("ab".to_owned()), Value::Binary(vec![0xFF, 0])]);
+    assert_eq!(row.to_bytes().unwrap(), record);
+    row.set_str(0, "abcdef").unwrap();
+    row.set_value(1, Value::Binary(vec![7])).unwrap();
+    assert_eq!(row.to_bytes().unwrap(), b" abcd\x07 ");
+    row.set_strict(true);
+    match row.to_bytes() {
+        Err(Error::Overflow { size: 6, max: 4 }) => (),
+        other => panic!("Expect overflow but found {:?}", other)
+    }
+}
+
+/// Compact index header for test index
+fn cdx_header(root: u32, key_len: u16, options: u8, descending: bool, expression: &str, filter: &str) -> Vec<u8> {
+    let mut bytes = vec![0u8; 1024];
+    bytes[0..4].copy_from_slice(&root.to_le_bytes());
+    bytes[4..8].copy_from_slice(&u32::MAX.to_le_bytes());
+    bytes[12..14].copy_from_slice(&key_len.to_le_bytes());
+    bytes[14] = 0x60 | options;
+    bytes[502] = descending as u8;
+    let key_expr_len = expression.len() + 1;
+    bytes[512..512 + expression.len()].copy_from_slice(expression.as_bytes());
+    if !filter.is_empty() {
+        bytes[506..508].copy_from_slice(&(filter.len() as u16 + 1).to_le_bytes());
+        bytes[512 + key_expr_len..512 + key_expr_len + filter.len()].copy_from_slice(filter.as_bytes());
+    }
+    bytes[510..512].copy_from_slice(&(key_expr_len as u16).to_le_bytes());
+    bytes
+}
+
+/// Leaf node with 16 bits record number and 8 bits duplicate and trailing count
+fn cdx_leaf(entries: &[(Vec<u8>, u32)], trail: u8, right: u32) -> Vec<u8> {
+    let mut bytes = vec![0u8; 512];
+    bytes[0..2].copy_from_slice(&3u16.to_le_bytes());
+    bytes[2..4].copy_from_slice(&(entries.len() as u16).to_le_bytes());
+    bytes[4..8].copy_from_slice(&u32::MAX.to_le_bytes());
+    bytes[8..12].copy_from_slice(&right.to_le_bytes());
+    bytes[14..18].copy_from_slice(&0xFFFFu32.to_le_bytes());
+    bytes[18..24].copy_from_slice(&[0xFF, 0xFF, 16, 8, 8, 4]);
+    let mut end = 512;
+    let mut previous: &[u8] = &[];
+    for (i, (key, record)) in entries.iter().enumerate() {
+        let dup = key.iter().zip(previous.iter()).take_while(|(a, b)| a == b).count();
+        let trailing = key[dup..].iter().rev().take_while(|b| **b == trail).count();
+        let stored = &key[dup..key.len() - trailing];
+        end -= stored.len();
+        bytes[end..end + stored.len()].copy_from_slice(stored);
+        let packed = *record | (dup as u32) << 16 | (trailing as u32) << 24;
+        bytes[24 + i * 4..28 + i * 4].copy_from_slice(&packed.to_le_bytes());
+        previous = key;
+    }
+    bytes
+}
+
+/// Interior node of given key, record number and child node
+fn cdx_interior(entries: &[(Vec<u8>, u32, u32)]) -> Vec<u8> {
+    let mut bytes = vec![0u8; 512];
+    bytes[0..2].copy_from_slice(&1u16.to_le_bytes());
+    bytes[2..4].copy_from_slice(&(entries.len() as u16).to_le_bytes());
+    bytes[4..12].copy_from_slice(&[0xFF; 8]);
+    let mut offset = 12;
+    for (key, record, child) in entries {
+        bytes[offset..offset + key.len()].copy_from_slice(key);
+        offset += key.len();
+        bytes[offset..offset + 4].copy_from_slice(&record.to_be_bytes());
+        bytes[offset + 4..offset + 8].copy_from_slice(&child.to_be_bytes());
+        offset += 8;
+    }
+    bytes
+}
+
+/// Table of `NAME C(4)`, `QTY N(5)` and `BORN D`
+fn people_schema() -> Schema {
+    let mut name = Field::new("NAME", b'C', 4, 0);
+    name.offset = 1;
+    let mut qty = Field::new("QTY", b'N', 5, 0);
+    qty.offset = 5;
+    let mut born = Field::new("BORN", b'D', 8, 0);
+    born.offset = 10;
+    Schema::new(vec![name, qty, born], "cp1252")
+}
+
+/// Compound index with tag `NAME` on `UPPER(NAME)` which has two levels and tag `QTY`
+/// on `QTY` which is unique, descending and filtered
+fn people_cdx() -> Vec<u8> {
+    let qty_key = |qty: i32| KeyType::Numeric.encode(&Value::Integer(qty), 8, "cp1252").unwrap();
+    let mut bytes = cdx_header(1024, 10, 0, false, "", "");
+    bytes.extend(cdx_leaf(&[(b"NAME      ".to_vec(), 1536), (b"QTY       ".to_vec(), 4096)], b' ', u32::MAX));
+    bytes.extend(cdx_header(2560, 4, 0, false, "UPPER(name)", ""));
+    bytes.extend(cdx_interior(&[(b"AB  ".to_vec(), 3, 3072), (b"BOB ".to_vec(), 2, 3584)]));
+    bytes.extend(cdx_leaf(&[(b"A   ".to_vec(), 4), (b"AB  ".to_vec(), 3)], b' ', 3584));
+    bytes.extend(cdx_leaf(&[(b"ABC ".to_vec(), 1), (b"BOB ".to_vec(), 2)], b' ', u32::MAX));
+    bytes.extend(cdx_header(5120, 8, 0x09, true, "QTY", "QTY > 0"));
+    bytes.extend(cdx_leaf(&[(qty_key(300), 2), (qty_key(7), 1), (qty_key(-1), 3)], 0, u32::MAX));
+    bytes
+}
+
+#[test]
+fn test_cdx_reader() {
+    use index::{Cdx, IndexEntry};
+
+    let mut cdx = Cdx::new(std::io::Cursor::new(people_cdx()), &people_schema()).unwrap();
+    let names: Vec<&str> = cdx.tags().iter().map(|tag| tag.name.as_str()).collect();
+    assert_eq!(names, vec!["NAME", "QTY"]);
+    let name = cdx.tag("name").unwrap();
+    assert_eq!(name.expression, "UPPER(name)");
+    assert_eq!((name.filter.as_deref(), name.descending, name.unique, name.key_len()), (None, false, false, 4));
+    assert_eq!(name.key().unwrap().key_type(), KeyType::Character);
+    let qty = cdx.tag("QTY").unwrap();
+    assert_eq!((qty.filter.as_deref(), qty.descending, qty.unique), (Some("QTY > 0"), true, true));
+    assert_eq!(qty.key().unwrap().key_type(), KeyType::Numeric);
+
+    assert_eq!(cdx.record_numbers("NAME").unwrap(), vec![4, 3, 1, 2]);
+    assert_eq!(cdx.record_numbers("qty").unwrap(), vec![2, 1, 3]);
+    let entries = cdx.entries("NAME").unwrap().collect::<Result<Vec<_>>>().unwrap();
+    assert_eq!(entries[2], IndexEntry { key: b"ABC ".to_vec(), record: 1 });
+    let entries = cdx.entries("QTY").unwrap().collect::<Result<Vec<_>>>().unwrap();
+    assert_eq!(&entries[1].key[..], &[0xC0, 0x1C, 0, 0, 0, 0, 0, 0]);
+    match cdx.entries("NOPE") {
+        Err(Error::TagNotFound(name)) => assert_eq!(name, "NOPE"),
+        _ => panic!("Expect tag not found")
+    }
+
+    // leaf pointing to itself
+    let mut looped = people_cdx();
+    looped[3584 + 8..3584 + 12].copy_from_slice(&3584u32.to_le_bytes());
+    let mut cdx = Cdx::new(std::io::Cursor::new(looped), &people_schema()).unwrap();
+    assert!(cdx.record_numbers("NAME").is_err());
+
+    // bit counts that don't fit in an entry
+    let mut corrupt = people_cdx();
+    corrupt[1024 + 20] = 200;
+    match Cdx::new(std::io::Cursor::new(&corrupt[..1536]), &people_schema()) {
+        Err(Error::Decode(_)) => (),
+        Err(other) => panic!("Expect decode error but found {:?}", other),
+        Ok(_) => panic!("Expect decode error")
+    }
+    let mut corrupt = people_cdx();
+    corrupt[3072 + 21..3072 + 23].copy_from_slice(&[24, 16]);
+    let mut cdx = Cdx::new(std::io::Cursor::new(corrupt), &people_schema()).unwrap();
+    match cdx.record_numbers("NAME") {
+        Err(Error::Decode(_)) => (),
+        other => panic!("Expect decode error but found {:?}", other)
+    }
+}
+
+#[test]
+fn test_key_expression() {
+    use index::KeyExpr;
+
+    let schema = people_schema();
+    let record = b" ab     4220200229";
+    let key = KeyExpr::parse("UPPER(people.name) + DTOS(born)+STR(qty, 4)", &schema).unwrap();
+    assert_eq!((key.key_type(), key.key_len(), key.fields()), (KeyType::Character, 16, vec![0, 2, 1]));
+    assert_eq!(key.key(record, &schema).unwrap(), b"AB  20200229  42");
+    let key = KeyExpr::parse("qty", &schema).unwrap();
+    assert_eq!(key.key_len(), 8);
+    assert_eq!(key.key(record, &schema).unwrap(), KeyType::Numeric.encode(&Value::Integer(42), 8, "cp1252").unwrap());
+    let key = KeyExpr::parse("born", &schema).unwrap();
+    assert_eq!(key.key(&[b' '; 18], &schema).unwrap(), vec![0; 8]);
+
+    // keys sort like values
+    let numbers: Vec<Vec<u8>> = [-2.5, -1.0, 0.0, 0.5, 300.0].iter()
+        .map(|v| KeyType::Numeric.encode(&Value::Double(*v), 8, "cp1252").unwrap())
+        .collect();
+    assert!(numbers.windows(2).all(|w| w[0] < w[1]));
+    assert!(KeyType::Integer.encode(&Value::Integer(-1), 4, "cp1252").unwrap() < KeyType::Integer.encode(&Value::Integer(1), 4, "cp1252").unwrap());
+
+    for bad in ["nope", "UPPER(qty)", "name + qty", "SUBSTR(name, 1, 2)", "name +"].iter() {
+        assert!(KeyExpr::parse(bad, &schema).is_err(), "{} shall not be parsed", bad);
+    }
+}
+
+/// Same as `people_cdx` but tag `QTY` has no FOR clause so that every tag can be written
+fn writable_people_cdx() -> Vec<u8> {
+    let mut bytes = people_cdx();
+    bytes[4096 + 14] = 0x61;
+    bytes[4096 + 506..4096 + 508].copy_from_slice(&[0, 0]);
+    bytes
+}
+
+/// Write people table that has structural index along with given index
+fn write_people_table(path: &std::path::Path, cdx: Vec<u8>) {
+    let header = Header {
+        table_flag: 0x01,
+        ..orders_header()
+    };
+    let fields: Vec<Field> = people_schema().fields().iter().map(|field| Field::new(&field.name, field.datatype, field.size, 0)).collect();
+    block_on(async {
+        let mut writer = TableWriter::create(path, &header, &fields).await.unwrap();
+        let schema = Schema::new(writer.fields().to_vec(), "cp1252");
+        for (name, qty) in [("abc", 7), ("bob", 300), ("ab", -1), ("a", 0)].iter() {
+            let mut row = DynamicRecord::new(schema.clone());
+            row.set_str(0, name).unwrap();
+            row.set_i32(1, *qty).unwrap();
+            writer.append(&row).await.unwrap();
+        }
+        writer.finish().await.unwrap();
+    });
+    // the writer doesn't mark structural index so it is marked once the index is there
+    let mut bytes = std::fs::read(path).unwrap();
+    bytes[28] = header.table_flag;
+    std::fs::write(path, bytes).unwrap();
+    std::fs::write(path.with_extension("cdx"), cdx).unwrap();
+}
+
+#[test]
+fn test_index_seek_and_range() {
+    let path = std::env::temp_dir().join("adbf_rs_indexed_people.dbf");
+    write_people_table(&path, people_cdx());
+
+    let mut table = block_on(Table::<DynamicRecord>::open(path.to_str().unwrap())).unwrap();
+    let names = |rows: Vec<DynamicRecord>| rows.iter().map(|row| row.get_str(0).unwrap().to_owned()).collect::<Vec<_>>();
+    assert_eq!(names(table.seek("name", &Value::Char("AB".to_owned())).unwrap()), vec!["ab", "abc"]);
+    assert_eq!(names(table.seek("name", &Value::Char("AB ".to_owned())).unwrap()), vec!["ab"]);
+    assert!(table.seek("name", &Value::Char("ab".to_owned())).unwrap().is_empty());
+    assert!(table.seek("name", &Value::Char("ABCDE".to_owned())).is_err());
+    let rows = table.range("NAME", Value::Char("AB".to_owned())..Value::Char("B".to_owned())).unwrap().collect::<Result<Vec<_>>>().unwrap();
+    assert_eq!(names(rows), vec!["ab", "abc"]);
+    let rows = table.range("NAME", ..=Value::Char("B".to_owned())).unwrap().collect::<Result<Vec<_>>>().unwrap();
+    assert_eq!(names(rows), vec!["a", "ab", "abc", "bob"]);
+
+    // descending tag
+    let rows = table.range("qty", Value::Integer(0)..).unwrap().collect::<Result<Vec<_>>>().unwrap();
+    assert_eq!(names(rows), vec!["bob", "abc"]);
+    assert_eq!(names(table.seek("qty", &Value::Integer(7)).unwrap()), vec!["abc"]);
+    match table.seek("nope", &Value::Integer(7)) {
+        Err(Error::TagNotFound(name)) => assert_eq!(name, "nope"),
+        _ => panic!("Expect tag not found")
+    }
+
+    table.delete(0).unwrap();
+    assert_eq!(names(table.seek("name", &Value::Char("AB".to_owned())).unwrap()), vec!["ab"]);
+    let rows = table.range("name", Value::Char("ABC".to_owned())..).unwrap().include_deleted(true).collect::<Result<Vec<_>>>().unwrap();
+    assert_eq!(names(rows), vec!["abc", "bob"]);
+
+    // single index file of the same name as a tag is not read at all
+    let dir = std::env::temp_dir().join("adbf_rs_indexed");
+    std::fs::create_dir_all(&dir).unwrap();
+    write_people_table(&dir.join("people.dbf"), people_cdx());
+    std::fs::write(dir.join("name.idx"), b"not an index").unwrap();
+    let table = block_on(Table::<DynamicRecord>::open(dir.join("people.dbf").to_str().unwrap())).unwrap();
+    assert_eq!(names(table.seek("name", &Value::Char("BOB".to_owned())).unwrap()), vec!["bob"]);
+    assert!(table.open_idx("name").is_err());
+
+    // leaf past the range isn't read, the next leaf is read only once rows of the first are taken
+    let mut cdx = people_cdx();
+    cdx[3584..3586].copy_from_slice(&0u16.to_le_bytes());
+    std::fs::write(dir.join("people.cdx"), cdx).unwrap();
+    let rows = table.range("name", ..Value::Char("AB".to_owned())).unwrap().collect::<Result<Vec<_>>>().unwrap();
+    assert_eq!(names(rows), vec!["a"]);
+    let mut rows = table.range("name", ..).unwrap();
+    assert_eq!(rows.next().unwrap().unwrap().get_str(0).unwrap(), "a");
+    assert_eq!(rows.next().unwrap().unwrap().get_str(0).unwrap(), "ab");
+    assert!(matches!(rows.next(), Some(Err(Error::Decode(_)))));
+}
+
+#[test]
+fn test_cdx_writer() {
+    use index::Cdx;
+
+    let schema = people_schema();
+    let mut cdx = Cdx::new(std::io::Cursor::new(people_cdx()), &schema).unwrap();
+    match cdx.insert_record(5, b" carl    5        ", &schema) {
+        Err(Error::Unsupported(_)) => (),
+        other => panic!("Expect unsupported but found {:?}", other)
+    }
+    assert!(cdx.insert("NAME", b"AAA", 5).is_err());
+    cdx.insert("NAME", b"AAA ", 5).unwrap();
+    assert_eq!(cdx.record_numbers("NAME").unwrap(), vec![4, 5, 3, 1, 2]);
+    assert!(!cdx.remove("NAME", b"AAA ", 6).unwrap());
+    assert!(cdx.remove("NAME", b"AAA ", 5).unwrap());
+    assert_eq!(cdx.record_numbers("NAME").unwrap(), vec![4, 3, 1, 2]);
+
+    // enough keys to split leaves and the root
+    let keys: Vec<(Vec<u8>, u32)> = (0..1000u32).map(|i| (format!("K{:03}", 999 - i).into_bytes(), 10 + i)).collect();
+    for (key, record) in keys.iter() {
+        cdx.insert("NAME", key, *record).unwrap();
+    }
+    let mut expected = vec![4, 3, 1, 2];
+    expected.extend((0..1000u32).rev().map(|i| 10 + i));
+    assert_eq!(cdx.record_numbers("NAME").unwrap(), expected);
+    assert_eq!(cdx.seek("NAME", b"K15").unwrap(), (0..10u32).rev().map(|i| 850 + i).collect::<Vec<_>>());
+    let grown = cdx.into_inner().into_inner();
+    assert!(grown.len() > people_cdx().len() + 8 * 512, "{}", grown.len());
+
+    let mut cdx = Cdx::new(std::io::Cursor::new(grown.clone()), &schema).unwrap();
+    for (key, record) in keys.iter() {
+        assert!(cdx.remove("NAME", key, *record).unwrap());
+    }
+    assert_eq!(cdx.record_numbers("NAME").unwrap(), vec![4, 3, 1, 2]);
+    // emptied nodes are reused
+    for (key, record) in keys.iter() {
+        cdx.insert("name", key, *record).unwrap();
+    }
+    assert_eq!(cdx.record_numbers("NAME").unwrap(), expected);
+    assert_eq!(cdx.into_inner().into_inner().len(), grown.len());
+
+    // every record along with its number
+    let records: Vec<(u32, Vec<u8>)> = [("abc", 7), ("bob", 300), ("ab", -1), ("a", 0), ("bob", 7)].iter().enumerate()
+        .map(|(i, (name, qty))| (i as u32 + 1, format!(" {:<4}{:>5}        ", name, qty).into_bytes()))
+        .collect();
+    let mut cdx = Cdx::new(std::io::Cursor::new(people_cdx()), &schema).unwrap();
+    assert!(cdx.reindex(records.clone(), &schema).is_err());
+    // space left after the new content goes to the free list
+    let mut bytes = writable_people_cdx();
+    bytes.extend(vec![0u8; 4 * 512]);
+    let mut cdx = Cdx::new(std::io::Cursor::new(bytes), &schema).unwrap();
+    cdx.reindex(records.clone(), &schema).unwrap();
+    let bytes = cdx.into_inner().into_inner();
+    assert_ne!(&bytes[4..8], &[0xFF; 4]);
+    let mut cdx = Cdx::new(std::io::Cursor::new(bytes), &schema).unwrap();
+    assert_eq!(cdx.record_numbers("NAME").unwrap(), vec![4, 3, 1, 2, 5]);
+    // unique keeps the first record of each key
+    assert_eq!(cdx.record_numbers("QTY").unwrap(), vec![2, 1, 4, 3]);
+    assert_eq!(cdx.tags().iter().map(|tag| tag.name.as_str()).collect::<Vec<_>>(), vec!["NAME", "QTY"]);
+    // key length that no longer match the expression is repaired
+    let mut stale = writable_people_cdx();
+    stale[1536 + 12] = 6;
+    let mut stale = Cdx::new(std::io::Cursor::new(stale), &schema).unwrap();
+    assert!(stale.insert("NAME", b"AB  ", 9).is_err());
+    stale.reindex(records.clone(), &schema).unwrap();
+    assert_eq!(stale.tag("NAME").unwrap().key_len(), 4);
+    let mut stale = Cdx::new(std::io::Cursor::new(stale.into_inner().into_inner()), &schema).unwrap();
+    assert_eq!(stale.record_numbers("NAME").unwrap(), vec![4, 3, 1, 2, 5]);
+    cdx.insert_record(6, b" al     -9        ", &schema).unwrap();
+    assert_eq!(cdx.seek("NAME", b"AL").unwrap(), vec![6]);
+    assert_eq!(cdx.record_numbers("QTY").unwrap(), vec![2, 1, 4, 3, 6]);
+    // root leaf is split
+    for i in 0..200 {
+        cdx.insert("NAME", format!("Z{:03}", i).as_bytes(), 100 + i).unwrap();
+    }
+    assert_eq!(cdx.record_numbers("NAME").unwrap().len(), 206);
+    assert_eq!(cdx.seek("NAME", b"Z19").unwrap(), (290..300).collect::<Vec<_>>());
+    for i in 0..200 {
+        assert!(cdx.remove("NAME", format!("Z{:03}", i).as_bytes(), 100 + i).unwrap());
+    }
+    assert_eq!(cdx.record_numbers("NAME").unwrap(), vec![4, 3, 1, 6, 2, 5]);
+
+    let orphans = cdx.update_record(1, &records[0].1, b" abc    8        ", &schema).unwrap();
+    assert_eq!(orphans, vec![("QTY".to_owned(), KeyType::Numeric.encode(&Value::Integer(7), 8, "cp1252").unwrap())]);
+    assert_eq!(cdx.record_numbers("QTY").unwrap(), vec![2, 1, 4, 3, 6]);
+    assert_eq!(cdx.seek("QTY", &KeyType::Numeric.encode(&Value::Integer(8), 8, "cp1252").unwrap()).unwrap(), vec![1]);
+    // key that isn't changed is left as is
+    assert!(cdx.update_record(2, &records[1].1, b" bob  300        ", &schema).unwrap().is_empty());
+}
+
+#[test]
+fn test_index_maintenance() {
+    let path = std::env::temp_dir().join("adbf_rs_maintained_people.dbf");
+    let names = |rows: Vec<DynamicRecord>| rows.iter().map(|row| row.get_str(0).unwrap().to_owned()).collect::<Vec<_>>();
+    let new_row = |schema: &Schema, name: &str, qty: i32| {
+        let mut row = DynamicRecord::new(schema.clone());
+        row.set_str(0, name).unwrap();
+        row.set_i32(1, qty).unwrap();
+        row
+    };
+
+    // tag with FOR clause or unsupported key expression cannot be kept up to date
+    // so every write is refused and nothing is written
+    let mut deleted_tag = writable_people_cdx();
+    deleted_tag[1536 + 512..1536 + 524].copy_from_slice(b"DELETED()\0\0\0");
+    deleted_tag[1536 + 510..1536 + 512].copy_from_slice(&10u16.to_le_bytes());
+    for cdx in [people_cdx(), deleted_tag] {
+        write_people_table(&path, cdx);
+        let bytes = std::fs::read(&path).unwrap();
+        let index_bytes = std::fs::read(path.with_extension("cdx")).unwrap();
+        let mut table = block_on(Table::<DynamicRecord>::open(path.to_str().unwrap())).unwrap();
+        let row = new_row(table.schema(), "carl", 5);
+        assert!(matches!(table.insert_owned(row), Err(Error::Unsupported(_))));
+        assert!(matches!(table.update(|row| row.set_i32(1, 1).unwrap()), Err(Error::Unsupported(_))));
+        table.delete(0).unwrap();
+        assert!(matches!(table.pack(), Err(Error::Unsupported(_))));
+        assert!(matches!(table.reindex(), Err(Error::Unsupported(_))));
+        assert_eq!((table.len(), table[1].get_i64(1).unwrap()), (4, 300));
+        assert_eq!(std::fs::read(path.with_extension("cdx")).unwrap(), index_bytes);
+        let mut after = std::fs::read(&path).unwrap();
+        // only the deletion flag of the first record is changed after the header
+        after[392] = b' ';
+        assert_eq!(after[32..], bytes[32..]);
+    }
+
+    write_people_table(&path, writable_people_cdx());
+    let mut table = block_on(Table::<DynamicRecord>::open(path.to_str().unwrap())).unwrap();
+    table.reindex().unwrap();
+    let qty = |table: &Table<DynamicRecord>, qty: i32| names(table.seek("qty", &Value::Integer(qty)).unwrap());
+    assert_eq!(qty(&table, 0), vec!["a"]);
+
+    let row = new_row(table.schema(), "carl", 5);
+    table.insert_owned(row).unwrap();
+    assert_eq!(names(table.seek("name", &Value::Char("CARL".to_owned())).unwrap()), vec!["carl"]);
+    assert_eq!(qty(&table, 5), vec!["carl"]);
+
+    // a batch shares one open index
+    let rows = [new_row(table.schema(), "dan", 6), new_row(table.schema(), "eve", 9)];
+    table.insert(&rows).unwrap();
+    assert_eq!(qty(&table, 6), vec!["dan"]);
+    assert_eq!(qty(&table, 9), vec!["eve"]);
+    table.delete(6).unwrap();
+    table.delete(5).unwrap();
+
+    table.update(|row| if row.get_str(0).unwrap() == "bob" {
+        row.set_i32(1, 8).unwrap();
+    }).unwrap();
+    assert_eq!(qty(&table, 8), vec!["bob"]);
+    assert!(qty(&table, 300).is_empty());
+
+    // unique tag takes another record of the key when its record moves away
+    table.update(|row| if row.get_str(0).unwrap() == "a" {
+        row.set_i32(1, 7).unwrap();
+    }).unwrap();
+    assert_eq!(qty(&table, 7), vec!["abc"]);
+    table.update(|row| if row.get_str(0).unwrap() == "abc" {
+        row.set_i32(1, 1).unwrap();
+    }).unwrap();
+    assert_eq!(qty(&table, 7), vec!["a"]);
+
+    // records are numbered anew
+    table.delete(0).unwrap();
+    table.pack().unwrap();
+    assert_eq!(names(table.seek("name", &Value::Char("A".to_owned())).unwrap()), vec!["a", "ab"]);
+    assert_eq!(table.open_cdx().unwrap().unwrap().record_numbers("NAME").unwrap(), vec![3, 2, 1, 4]);
+}
+
+#[test]
+fn test_insert_undone_when_index_fails() {
+    let path = std::env::temp_dir().join("adbf_rs_undone_people.dbf");
+    // tag QTY can be written but its root is an interior node without any child
+    let mut cdx = writable_people_cdx();
+    cdx[5120..5124].copy_from_slice(&[0, 0, 0, 0]);
+    write_people_table(&path, cdx);
+    let bytes = std::fs::read(&path).unwrap();
+
+    let mut table = block_on(Table::<DynamicRecord>::open(path.to_str().unwrap())).unwrap();
+    let mut row = DynamicRecord::new(table.schema().clone());
+    row.set_str(0, "carl").unwrap();
+    row.set_i32(1, 5).unwrap();
+    assert!(matches!(table.insert_owned(row), Err(Error::Decode(_))));
+    // the record is taken out of the file and out of tag NAME which took it before QTY failed
+    assert_eq!(table.len(), 4);
+    assert_eq!(std::fs::read(&path).unwrap(), bytes);
+    assert_eq!(table.open_cdx().unwrap().unwrap().record_numbers("NAME").unwrap(), vec![4, 3, 1, 2]);
+    let table = block_on(Table::<DynamicRecord>::open(path.to_str().unwrap())).unwrap();
+    assert_eq!(table.len(), 4);
+}
+
+#[test]
+fn test_update_undone_when_index_fails() {
+    let path = std::env::temp_dir().join("adbf_rs_update_undone_people.dbf");
+    let mut cdx = writable_people_cdx();
+    cdx[5120..5124].copy_from_slice(&[0, 0, 0, 0]);
+    write_people_table(&path, cdx);
+    let bytes = std::fs::read(&path).unwrap();
+
+    let mut table = block_on(Table::<DynamicRecord>::open(path.to_str().unwrap())).unwrap();
+    assert!(table.update(|row| if row.get_str(0).unwrap() == "bob" {
+        row.set_str(0, "bo").unwrap();
+        row.set_i32(1, 8).unwrap();
+    }).is_err());
+    // the record is written back the way it was in the file
+    assert_eq!(table[1].get_str(0).unwrap(), "bob");
+    assert_eq!(table[1].get_i64(1).unwrap(), 300);
+    assert_eq!(std::fs::read(&path).unwrap(), bytes);
+    // tag NAME which took the new key before QTY failed is moved back
+    let mut cdx = table.open_cdx().unwrap().unwrap();
+    assert_eq!(cdx.record_numbers("NAME").unwrap(), vec![4, 3, 1, 2]);
+    assert_eq!(cdx.seek("NAME", b"BOB ").unwrap(), vec![2]);
+
+    // key that doesn't fit the tag is refused before anything is written
+    let mut stale = writable_people_cdx();
+    stale[1536 + 12] = 6;
+    write_people_table(&path, stale);
+    let bytes = std::fs::read(&path).unwrap();
+    let mut table = block_on(Table::<DynamicRecord>::open(path.to_str().unwrap())).unwrap();
+    assert!(matches!(table.update(|row| if row.get_str(0).unwrap() == "abc" {
+        row.set_str(0, "abd").unwrap();
+    }), Err(Error::Encode(_))));
+    assert_eq!(table[0].get_str(0).unwrap(), "abc");
+    assert_eq!(std::fs::read(&path).unwrap(), bytes);
+
+    // rows written before the one that fails are kept and the header is touched
+    let mut cdx = writable_people_cdx();
+    cdx[5120..5124].copy_from_slice(&[0, 0, 0, 0]);
+    write_people_table(&path, cdx);
+    let mut bytes = std::fs::read(&path).unwrap();
+    bytes[1..4].copy_from_slice(&[99, 12, 31]);
+    std::fs::write(&path, &bytes).unwrap();
+    let mut table = block_on(Table::<DynamicRecord>::open(path.to_str().unwrap())).unwrap();
+    assert!(table.update(|row| match row.get_str(0).unwrap() {
+        "abc" => row.set_str(0, "abd").unwrap(),
+        "bob" => row.set_i32(1, 8).unwrap(),
+        _ => ()
+    }).is_err());
+    assert_eq!((table[0].get_str(0).unwrap(), table[1].get_i64(1).unwrap()), ("abd", 300));
+    let updated = std::fs::read(&path).unwrap();
+    assert_ne!(updated[1..4], [99, 12, 31]);
+    let table = block_on(Table::<DynamicRecord>::open(path.to_str().unwrap())).unwrap();
+    assert_eq!((table[0].get_str(0).unwrap(), table[1].get_i64(1).unwrap()), ("abd", 300));
+}
+
+/// Standard index header
+fn idx_header(root: u32, key_len: u16, options: u8, expression: &str) -> Vec<u8> {
+    let mut bytes = vec![0u8; 512];
+    bytes[0..4].copy_from_slice(&root.to_le_bytes());
+    bytes[4..8].copy_from_slice(&u32::MAX.to_le_bytes());
+    bytes[12..14].copy_from_slice(&key_len.to_le_bytes());
+    bytes[14] = options;
+    bytes[16..16 + expression.len()].copy_from_slice(expression.as_bytes());
+    bytes
+}
+
+/// Standard index node of given key and record number, or child node of interior node
+fn idx_node(attributes: u16, entries: &[(&[u8], u32)], right: u32) -> Vec<u8> {
+    let mut bytes = vec![0u8; 512];
+    bytes[0..2].copy_from_slice(&attributes.to_le_bytes());
+    bytes[2..4].copy_from_slice(&(entries.len() as u16).to_le_bytes());
+    bytes[4..8].copy_from_slice(&u32::MAX.to_le_bytes());
+    bytes[8..12].copy_from_slice(&right.to_le_bytes());
+    let mut offset = 12;
+    for (key, number) in entries {
+        bytes[offset..offset + key.len()].copy_from_slice(key);
+        bytes[offset + key.len()..offset + key.len() + 4].copy_from_slice(&number.to_be_bytes());
+        offset += key.len() + 4;
+    }
+    bytes
+}
+
+#[test]
+fn test_idx() {
+    use index::Idx;
+
+    let schema = people_schema();
+    let mut bytes = idx_header(512, 4, 0, "UPPER(name)");
+    bytes.extend(idx_node(1, &[(b"AB  ", 1024), (b"BOB ", 1536)], u32::MAX));
+    bytes.extend(idx_node(2, &[(b"A   ", 4), (b"AB  ", 3)], 1536));
+    bytes.extend(idx_node(2, &[(b"ABC ", 1), (b"BOB ", 2)], u32::MAX));
+    let mut idx = Idx::new(std::io::Cursor::new(bytes), "people", &schema).unwrap();
+    assert!(!idx.is_compact());
+    assert_eq!((idx.tag().name.as_str(), idx.tag().expression.as_str(), idx.tag().key_len()), ("PEOPLE", "UPPER(name)", 4));
+    assert_eq!(idx.record_numbers().unwrap(), vec![4, 3, 1, 2]);
+    assert_eq!(idx.seek(b"AB").unwrap(), vec![3, 1]);
+    assert_eq!(idx.range(b"AC".to_vec()..).unwrap().len(), 1);
+
+    // duplicate keys span many leaves and interior node doesn't tell their record number
+    for record in (10..200).rev() {
+        idx.insert(b"DUP ", record).unwrap();
+    }
+    assert_eq!(idx.seek(b"DUP").unwrap(), (10..200).collect::<Vec<_>>());
+    assert!(idx.remove(b"DUP ", 150).unwrap());
+    assert!(!idx.remove(b"DUP ", 150).unwrap());
+    assert!(!idx.seek(b"DUP").unwrap().contains(&150));
+    assert!(idx.remove(b"BOB ", 2).unwrap());
+    assert_eq!(idx.record_numbers().unwrap().len(), 192);
+    let bytes = idx.into_inner().into_inner();
+    assert_eq!(u32::from_le_bytes([bytes[8], bytes[9], bytes[10], bytes[11]]) as usize, bytes.len());
+
+    let records: Vec<(u32, Vec<u8>)> = [("bob", 1), ("al", 2)].iter().enumerate()
+        .map(|(i, (name, qty))| (i as u32 + 1, format!(" {:<4}{:>5}        ", name, qty).into_bytes()))
+        .collect();
+    let mut idx = Idx::new(std::io::Cursor::new(bytes), "people", &schema).unwrap();
+    idx.reindex(records.clone(), &schema).unwrap();
+    assert_eq!(idx.record_numbers().unwrap(), vec![2, 1]);
+    let bytes = idx.into_inner().into_inner();
+    assert_eq!(&bytes[512..514], &[3, 0]);
+    assert_ne!(&bytes[4..8], &[0xFF; 4]);
+    let mut idx = Idx::new(std::io::Cursor::new(bytes), "people", &schema).unwrap();
+    assert_eq!(idx.update_record(1, &records[0].1, b" ann    1        ", &schema).unwrap(), None);
+    assert_eq!(idx.record_numbers().unwrap(), vec![2, 1]);
+    assert_eq!(idx.seek(b"ANN").unwrap(), vec![1]);
+
+    // compact index is a tag at the start of file
+    let mut bytes = cdx_header(1024, 4, 0, false, "UPPER(name)", "");
+    bytes[14] = 0x20;
+    bytes.extend(cdx_leaf(&[(b"A   ".to_vec(), 4), (b"AB  ".to_vec(), 3)], b' ', u32::MAX));
+    let mut idx = Idx::new(std::io::Cursor::new(bytes), "byname", &schema).unwrap();
+    assert!(idx.is_compact());
+    assert_eq!(idx.record_numbers().unwrap(), vec![4, 3]);
+    idx.insert_record(5, b" abc    1        ", &schema).unwrap();
+    assert_eq!(idx.seek(b"AB").unwrap(), vec![3, 5]);
+    idx.reindex(records.clone(), &schema).unwrap();
+    assert_eq!(idx.record_numbers().unwrap(), vec![2, 1]);
+
+    // key length that no longer match the expression is repaired
+    let mut bytes = cdx_header(1024, 6, 0, false, "UPPER(name)", "");
+    bytes[14] = 0x20;
+    bytes.extend(cdx_leaf(&[], b' ', u32::MAX));
+    let mut idx = Idx::new(std::io::Cursor::new(bytes), "byname", &schema).unwrap();
+    assert!(idx.insert_record(5, b" abc    1        ", &schema).is_err());
+    idx.reindex(records, &schema).unwrap();
+    assert_eq!(idx.tag().key_len(), 4);
+    let bytes = idx.into_inner().into_inner();
+    assert_eq!(&bytes[12..14], &[4, 0]);
+    let mut idx = Idx::new(std::io::Cursor::new(bytes), "byname", &schema).unwrap();
+    assert_eq!(idx.seek(b"AL").unwrap(), vec![2]);
+}
+
+#[test]
+fn test_table_idx() {
+    let path = std::env::temp_dir().join("adbf_rs_idx_people.dbf");
+    write_people_table(&path, people_cdx());
+    let idx_path = std::env::temp_dir().join("ADBF_RS_BYQTY.IDX");
+    let mut bytes = idx_header(512, 8, 0, "qty");
+    bytes.extend(idx_node(3, &[], u32::MAX));
+    std::fs::write(&idx_path, bytes).unwrap();
+
+    let table = block_on(Table::<DynamicRecord>::open(path.to_str().unwrap())).unwrap();
+    assert!(table.open_idx("adbf_rs_nope").unwrap().is_none());
+    assert!(table.seek("adbf_rs_byqty", &Value::Integer(300)).unwrap().is_empty());
+    table.reindex_idx("adbf_rs_byqty").unwrap();
+    let names = |rows: Vec<DynamicRecord>| rows.iter().map(|row| row.get_str(0).unwrap().to_owned()).collect::<Vec<_>>();
+    assert_eq!(names(table.seek("adbf_rs_byqty", &Value::Integer(300)).unwrap()), vec!["bob"]);
+    let rows = table.range("adbf_rs_byqty", ..Value::Integer(7)).unwrap().collect::<Result<Vec<_>>>().unwrap();
+    assert_eq!(names(rows), vec!["ab", "a"]);
+    // tag of structural index comes first
+    assert_eq!(names(table.seek("name", &Value::Char("BOB".to_owned())).unwrap()), vec!["bob"]);
+    assert_eq!(table.open_idx("ADBF_RS_BYQTY").unwrap().unwrap().tag().name, "ADBF_RS_BYQTY");
+}
+
+#[test]
+fn test_table_idx_names() {
+    let dir = std::env::temp_dir().join("adbf_rs_idx_names");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("people.dbf");
+    write_people_table(&path, people_cdx());
+    let write_idx = |name: &str, expression: &str| {
+        let mut bytes = idx_header(512, 8, 0, expression);
+        bytes.extend(idx_node(3, &[], u32::MAX));
+        std::fs::write(dir.join(name), bytes).unwrap();
+    };
+    write_idx("BYQTY.IDX", "qty");
+    write_idx("byname.idx", "UPPER(name)");
+    // index of other table in the same directory
+    write_idx("bycust.idx", "custno");
+    std::fs::write(dir.join("broken.idx"), b"not an index").unwrap();
+
+    let table = block_on(Table::<DynamicRecord>::open(path.to_str().unwrap())).unwrap();
+    assert_eq!(table.idx_names().unwrap(), vec!["BYQTY", "byname"]);
+    assert!(table.open_idx("byname").unwrap().is_some());
+    assert!(Vec::<DynamicRecord>::new().into_iter().collect::<Table<_>>().idx_names().unwrap().is_empty());
+}
+
+/// Path of file written by Visual FoxPro with `tests/fixtures/vfp/make_fixtures.prg`
+fn vfp_fixture(name: &str) -> std::path::PathBuf {
+    std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/vfp").join(name)
+}
+
+/// Name and quantity of record `i` of the fixture table, computed the same way as `make_fixtures.prg`
+fn vfp_person(i: u32) -> (String, i64) {
+    (format!("N{:04}", i * 7919 % 2000), (i * 37 % 101) as i64 - 50)
+}
+
+/// True if root of given single index file is an interior node
+fn idx_has_levels(path: &std::path::Path) -> bool {
+    let bytes = std::fs::read(path).unwrap();
+    let root = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize;
+    bytes[root] & 2 == 0
+}
+
+#[test]
+#[ignore = "needs files written by Visual FoxPro, see tests/fixtures/vfp/make_fixtures.prg"]
+fn test_vfp_index_fixtures() {
+    let names = |rows: Vec<DynamicRecord>| rows.iter().map(|row| row.get_str(0).unwrap().to_owned()).collect::<Vec<_>>();
+    let every_record: Vec<u32> = (1..=2000).collect();
+    let mut by_name = every_record.clone();
+    by_name.sort_by_key(|i| vfp_person(*i).0);
+    // FoxPro may order records of the same key its own way
+    let by_qty = |records: &[u32], descending: bool| {
+        let mut sorted = records.to_vec();
+        sorted.sort_unstable();
+        sorted == every_record && records.windows(2).all(|pair| {
+            let (a, b) = (vfp_person(pair[0]).1, vfp_person(pair[1]).1);
+            if descending { a >= b } else { a <= b }
+        })
+    };
+
+    let table = block_on(Table::<DynamicRecord>::open(vfp_fixture("people.dbf").to_str().unwrap())).unwrap();
+    assert_eq!(table.len(), 2000);
+    let mut cdx = table.open_cdx().unwrap().unwrap();
+    assert_eq!(cdx.tags().iter().map(|tag| tag.name.as_str()).collect::<Vec<_>>(), vec!["NAME", "QTYD"]);
+    assert!(cdx.tag("QTYD").unwrap().descending);
+    assert_eq!(cdx.record_numbers("NAME").unwrap(), by_name);
+    assert!(by_qty(&cdx.record_numbers("QTYD").unwrap(), true));
+    assert_eq!(names(table.seek("name", &Value::Char("N0042".to_owned())).unwrap()), vec!["N0042"]);
+    let sevens = every_record.iter().filter(|i| vfp_person(**i).1 == 7).count();
+    assert_eq!(table.seek("qtyd", &Value::Integer(7)).unwrap().len(), sevens);
+
+    let mut byname = table.open_idx("byname").unwrap().unwrap();
+    assert!(byname.is_compact());
+    assert_eq!(byname.record_numbers().unwrap(), by_name);
+    let mut byqty = table.open_idx("byqty").unwrap().unwrap();
+    assert!(!byqty.is_compact());
+    assert!(by_qty(&byqty.record_numbers().unwrap(), false));
+    assert!(idx_has_levels(&vfp_fixture("byname.idx")));
+    assert!(idx_has_levels(&vfp_fixture("byqty.idx")));
+
+    // index written by FoxPro is kept up to date
+    let dir = std::env::temp_dir().join("adbf_rs_vfp");
+    std::fs::create_dir_all(&dir).unwrap();
+    for name in ["people.dbf", "people.cdx"].iter() {
+        std::fs::copy(vfp_fixture(name), dir.join(name)).unwrap();
+    }
+    let mut table = block_on(Table::<DynamicRecord>::open(dir.join("people.dbf").to_str().unwrap())).unwrap();
+    let mut row = DynamicRecord::new(table.schema().clone());
+    row.set_str(0, "N0042A").unwrap();
+    row.set_i32(1, 99).unwrap();
+    table.insert_owned(row).unwrap();
+    assert_eq!(names(table.seek("name", &Value::Char("N0042".to_owned())).unwrap()), vec!["N0042", "N0042A"]);
+    assert_eq!(table.open_cdx().unwrap().unwrap().record_numbers("QTYD").unwrap()[0], 2001);
+}
@@ -2,15 +2,18 @@ use chrono::naive::{
     NaiveDate,
     NaiveDateTime
 };
-use encoding_rs::{CoderResult, Encoder, Encoding, Decoder};
+use encoding_rs::{Encoder, Encoding, Decoder};
 use futures::{
     future::{
         BoxFuture,
-        Future,
-        Lazy,
-        lazy
+        Future
+    },
+    io::{
+        AsyncRead,
+        AsyncReadExt,
+        AsyncSeek,
+        AsyncSeekExt
     },
-    lock::Mutex,
     stream::Stream,
     task::{
         Context,
@@ -32,6 +35,7 @@ use std::{
         FromIterator,
         FusedIterator
     },
+    marker::PhantomData,
     ops::{
         Deref,
         DerefMut,
@@ -44,35 +48,233 @@ use std::{
 #[cfg(test)]
 mod tests;
 
+pub mod dbase;
 pub mod foxpro;
 
-pub fn get_encoding(cp: &str) -> &'static Encoding {
-    match Encoding::for_label(cp.as_bytes()) {
-        Some(e) => e,
-        None => {
-            panic!("Fail to find encoding for codepage {}", cp)
+/// Shared pointer to bytes of a field. It is `Arc` with `threaded` feature so
+/// fields can be sent across threads, otherwise it is `Box`.
+#[cfg(not(feature = "threaded"))]
+pub(crate) type MemReferer<T> = Box<T>;
+
+#[cfg(feature = "threaded")]
+pub(crate) type MemReferer<T> = std::sync::Arc<T>;
+
+/// Derive [RecordOps](trait.RecordOps.html) from `#[dbf(...)]` annotated struct.
+/// It requires `derive` feature.
+#[cfg(feature = "derive")]
+pub use adbf_rs_derive::RecordOps;
+
+/// Error that may occur while reading or writing DBF file.
+#[derive(Debug)]
+pub enum Error {
+    /// Underlying IO operation fail.
+    Io(std::io::Error),
+    /// Source has less bytes than expected.
+    Truncated {
+        expected: usize,
+        found: usize
+    },
+    /// Codepage mark or codepage label that this crate cannot map to any encoding.
+    UnknownCodepage(String),
+    /// Field type flag that is not supported or doesn't match the field being read.
+    BadFieldType(u8),
+    /// Bytes cannot be decoded into the expected value.
+    Decode(String),
+    /// Bytes of a date field that is neither empty nor a valid date.
+    BadDate(Vec<u8>),
+    /// Value cannot be encoded into bytes.
+    Encode(String),
+    /// Value is larger than the space reserved for it.
+    Overflow {
+        size: usize,
+        max: usize
+    },
+    /// Header contains invalid value.
+    BadHeader(String),
+    /// Operation is not supported by the implementation.
+    Unsupported(&'static str),
+    /// There's no record at given index.
+    RecordNotFound(usize),
+    /// There's no field of given name or index.
+    FieldNotFound(String),
+    /// There's no index tag of given name.
+    TagNotFound(String)
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Io(err) => write!(f, "IO error: {}", err),
+            Error::Truncated { expected, found } => write!(f, "Expect {} bytes but found only {} bytes", expected, found),
+            Error::UnknownCodepage(cp) => write!(f, "Unknown codepage {}", cp),
+            Error::BadFieldType(flag) => write!(f, "Bad field type {:?}", *flag as char),
+            Error::Decode(msg) => write!(f, "Fail to decode value: {}", msg),
+            Error::BadDate(bytes) => write!(f, "Bad date {:?}", String::from_utf8_lossy(bytes)),
+            Error::Encode(msg) => write!(f, "Fail to encode value: {}", msg),
+            Error::Overflow { size, max } => write!(f, "Value is {} bytes but max length is {} bytes", size, max),
+            Error::BadHeader(msg) => write!(f, "Bad header: {}", msg),
+            Error::Unsupported(op) => write!(f, "Operation not support: {}", op),
+            Error::RecordNotFound(i) => write!(f, "Record {} not found", i),
+            Error::FieldNotFound(name) => write!(f, "Field {} not found", name),
+            Error::TagNotFound(name) => write!(f, "Index tag {} not found", name)
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(err) => Some(err),
+            _ => None
         }
     }
 }
 
-pub fn get_decoder(cp: &str) -> Decoder {
-    let encoding = get_encoding(cp);
-    encoding.new_decoder()
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Error {
+        Error::Io(err)
+    }
+}
+
+/// Result type of every fallible operation in this crate.
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Label that encoding_rs doesn't recognize but is used by DBF codepage mark.
+fn codepage_alias(cp: &str) -> Option<&'static str> {
+    match cp {
+        "cp10000" => Some("macintosh"),
+        "cp10007" => Some("x-mac-cyrillic"),
+        "cp932" => Some("shift_jis"),
+        "cp936" => Some("gbk"),
+        "cp949" => Some("euc-kr"),
+        "cp950" => Some("big5"),
+        "tis620" => Some("tis-620"),
+        _ => None
+    }
 }
 
-pub fn get_encoder(cp: &str) -> Encoder {
-    let encoding = get_encoding(cp);
-    encoding.new_encoder()
+pub fn get_encoding(cp: &str) -> Result<&'static Encoding> {
+    Encoding::for_label(cp.as_bytes())
+        .or_else(|| codepage_alias(cp).and_then(|alias| Encoding::for_label(alias.as_bytes())))
+        .ok_or_else(|| Error::UnknownCodepage(cp.to_owned()))
+}
+
+pub fn get_decoder(cp: &str) -> Result<Decoder> {
+    let encoding = get_encoding(cp)?;
+    Ok(encoding.new_decoder())
+}
+
+pub fn get_encoder(cp: &str) -> Result<Encoder> {
+    let encoding = get_encoding(cp)?;
+    Ok(encoding.new_encoder())
+}
+
+/// Decode all given bytes into String using given codepage.
+/// Bytes that cannot be decoded result in `Error::Decode`.
+pub fn decode(cp: &str, bytes: &[u8]) -> Result<String> {
+    let (value, had_errors) = get_encoding(cp)?.decode_without_bom_handling(bytes);
+    if had_errors {
+        Err(Error::Decode(format!("bytes is not a valid {} string", cp)))
+    } else {
+        Ok(value.into_owned())
+    }
+}
+
+/// Encode given string into bytes using given codepage.
+/// Character that cannot be represented in the codepage result in `Error::Encode`.
+pub fn encode(cp: &str, value: &str) -> Result<Vec<u8>> {
+    let (bytes, _, had_errors) = get_encoding(cp)?.encode(value);
+    if had_errors {
+        Err(Error::Encode(format!("{} cannot be represented in {}", value, cp)))
+    } else {
+        Ok(bytes.into_owned())
+    }
+}
+
+/// Take first `N` bytes of given slice as array.
+/// If slice is shorter than `N` bytes, it return `Error::Truncated`.
+pub fn take_bytes<const N: usize>(bytes: &[u8]) -> Result<[u8; N]> {
+    match bytes.get(..N) {
+        Some(b) => Ok(b.try_into().expect("Slice length is checked")),
+        None => Err(Error::Truncated {
+            expected: N,
+            found: bytes.len()
+        })
+    }
+}
+
+/// Slice `size` bytes starting at `offset` out of a record.
+/// If record is too short, it return `Error::Truncated`.
+pub fn record_slice(record: &[u8], offset: usize, size: usize) -> Result<&[u8]> {
+    record.get(offset..(offset + size)).ok_or(Error::Truncated {
+        expected: offset + size,
+        found: record.len()
+    })
+}
+
+/// Copy field bytes into `size` bytes of a record starting at `offset`.
+/// The rest of the field is filled with `pad`.
+/// If the bytes is longer than `size`, it return `Error::Overflow`.
+pub fn put_field(record: &mut [u8], offset: usize, size: usize, bytes: &[u8], pad: u8) -> Result<()> {
+    if bytes.len() > size {
+        return Err(Error::Overflow {
+            size: bytes.len(),
+            max: size
+        });
+    }
+    let record_len = record.len();
+    let field = record.get_mut(offset..(offset + size)).ok_or(Error::Truncated {
+        expected: offset + size,
+        found: record_len
+    })?;
+    field[..bytes.len()].copy_from_slice(bytes);
+    field[bytes.len()..].iter_mut().for_each(|b| *b = pad);
+    Ok(())
+}
+
+/// Fill entire buffer from given source.
+/// If source end before buffer is filled, it return `Error::Truncated`.
+pub fn read_full<R: Read>(source: &mut R, buffer: &mut [u8]) -> Result<()> {
+    let mut total_read = 0;
+    while total_read < buffer.len() {
+        match source.read(&mut buffer[total_read..]) {
+            Ok(0) => return Err(Error::Truncated {
+                expected: buffer.len(),
+                found: total_read
+            }),
+            Ok(readed) => total_read += readed,
+            Err(err) if err.kind() == std::io::ErrorKind::Interrupted => (),
+            Err(err) => return Err(Error::Io(err))
+        }
+    }
+    Ok(())
+}
+
+/// Same as [read_full](fn.read_full.html) but read from async source.
+pub async fn read_full_async<R: AsyncRead + Unpin>(source: &mut R, buffer: &mut [u8]) -> Result<()> {
+    let mut total_read = 0;
+    while total_read < buffer.len() {
+        match source.read(&mut buffer[total_read..]).await {
+            Ok(0) => return Err(Error::Truncated {
+                expected: buffer.len(),
+                found: total_read
+            }),
+            Ok(readed) => total_read += readed,
+            Err(err) if err.kind() == std::io::ErrorKind::Interrupted => (),
+            Err(err) => return Err(Error::Io(err))
+        }
+    }
+    Ok(())
 }
 
 /// All field that may need a conversion layer between actual
 /// value and underlying value
 pub trait ConversionField<T> {
-    fn get(&self) -> T;
-    fn set(&mut self, value: &T);
+    fn get(&self) -> Result<T>;
+    fn set(&mut self, value: &T) -> Result<()>;
 }
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub enum DBFType {
     FoxBase,
     DBaseIIIPlus,
@@ -87,6 +289,7 @@ pub enum DBFType {
     DBaseIVMemos,
     DBaseIVSQLTable,
     FoxProMemos,
+    #[default]
     Undefined
 }
 
@@ -137,6 +340,41 @@ impl DBFType {
             }
         }
     }
+
+    /// Return a first byte of dbf file that represent this type.
+    /// It is an inverse of [parse_type](enum.DBFType.html#method.parse_type).
+    /// `Undefined` has no such byte so it return `None`.
+    pub fn flag(&self) -> Option<u8> {
+        match self {
+            DBFType::FoxBase => Some(0x02),
+            DBFType::DBaseIIIPlus => Some(0x03),
+            DBFType::DBaseIV => Some(0x04),
+            DBFType::DBaseV => Some(0x05),
+            DBFType::VisualFoxPro => Some(0x30),
+            DBFType::VisualFoxProAutoInc => Some(0x31),
+            DBFType::VisualFoxProVarBLOB => Some(0x32),
+            DBFType::DBaseIVSQLTableFiles => Some(0x43),
+            DBFType::DBaseIVSQLSystem => Some(0x63),
+            DBFType::DBaseIIIPlusMemos => Some(0x83),
+            DBFType::DBaseIVMemos => Some(0x8b),
+            DBFType::DBaseIVSQLTable => Some(0x8e),
+            DBFType::FoxProMemos => Some(0xf5),
+            DBFType::Undefined => None
+        }
+    }
+
+    /// Return true if this type is one of Visual FoxPro type which has
+    /// a 263 bytes backlink after field subrecords.
+    pub fn is_visual_foxpro(&self) -> bool {
+        matches!(self, DBFType::VisualFoxPro | DBFType::VisualFoxProAutoInc | DBFType::VisualFoxProVarBLOB)
+    }
+
+    /// Return true if this type is one of dBase type which can be read by
+    /// [dbase](dbase/index.html) module.
+    pub fn is_dbase(&self) -> bool {
+        matches!(self, DBFType::DBaseIIIPlus | DBFType::DBaseIV | DBFType::DBaseV | DBFType::DBaseIIIPlusMemos | DBFType::DBaseIVMemos
+            | DBFType::DBaseIVSQLTableFiles | DBFType::DBaseIVSQLSystem | DBFType::DBaseIVSQLTable)
+    }
 }
 
 pub enum DataType {
@@ -188,10 +426,12 @@ pub trait FieldMeta {
 /// Operation conversion from/to bytes into field
 pub trait FieldOps : FieldMeta + Display + Send {
     /// Parse bytes based on current meta data and update the state
-    fn from_record_bytes(&mut self) -> BoxFuture<()>;
+    #[allow(clippy::wrong_self_convention)]
+    fn from_record_bytes(&mut self) -> BoxFuture<'_, Result<()>>;
     /// Return bytes represent by this field.
     /// The result is a byte slice with length equals to size stored in meta data.
-    fn to_bytes(&self) -> BoxFuture<&[u8]>;
+    /// It is `Error::Truncated` if the record ends before the field does.
+    fn to_bytes(&self) -> BoxFuture<'_, Result<&[u8]>>;
     /// Return true if the field is ready to be read
     fn ready(&self) -> bool;
 }
@@ -202,6 +442,7 @@ pub trait RawSize {
     fn size() -> usize;
 }
 
+#[allow(dead_code)]
 pub struct TableFile<R> where R: Read {
     f: std::sync::Mutex<R>
 }
@@ -225,40 +466,32 @@ impl<R, T> RecordFuture<R, T> where R: std::io::Read + std::io::Seek, T: RawSize
 }
 
 impl<R, T> Future for Box<RecordFuture<R, T>> where R: std::io::Read + std::io::Seek, T: RawSize + Clone + RecordOps {
-    type Output=T;
+    type Output=Result<T>;
 
-    fn poll(mut self: Pin<&mut Self>, context: &mut Context) -> Poll<T> {
+    fn poll(mut self: Pin<&mut Self>, context: &mut Context) -> Poll<Result<T>> {
         if let Some(ref v) = self.value {
-            Poll::Ready(v.clone())
+            Poll::Ready(Ok(v.clone()))
         } else {
             let offset = self.first_record_offset;
-            let mut buffer: Vec<u8> = Vec::with_capacity(T::size());
-            unsafe {buffer.set_len(T::size());}
+            let mut buffer: Vec<u8> = vec![0; T::size()];
             let mut value = None;
 
             match self.raw.try_lock() {
                 std::sync::TryLockResult::Err(_) => (),
                 std::sync::TryLockResult::Ok(mut locked_file) => {
-                    locked_file.seek(std::io::SeekFrom::Start(offset)).unwrap();
-                    let mut total_read = 0;
-                    loop {
-                        if let Ok(readed) = locked_file.read(&mut buffer[total_read..]) {
-                            total_read = total_read + readed;
-
-                            if total_read == buffer.len() {
-                                break;
-                            }
-                        } else {
-                            panic!("Fail to fill buffer using source IO")
-                        }
+                    if let Err(err) = locked_file.seek(std::io::SeekFrom::Start(offset)) {
+                        return Poll::Ready(Err(Error::Io(err)));
+                    }
+                    if let Err(err) = read_full(&mut *locked_file, &mut buffer) {
+                        return Poll::Ready(Err(err));
                     }
-                    value = Some(T::from_bytes(&buffer));
+                    value = Some(T::from_bytes(&buffer)?);
                 }
             }
             
             if let Some(v) = value {
                 self.value = Some(v.clone());
-                Poll::Ready(v)
+                Poll::Ready(Ok(v))
             } else {
                 self.waker = Some(context.waker().clone());
                 Poll::Pending
@@ -267,6 +500,119 @@ impl<R, T> Future for Box<RecordFuture<R, T>> where R: std::io::Read + std::io::
     }
 }
 
+/// A `Stream` of records read from any async source such as a DBF file.
+/// 
+/// It start at [Header::first_record_position](struct.Header.html#structfield.first_record_position)
+/// and read [Header::record_len](struct.Header.html#structfield.record_len) bytes at a time
+/// for [Header::records_count](struct.Header.html#structfield.records_count) records.
+/// Only one record is kept in memory at any time so it is suitable for scanning
+/// a table that is too large to fit in an `InMemoryTable`.
+/// 
+/// Each record is given to `T::from_bytes_with_schema` as a whole, including the deletion flag at byte 0.
+pub struct RecordStream<R, T> where R: AsyncRead + AsyncSeek + Unpin, T: RecordOps {
+    source: R,
+    first_record_position: u64,
+    remaining: usize,
+    include_deleted: bool,
+    positioned: bool,
+    buffer: Vec<u8>,
+    filled: usize,
+    schema: foxpro::Schema,
+    _row: PhantomData<fn() -> T>
+}
+
+impl<R, T> RecordStream<R, T> where R: AsyncRead + AsyncSeek + Unpin, T: RecordOps {
+    /// Create a stream over `source` using the record layout from given header.
+    pub fn new(source: R, header: &Header) -> RecordStream<R, T> {
+        RecordStream {
+            source,
+            first_record_position: header.first_record_position as u64,
+            remaining: header.records_count,
+            include_deleted: false,
+            positioned: false,
+            buffer: vec![0; header.record_len],
+            filled: 0,
+            schema: foxpro::Schema::new(Vec::new(), header.codepage),
+            _row: PhantomData
+        }
+    }
+
+    /// Give schema of the table to each record.
+    /// See [RecordOps::from_bytes_with_schema](trait.RecordOps.html#method.from_bytes_with_schema).
+    pub fn with_schema(mut self, schema: foxpro::Schema) -> RecordStream<R, T> {
+        self.schema = schema;
+        self
+    }
+
+    /// By default, records that are marked as deleted are skipped.
+    /// Set `include` to true to also yield deleted records.
+    pub fn include_deleted(mut self, include: bool) -> RecordStream<R, T> {
+        self.include_deleted = include;
+        self
+    }
+}
+
+impl<R, T> Stream for RecordStream<R, T> where R: AsyncRead + AsyncSeek + Unpin, T: RecordOps {
+    type Item=Result<T>;
+
+    fn poll_next(self: Pin<&mut Self>, context: &mut Context) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        if this.remaining == 0 {
+            return Poll::Ready(None);
+        }
+
+        if !this.positioned {
+            let target = std::io::SeekFrom::Start(this.first_record_position);
+            match Pin::new(&mut this.source).poll_seek(context, target) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(Err(err)) => {
+                    this.remaining = 0;
+                    return Poll::Ready(Some(Err(Error::Io(err))));
+                },
+                Poll::Ready(Ok(_)) => this.positioned = true
+            }
+        }
+
+        loop {
+            while this.filled < this.buffer.len() {
+                match Pin::new(&mut this.source).poll_read(context, &mut this.buffer[this.filled..]) {
+                    Poll::Pending => return Poll::Pending,
+                    Poll::Ready(Ok(0)) => {
+                        this.remaining = 0;
+                        return Poll::Ready(Some(Err(Error::Truncated {
+                            expected: this.buffer.len(),
+                            found: this.filled
+                        })));
+                    },
+                    Poll::Ready(Ok(readed)) => this.filled += readed,
+                    Poll::Ready(Err(err)) => {
+                        this.remaining = 0;
+                        return Poll::Ready(Some(Err(Error::Io(err))));
+                    }
+                }
+            }
+
+            this.filled = 0;
+            this.remaining -= 1;
+
+            if this.include_deleted || this.buffer.first() != Some(&b'*') {
+                return Poll::Ready(Some(T::from_bytes_with_schema(&this.buffer, &this.schema)));
+            } else if this.remaining == 0 {
+                return Poll::Ready(None);
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        if self.include_deleted {
+            (self.remaining, Some(self.remaining))
+        } else {
+            (0, Some(self.remaining))
+        }
+    }
+}
+
 /// Type wrapper to wrap trait object inside Vec.
 /// This ease user on type annotation only.
 /// It implement Deref and DerefMut into Vec so
@@ -277,15 +623,33 @@ impl<R, T> Future for Box<RecordFuture<R, T>> where R: std::io::Read + std::io::
 /// or simply call `load_all` which will load every field in this record.
 /// Both method is `async` which mean user need to `await` for
 /// each field to be ready to read.
-pub struct Record (Vec<Box<dyn FieldOps>>);
+pub struct Record<'a> (Vec<Box<dyn FieldOps + 'a>>);
+
+impl<'a> Record<'a> {
+    /// Build a record over given record bytes with one field per visible field in the schema.
+    /// Fields are not loaded yet. Call `load_all` to parse them.
+    /// Field type that has no field of its own, e.g. `N`, `I` or `M`, is
+    /// [foxpro::ValueField](foxpro/struct.ValueField.html).
+    pub fn from_schema(schema: &foxpro::Schema, record: &'a [u8]) -> Result<Record<'a>> {
+        schema.fields().iter().enumerate().map(|(i, field)| {
+            let meta = field.clone();
+            Ok(match field.datatype_flag() {
+                b'C' => Box::new(foxpro::CharField::new(meta, schema.codepage(), record)) as Box<dyn FieldOps + 'a>,
+                b'Y' => Box::new(foxpro::CurrencyField::new(meta, record)),
+                b'D' => Box::new(foxpro::DateField::new(meta, record)),
+                b'T' => Box::new(foxpro::DateTimeField::new(meta, record)),
+                b'L' => Box::new(foxpro::LogicalField::new(meta, record)),
+                _ => Box::new(foxpro::ValueField::new(schema, i, record)?)
+            })
+        }).collect::<Result<Vec<_>>>().map(Record)
+    }
 
-impl Record {
     /// Load up all fields in this record.
     /// It return Vec of Future where each Future represent
     /// each field byte parsing.
     /// The order of Future in Vec is similar to the order
     /// of field in the record.
-    pub fn load_all(&mut self) -> Vec<BoxFuture<()>> {
+    pub fn load_all(&mut self) -> Vec<BoxFuture<'_, Result<()>>> {
         self.iter_mut().map(|field| field.from_record_bytes()).collect()
     }
     /// Load up all fields in this record.
@@ -293,25 +657,25 @@ impl Record {
     /// each field byte parsing.
     /// When any field is loaded, the future return an index of completed
     /// one. This can help improve efficiency when order is unimportant.
-    pub fn load_all_unordered(&mut self) -> Vec<BoxFuture<usize>> {
+    pub fn load_all_unordered(&mut self) -> Vec<BoxFuture<'_, Result<usize>>> {
         self.iter_mut().enumerate().map(|(i, field)| 
             Box::pin(async move {
-                field.from_record_bytes();
-                i
-            }) as BoxFuture<usize> // need explicit type
+                field.from_record_bytes().await?;
+                Ok(i)
+            }) as BoxFuture<Result<usize>> // need explicit type
         ).collect()
     }
 }
 
-impl Deref for Record {
-    type Target=Vec<Box<dyn FieldOps>>;
+impl<'a> Deref for Record<'a> {
+    type Target=Vec<Box<dyn FieldOps + 'a>>;
 
     fn deref(&self) -> &Self::Target {
         &self.0
     }
 }
 
-impl DerefMut for Record {
+impl<'a> DerefMut for Record<'a> {
     fn deref_mut(&mut self) -> &mut Self::Target {
         &mut self.0
     }
@@ -327,201 +691,232 @@ impl DerefMut for Record {
 /// struct into `Vec<u8>` so that it can be persisted.
 pub trait RecordOps {
     /// Parse a slice of bytes and construct a record from it.
-    fn from_bytes(record: &[u8]) -> Self;
+    fn from_bytes(record: &[u8]) -> Result<Self> where Self: Sized;
+    /// Parse a slice of bytes using [Schema](foxpro/struct.Schema.html) read from the table.
+    /// 
+    /// This is what [foxpro::Table](foxpro/struct.Table.html) and [RecordStream](struct.RecordStream.html)
+    /// call when they know the table layout. Record that has fixed layout doesn't need
+    /// the table layout so by default, it simply call `from_bytes`.
+    /// Record that is built from the table layout, such as
+    /// [foxpro::DynamicRecord](foxpro/struct.DynamicRecord.html), shall override this.
+    #[allow(unused)]
+    fn from_bytes_with_schema(record: &[u8], schema: &foxpro::Schema) -> Result<Self> where Self: Sized {
+        Self::from_bytes(record)
+    }
     /// Convert this struct as `Vec<u8>`
-    fn to_bytes(&self) -> Vec<u8>;
+    fn to_bytes(&self) -> Result<Vec<u8>>;
+    /// Return true if this record is marked as deleted.
+    /// The deletion flag is the first byte of record. It is `*` when
+    /// the record is deleted or space otherwise.
+    /// 
+    /// Record that doesn't keep the flag is never deleted.
+    fn is_deleted(&self) -> bool {
+        false
+    }
 }
 
 pub trait DynamicRecordOps: RecordOps {
     /// Get a field of this record at index `i` as string
     #[allow(unused)]
-    fn get_string(&self, i: usize) -> &String {
-        unimplemented!("Operation not support")
+    fn get_string(&self, i: usize) -> Result<&String> {
+        Err(Error::Unsupported("get_string"))
     }
     /// Set a field of this record at index `i` as given string
-    #[allow(unused)]
-    fn set_string(&mut self, i: usize, value: &String) {
-        unimplemented!("Operation not support")
+    #[allow(unused, clippy::ptr_arg)]
+    fn set_string(&mut self, i: usize, value: &String) -> Result<()> {
+        Err(Error::Unsupported("set_string"))
     }
 
     /// Get a field of this record at index `i` as &str
     #[allow(unused)]
-    fn get_str(&self, i: usize) -> &str {
-        unimplemented!("Operation not support")
+    fn get_str(&self, i: usize) -> Result<&str> {
+        Err(Error::Unsupported("get_str"))
     }
 
     /// Set a field of this record at index `i` as &str
     #[allow(unused)]
-    fn set_str(&mut self, i: usize, value: &str) {
-        unimplemented!("Operation not support")
+    fn set_str(&mut self, i: usize, value: &str) -> Result<()> {
+        Err(Error::Unsupported("set_str"))
     }
 
     /// Get a field of this record at index `i` as u8
     #[allow(unused)]
-    fn get_u8(&self, i: usize) -> u8 {
-        unimplemented!("Operation not support")
+    fn get_u8(&self, i: usize) -> Result<u8> {
+        Err(Error::Unsupported("get_u8"))
     }
 
     /// Set a field of this record at index `i` as u8
     #[allow(unused)]
-    fn set_u8(&mut self, i: usize, value: u8) {
-        unimplemented!("Operation not support")
+    fn set_u8(&mut self, i: usize, value: u8) -> Result<()> {
+        Err(Error::Unsupported("set_u8"))
     }
 
     /// Get a field of this record at index `i` as u16
     #[allow(unused)]
-    fn get_u16(&self, i: usize) -> u16 {
-        unimplemented!("Operation not support")
+    fn get_u16(&self, i: usize) -> Result<u16> {
+        Err(Error::Unsupported("get_u16"))
     }
 
     /// Set a field of this record at index `i` as u16
     #[allow(unused)]
-    fn set_u16(&mut self, i: usize, value: u16) {
-        unimplemented!("Operation not support")
+    fn set_u16(&mut self, i: usize, value: u16) -> Result<()> {
+        Err(Error::Unsupported("set_u16"))
     }
 
     /// Get a field of this record at index `i` as u32
     #[allow(unused)]
-    fn get_u32(&self, i: usize) -> u32 {
-        unimplemented!("Operation not support")
+    fn get_u32(&self, i: usize) -> Result<u32> {
+        Err(Error::Unsupported("get_u32"))
     }
 
     /// Set a field of this record at index `i` as u32
     #[allow(unused)]
-    fn set_u32(&mut self, i: usize, value: u32) {
-        unimplemented!("Operation not support")
+    fn set_u32(&mut self, i: usize, value: u32) -> Result<()> {
+        Err(Error::Unsupported("set_u32"))
     }
 
     /// Get a field of this record at index `i` as u64
     #[allow(unused)]
-    fn get_u64(&self, i: usize) -> u64 {
-        unimplemented!("Operation not support")
+    fn get_u64(&self, i: usize) -> Result<u64> {
+        Err(Error::Unsupported("get_u64"))
     }
 
     /// Set a field of this record at index `i` as u64
     #[allow(unused)]
-    fn set_u64(&mut self, i: usize, value: u64) {
-        unimplemented!("Operation not support")
+    fn set_u64(&mut self, i: usize, value: u64) -> Result<()> {
+        Err(Error::Unsupported("set_u64"))
     }
 
     /// Get a field of this record at index `i` as u128
     #[allow(unused)]
-    fn get_u128(&self, i: usize) -> u128 {
-        unimplemented!("Operation not support")
+    fn get_u128(&self, i: usize) -> Result<u128> {
+        Err(Error::Unsupported("get_u128"))
     }
 
     /// Set a field of this record at index `i` as u128
     #[allow(unused)]
-    fn set_u128(&mut self, i: usize, value: u128) {
-        unimplemented!("Operation not support")
+    fn set_u128(&mut self, i: usize, value: u128) -> Result<()> {
+        Err(Error::Unsupported("set_u128"))
     }
 
     /// Get a field of this record at index `i` as i8
     #[allow(unused)]
-    fn get_i8(&self, i: usize) -> i8 {
-        unimplemented!("Operation not support")
+    fn get_i8(&self, i: usize) -> Result<i8> {
+        Err(Error::Unsupported("get_i8"))
     }
 
     /// Set a field of this record at index `i` as i8
     #[allow(unused)]
-    fn set_i8(&mut self, i: usize, value: i8) {
-        unimplemented!("Operation not support")
+    fn set_i8(&mut self, i: usize, value: i8) -> Result<()> {
+        Err(Error::Unsupported("set_i8"))
     }
 
     /// Get a field of this record at index `i` as i16
     #[allow(unused)]
-    fn get_i16(&self, i: usize) -> i16 {
-        unimplemented!("Operation not support")
+    fn get_i16(&self, i: usize) -> Result<i16> {
+        Err(Error::Unsupported("get_i16"))
     }
 
     /// Set a field of this record at index `i` as i16
     #[allow(unused)]
-    fn set_i16(&mut self, i: usize, value: i16) {
-        unimplemented!("Operation not support")
+    fn set_i16(&mut self, i: usize, value: i16) -> Result<()> {
+        Err(Error::Unsupported("set_i16"))
     }
 
     /// Get a field of this record at index `i` as i32
     #[allow(unused)]
-    fn get_i32(&self, i: usize) -> i32 {
-        unimplemented!("Operation not support")
+    fn get_i32(&self, i: usize) -> Result<i32> {
+        Err(Error::Unsupported("get_i32"))
     }
 
     /// Set a field of this record at index `i` as i32
     #[allow(unused)]
-    fn set_i32(&mut self, i: usize, value: i32) {
-        unimplemented!("Operation not support")
+    fn set_i32(&mut self, i: usize, value: i32) -> Result<()> {
+        Err(Error::Unsupported("set_i32"))
     }
 
     /// Get a field of this record at index `i` as i64
     #[allow(unused)]
-    fn get_i64(&self, i: usize) -> i64 {
-        unimplemented!("Operation not support")
+    fn get_i64(&self, i: usize) -> Result<i64> {
+        Err(Error::Unsupported("get_i64"))
     }
 
     /// Set a field of this record at index `i` as i64
     #[allow(unused)]
-    fn set_i64(&mut self, i: usize, value: i64) {
-        unimplemented!("Operation not support")
+    fn set_i64(&mut self, i: usize, value: i64) -> Result<()> {
+        Err(Error::Unsupported("set_i64"))
     }
 
     /// Get a field of this record at index `i` as i128
     #[allow(unused)]
-    fn get_i128(&self, i: usize) -> i128 {
-        unimplemented!("Operation not support")
+    fn get_i128(&self, i: usize) -> Result<i128> {
+        Err(Error::Unsupported("get_i128"))
     }
 
     /// Set a field of this record at index `i` as i128
     #[allow(unused)]
-    fn set_i128(&mut self, i: usize, value: i128) {
-        unimplemented!("Operation not support")
+    fn set_i128(&mut self, i: usize, value: i128) -> Result<()> {
+        Err(Error::Unsupported("set_i128"))
     }
 
     /// Get a field of this record at index `i` as f32
     #[allow(unused)]
-    fn get_f32(&self, i: usize) -> f32 {
-        unimplemented!("Operation not support")
+    fn get_f32(&self, i: usize) -> Result<f32> {
+        Err(Error::Unsupported("get_f32"))
     }
 
     /// Set a field of this record at index `i` as f32
     #[allow(unused)]
-    fn set_f32(&mut self, i: usize, value: f32) {
-        unimplemented!("Operation not support")
+    fn set_f32(&mut self, i: usize, value: f32) -> Result<()> {
+        Err(Error::Unsupported("set_f32"))
     }
 
     /// Get a field of this record at index `i` as f64
     #[allow(unused)]
-    fn get_f64(&self, i: usize) -> f64 {
-        unimplemented!("Operation not support")
+    fn get_f64(&self, i: usize) -> Result<f64> {
+        Err(Error::Unsupported("get_f64"))
     }
 
     /// Set a field of this record at index `i` as f64
     #[allow(unused)]
-    fn set_f64(&mut self, i: usize, value: f64) {
-        unimplemented!("Operation not support")
+    fn set_f64(&mut self, i: usize, value: f64) -> Result<()> {
+        Err(Error::Unsupported("set_f64"))
     }
 
     /// Get a field of this record at index `i` as `NaiveDate`
     #[allow(unused)]
-    fn get_date(&self, i: usize) -> NaiveDate {
-        unimplemented!("Operation not support")
+    fn get_date(&self, i: usize) -> Result<NaiveDate> {
+        Err(Error::Unsupported("get_date"))
     }
 
     /// Set a field of this record at index `i` as given NaiveDate
     #[allow(unused)]
-    fn set_date(&mut self, i: usize, value: &NaiveDate) {
-        unimplemented!("Operation not support")
+    fn set_date(&mut self, i: usize, value: &NaiveDate) -> Result<()> {
+        Err(Error::Unsupported("set_date"))
     }
 
     /// Get a field of this record at index `i` as `NaiveDateTime`
     #[allow(unused)]
-    fn get_datetime(&self, i: usize) -> NaiveDateTime {
-        unimplemented!("Operation not support")
+    fn get_datetime(&self, i: usize) -> Result<NaiveDateTime> {
+        Err(Error::Unsupported("get_datetime"))
     }
 
     /// Set a field of this record at index `i` as given NaiveDateTime
     #[allow(unused)]
-    fn set_datetime(&mut self, i: usize, value: &NaiveDateTime) {
-        unimplemented!("Operation not support")
+    fn set_datetime(&mut self, i: usize, value: &NaiveDateTime) -> Result<()> {
+        Err(Error::Unsupported("set_datetime"))
+    }
+
+    /// Get a field of this record at index `i` as bool
+    #[allow(unused)]
+    fn get_bool(&self, i: usize) -> Result<bool> {
+        Err(Error::Unsupported("get_bool"))
+    }
+
+    /// Set a field of this record at index `i` as bool
+    #[allow(unused)]
+    fn set_bool(&mut self, i: usize, value: bool) -> Result<()> {
+        Err(Error::Unsupported("set_bool"))
     }
 }
 
@@ -580,13 +975,17 @@ pub trait TableOps: IntoIterator<Item=<Self as TableOps>::Row> + Index<usize, Ou
 
     /// Insert all the rows into this table.
     /// Each row shall be clonable.
-    fn insert(&mut self, rows: &[Self::Row]) where Self::Row: Clone {
-        rows.iter().for_each(|r| self.insert_owned(r.clone()));
+    fn insert(&mut self, rows: &[Self::Row]) -> Result<()> where Self::Row: Clone {
+        rows.iter().try_for_each(|r| self.insert_owned(r.clone()))
     }
 
     /// Take the row and put it into this table.
     /// This won't clone the row.
-    fn insert_owned(&mut self, row: Self::Row);
+    ///
+    /// Table that is backed by a file may refuse the row, leaving both the table and the file
+    /// as they are. For example, [foxpro::Table](foxpro/struct.Table.html) return
+    /// `Error::Unsupported` if its structural index has a tag it cannot keep up to date.
+    fn insert_owned(&mut self, row: Self::Row) -> Result<()>;
 
     /// Perform aggregation operation on this table.
     /// This is just a syntax sugar for `table.iter().fold(initial_value, op)`.
@@ -603,11 +1002,15 @@ pub trait TableOps: IntoIterator<Item=<Self as TableOps>::Row> + Index<usize, Ou
         }
     }
 
-    /// Update table by evaluate each row in the table and feed each row as `&mut` to `op` function
-    fn update<F>(&mut self, mut op: F) where for<'r> F: FnMut(&'r mut Self::Row) {
+    /// Update table by evaluate each row in the table and feed each row as `&mut` to `op` function.
+    ///
+    /// Like [insert_owned](trait.TableOps.html#tymethod.insert_owned), table that is backed by
+    /// a file may refuse the update before `op` is called on any row.
+    fn update<F>(&mut self, mut op: F) -> Result<()> where for<'r> F: FnMut(&'r mut Self::Row) {
         for i in 0..self.len() {
             op(&mut self[i]);
         }
+        Ok(())
     }
 
     /// Make a query operation on table.
@@ -626,22 +1029,49 @@ pub trait TableOps: IntoIterator<Item=<Self as TableOps>::Row> + Index<usize, Ou
     /// Return the number of record in this table
     fn len(&self) -> usize;
 
-    /// Return an iterator over table which yield reference to each row.
+    /// Return true if this table has no record
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Return true if row at index `i` is marked as deleted.
+    /// The default implementation ask the row itself using
+    /// [RecordOps::is_deleted](trait.RecordOps.html#method.is_deleted).
+    fn is_deleted(&self, i: usize) -> bool {
+        self[i].is_deleted()
+    }
+
+    /// Return an iterator over table which yield reference to each row
+    /// that isn't marked as deleted.
     /// This is a default implementation where it use running cursor to
     /// index into each element in this table.
-    fn iter<'a>(&'a self) -> TableIter<'a, Self, Self::Row> {
+    fn iter(&self) -> TableIter<'_, Self, Self::Row> {
         TableIter {
             i: 0,
-            table: self
+            table: self,
+            include_deleted: false
+        }
+    }
+
+    /// Return an iterator over table which yield reference to every row
+    /// including the one that is marked as deleted.
+    fn iter_with_deleted(&self) -> TableIter<'_, Self, Self::Row> {
+        TableIter {
+            i: 0,
+            table: self,
+            include_deleted: true
         }
     }
 }
 
 /// A very straight forward implementation of generic Iterator for any table.
 /// It simply return a record by using indexing and move the cursor by 1.
+/// Rows that are marked as deleted are skipped unless the iterator is
+/// created by [iter_with_deleted](trait.TableOps.html#method.iter_with_deleted).
 pub struct TableIter<'a, T, ROW> where T: 'a + TableOps<Row=ROW>, ROW: 'a + RecordOps {
     i: usize,
-    table: &'a T
+    table: &'a T,
+    include_deleted: bool
 }
 
 impl<'a, T, ROW> Iterator for TableIter<'a, T, ROW> 
@@ -650,34 +1080,47 @@ impl<'a, T, ROW> Iterator for TableIter<'a, T, ROW>
     type Item=&'a ROW;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.i >= self.table.len() {
-            return None;
+        while self.i < self.table.len() {
+            let i = self.i;
+            self.i += 1;
+
+            if self.include_deleted || !self.table.is_deleted(i) {
+                return Some(&self.table[i]);
+            }
         }
 
-        let r = Some(&self.table[self.i]);
-        self.i = self.i + 1;
-        return r;
+        None
     }
 
     /// Since this iterator use indexing technique under the hood,
-    /// we don't need to call `next` for `n` times.
+    /// we don't need to call `next` for `n` times when deleted rows are included.
     /// This override such behavior and use direct indexing method.
     fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        if !self.include_deleted {
+            for _ in 0..n {
+                self.next()?;
+            }
+            return self.next();
+        }
+
         if self.i + n < self.table.len() {
             let new_i = self.i + n;
-            self.i = new_i;
+            self.i = new_i + 1;
             Some(&self.table[new_i])
         } else {
+            self.i = self.table.len();
             None
         }
     }
-}
 
-impl<'a, T, ROW> ExactSizeIterator for TableIter<'a, T, ROW> 
-    where T: 'a + TableOps<Row=ROW>, ROW: 'a + RecordOps 
-{
-    fn len(&self) -> usize {
-        self.table.len()
+    /// It is exact when deleted rows are included. Otherwise any of the rows left
+    /// may be deleted so only the upper bound is known.
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remain = self.table.len().saturating_sub(self.i);
+        match self.include_deleted {
+            true => (remain, Some(remain)),
+            false => (0, Some(remain))
+        }
     }
 }
 
@@ -738,9 +1181,9 @@ impl<'a, 'b, COND, T1, T2, ROW1, ROW2, ROW3> JoinTableIter<'a, 'b, COND, T1, T2,
 {
     fn new(table1: &'a T1, table2: &'b T2, cond_fn: COND) -> Self {
         JoinTableIter {
-            cond_fn: cond_fn,
+            cond_fn,
             t1_iter: table1.iter(),
-            table2: table2,
+            table2,
             t2_iter: table2.iter(),
             r1: None
         }
@@ -764,8 +1207,8 @@ impl<'a, 'b, COND, T1, T2, ROW1, ROW2, ROW3> Iterator for JoinTableIter<'a, 'b,
             self.r1 = self.t1_iter.next()
         }
 
-        while let Some(ref r1) = self.r1 {
-            while let Some(r2) = self.t2_iter.next() {
+        while let Some(r1) = self.r1 {
+            for r2 in self.t2_iter.by_ref() {
                 if let Some(r3) = (self.cond_fn)(r1, r2) {
                     return Some(r3);
                 }
@@ -783,7 +1226,7 @@ impl<'a, 'b, COND, T1, T2, ROW1, ROW2, ROW3> Iterator for JoinTableIter<'a, 'b,
     /// The only exception is the "cross join" type where number of row is equals to 
     /// `table1.len() ^ table2.len()`.
     fn size_hint(&self) -> (usize, Option<usize>) {
-        (0, Some(std::cmp::max(self.t1_iter.len(), self.table2.len())))
+        (0, Some(std::cmp::max(self.t1_iter.size_hint().1.unwrap_or(0), self.table2.len())))
     }
 }
 
@@ -844,7 +1287,7 @@ impl<'a, T> IntoIterator for &'a InMemoryTable<T> where T: RecordOps {
     type IntoIter=std::slice::Iter<'a, T>;
 
     fn into_iter(self) -> Self::IntoIter {
-        self.rows.as_slice().into_iter()
+        self.rows.iter()
     }
 }
 
@@ -853,14 +1296,14 @@ impl<'a, T> IntoIterator for &'a mut InMemoryTable<T> where T: RecordOps {
     type IntoIter=std::slice::IterMut<'a, T>;
 
     fn into_iter(self) -> Self::IntoIter {
-        self.rows.as_mut_slice().into_iter()
+        self.rows.iter_mut()
     }
 }
 
 impl<T> FromIterator<T> for InMemoryTable<T> where T: RecordOps {
     fn from_iter<I: IntoIterator<Item=T>>(iter: I) -> Self {
         InMemoryTable {
-            rows: iter.into_iter().map(|item| item).collect()
+            rows: iter.into_iter().collect()
         }
     }
 }
@@ -879,11 +1322,12 @@ impl<T> IndexMut<usize> for InMemoryTable<T> where T: RecordOps {
     }
 }
 
-impl<'a, T> TableOps for InMemoryTable<T> where T: 'a + RecordOps {
+impl<T> TableOps for InMemoryTable<T> where T: RecordOps {
     type Row=T;
 
-    fn insert_owned(&mut self, row: Self::Row) {
-        self.rows.push(row)
+    fn insert_owned(&mut self, row: Self::Row) -> Result<()> {
+        self.rows.push(row);
+        Ok(())
     }
 
     fn len(&self) -> usize {
@@ -891,7 +1335,7 @@ impl<'a, T> TableOps for InMemoryTable<T> where T: 'a + RecordOps {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct Header {
     pub db_type: DBFType,
     pub last_update: NaiveDate,
@@ -902,24 +1346,24 @@ pub struct Header {
     pub codepage: &'static str
 }
 
+impl Header {
+    /// Return true if the table has structural compound index (.cdx),
+    /// i.e. bit 0x01 of `table_flag` is set.
+    pub fn has_structural_cdx(&self) -> bool {
+        self.table_flag & 0x01 != 0
+    }
+}
+
 /// Read a first byte of dbf file and return an Enum that represent the
 /// DBF's type. If it doesn't recognize the first byte, it'll be 
 /// [DBFType::Undefined](enum.DBFType.html#variant.Undefined)
 /// 
 /// # Return
 /// [DBFType](enum.DBFType.html)
-pub fn read_dbf_type<P: std::convert::AsRef<std::path::Path> + std::fmt::Debug>(path: P) -> std::io::Result<DBFType> {
-    let mut file = match File::open(&path) {
-        Ok(f) => f,
-        Err(_) => panic!("Fail to read file from {:?}", path)
-    };
+pub fn read_dbf_type<P: std::convert::AsRef<std::path::Path>>(path: P) -> Result<DBFType> {
+    let mut file = File::open(&path)?;
     let flag = &mut [0];
-    match file.read_exact(flag) {
-        Err(err) => {
-            return std::io::Result::Err(err);
-        },
-        _ => {}
-    }
+    read_full(&mut file, flag)?;
 
     Ok(DBFType::parse_type(flag[0]))
 }
@@ -933,7 +1377,7 @@ pub fn read_dbf_type<P: std::convert::AsRef<std::path::Path> + std::fmt::Debug>(
 /// | Byte Offset | Description |
 /// | --- | --- |
 /// | 0 | DBF File type: <br/>0x02 FoxBASE<br/> 0x03 FoxBASE+/Dbase III plus, no memo<br/> 0x30   Visual FoxPro<br/> 0x31   Visual FoxPro, autoincrement enabled<br/> 0x32   Visual FoxPro with field type Varchar or Varbinary<br/>0x43   dBASE IV SQL table files, no memo<br/>0x63   dBASE IV SQL system files, no memo<br/>0x83   FoxBASE+/dBASE III PLUS, with memo<br/>0x8B   dBASE IV with memo<br/>0xCB   dBASE IV SQL table files, with memo<br/>0xF5   FoxPro 2.x (or earlier) with memo<br/>0xE5   HiPer-Six format with SMT memo file<br/>0xFB   FoxBASE |
-/// | 1 - 3 | Last update (YYMMDD) |
+/// | 1 - 3 | Last update (YYMMDD) where YY is number of years since 1900 |
 /// | 4 - 7 | Number of records in file |
 /// | 8 - 9 | Position of first data record |
 /// | 10 - 11 | Length of one data record, including delete flag |
@@ -945,22 +1389,42 @@ pub fn read_dbf_type<P: std::convert::AsRef<std::path::Path> + std::fmt::Debug>(
 /// | n + 1 | Header record terminator, must be 0x0D |
 /// | n + 2 to n + 264 | VFP only. A 263-byte range that contains the backlink, which is relative path of an associated database (.dbc) file, information. If the first byte is 0x00, the file is not associated with a database. Thus database files always have 0x00. |
 /// ---
-pub async fn read_header<P: std::convert::AsRef<std::path::Path> + Display>(p: P, cp_mapper: impl Fn(u8) -> Result<&'static str, &'static str>) -> std::io::Result<Header> {
+pub async fn read_header<P: std::convert::AsRef<std::path::Path> + Display>(p: P, cp_mapper: impl Fn(u8) -> Result<&'static str>) -> Result<Header> {
     let common = &mut [0; 32];
-    let mut f = File::open(p).unwrap();
-    f.read_exact(common)?;
+    let mut f = File::open(p)?;
+    read_full(&mut f, common)?;
+    parse_header(common, cp_mapper)
+}
+
+/// Read header from the start of given async source, along with the rest of header area
+/// up to the first record. The rest begins with field subrecords at byte 32.
+pub(crate) async fn read_header_area<R>(source: &mut R, cp_mapper: impl Fn(u8) -> Result<&'static str>) -> Result<(Header, Vec<u8>)>
+    where R: AsyncRead + AsyncSeek + Unpin
+{
+    let common = &mut [0; 32];
+    source.seek(std::io::SeekFrom::Start(0)).await?;
+    read_full_async(source, common).await?;
+    let header = parse_header(common, cp_mapper)?;
+    let mut rest = vec![0; header.first_record_position.saturating_sub(common.len())];
+    read_full_async(source, &mut rest).await?;
+    Ok((header, rest))
+}
+
+/// Parse first 32 bytes of DBF file. See [read_header](fn.read_header.html) for the layout.
+fn parse_header(common: &[u8; 32], cp_mapper: impl Fn(u8) -> Result<&'static str>) -> Result<Header> {
+    let last_update = match NaiveDate::from_ymd_opt(1900 + common[1] as i32, common[2] as u32, common[3] as u32) {
+        Some(d) => d,
+        None => return Err(Error::BadHeader(format!("Invalid last update date {:?}", &common[1..4])))
+    };
     
     Ok(Header {
         db_type: DBFType::parse_type(common[0]),
-        last_update: NaiveDate::from_ymd(common[1] as i32, common[2] as u32, common[3] as u32),
-        records_count: u32::from_le_bytes(common[4..8].try_into().unwrap()) as usize,
-        first_record_position: u16::from_le_bytes(common[8..10].try_into().unwrap()) as usize,
-        record_len: u16::from_le_bytes(common[10..=11].try_into().unwrap()) as usize,
+        last_update,
+        records_count: u32::from_le_bytes(take_bytes(&common[4..8])?) as usize,
+        first_record_position: u16::from_le_bytes(take_bytes(&common[8..10])?) as usize,
+        record_len: u16::from_le_bytes(take_bytes(&common[10..=11])?) as usize,
         table_flag: common[28],
-        codepage: match cp_mapper(common[29]) {
-            Ok(cp) => cp,
-            Err(msg) => panic!(msg)
-        }
+        codepage: cp_mapper(common[29])?
     })
 }
 // /// Load table into memory
@@ -1,4 +1,5 @@
 use super::*;
+use encoding_rs::CoderResult;
 
 #[test]
 fn test_convert_byte_to_struct() {
@@ -16,7 +17,7 @@ fn test_convert_byte_to_struct() {
     }
 
     impl RecordOps for Rec {
-        fn from_bytes(record: &[u8]) -> Rec {
+        fn from_bytes(record: &[u8]) -> Result<Rec> {
             let encoding = Encoding::for_label("tis-620".as_bytes()).unwrap();
             let mut decoder = encoding.new_decoder();
             let mut name = String::with_capacity(2);
@@ -32,19 +33,18 @@ fn test_convert_byte_to_struct() {
                 }
             }
             let cost = i64::from_le_bytes(record[2..10].try_into().unwrap());
-            let date = NaiveDate::from_num_days_from_ce(u64::from_le_bytes(record[10..18].try_into().unwrap()) as i32);
-            Rec {
-                date: date,
-                name: name,
+            let date = NaiveDate::from_num_days_from_ce_opt(u64::from_le_bytes(record[10..18].try_into().unwrap()) as i32).unwrap();
+            Ok(Rec {
+                date,
+                name,
                 cost: (cost as f64) / 10000f64
-            }
+            })
         }
 
-        fn to_bytes(&self) -> Vec<u8> {
+        fn to_bytes(&self) -> Result<Vec<u8>> {
             let encoding = Encoding::for_label("tis-620".as_bytes()).unwrap();
             let mut encoder = encoding.new_encoder();
-            let mut result = Vec::with_capacity(18);
-            unsafe {result.set_len(18)}
+            let mut result = vec![0; 18];
             let (enc_res, _read_size, write_size, _last) = encoder.encode_from_utf8(self.name.as_str(), &mut result, true);
             if write_size != 2 {
                 match enc_res {
@@ -58,21 +58,21 @@ fn test_convert_byte_to_struct() {
             }
             let cost = ((self.cost * 10000f64) as i64).to_le_bytes();
             result[2..10].iter_mut().zip(cost.iter()).for_each(|(r, c)| {*r = *c});
-            let days = (self.date - NaiveDate::from_num_days_from_ce(0)).num_days().to_le_bytes();
+            let days = (self.date - NaiveDate::from_num_days_from_ce_opt(0).unwrap()).num_days().to_le_bytes();
             result[10..].iter_mut().zip(days.iter()).for_each(|(r, c)| {*r = *c});
 
-            result
+            Ok(result)
         }
     }
 
     // Convert from bytes into struct
-    let rec = Rec::from_bytes(record);
+    let rec = Rec::from_bytes(record).unwrap();
 
     // Expected Record data comparison
-    assert_eq!(rec, Rec{ date: NaiveDate::from_ymd(2020, 2, 29), name: "ab".to_string(), cost: 0.0001});
+    assert_eq!(rec, Rec{ date: NaiveDate::from_ymd_opt(2020, 2, 29).unwrap(), name: "ab".to_string(), cost: 0.0001});
 
     // Convert from struct back into bytes then compare to source
-    assert_eq!(record, rec.to_bytes().as_slice());
+    assert_eq!(record, rec.to_bytes().unwrap().as_slice());
 }
 
 #[test]
@@ -111,7 +111,7 @@ fn test_in_memory_table() {
     }
 
     impl RecordOps for Rec {
-        fn from_bytes(record: &[u8]) -> Rec {
+        fn from_bytes(record: &[u8]) -> Result<Rec> {
             let encoding = Encoding::for_label("tis-620".as_bytes()).unwrap();
             let mut decoder = encoding.new_decoder();
             let mut name = String::with_capacity(2);
@@ -127,19 +127,18 @@ fn test_in_memory_table() {
                 }
             }
             let cost = i64::from_le_bytes(record[2..10].try_into().unwrap());
-            let date = NaiveDate::from_num_days_from_ce(u64::from_le_bytes(record[10..18].try_into().unwrap()) as i32);
-            Rec {
-                date: date,
-                name: name,
+            let date = NaiveDate::from_num_days_from_ce_opt(u64::from_le_bytes(record[10..18].try_into().unwrap()) as i32).unwrap();
+            Ok(Rec {
+                date,
+                name,
                 cost: (cost as f64) / 10000f64
-            }
+            })
         }
 
-        fn to_bytes(&self) -> Vec<u8> {
+        fn to_bytes(&self) -> Result<Vec<u8>> {
             let encoding = Encoding::for_label("tis-620".as_bytes()).unwrap();
             let mut encoder = encoding.new_encoder();
-            let mut result = Vec::with_capacity(18);
-            unsafe {result.set_len(18)}
+            let mut result = vec![0; 18];
             let (enc_res, _read_size, write_size, _last) = encoder.encode_from_utf8(self.name.as_str(), &mut result, true);
             if write_size != 2 {
                 match enc_res {
@@ -153,15 +152,15 @@ fn test_in_memory_table() {
             }
             let cost = ((self.cost * 10000f64) as i64).to_le_bytes();
             result[2..10].iter_mut().zip(cost.iter()).for_each(|(r, c)| {*r = *c});
-            let days = (self.date - NaiveDate::from_num_days_from_ce(0)).num_days().to_le_bytes();
+            let days = (self.date - NaiveDate::from_num_days_from_ce_opt(0).unwrap()).num_days().to_le_bytes();
             result[10..].iter_mut().zip(days.iter()).for_each(|(r, c)| {*r = *c});
 
-            result
+            Ok(result)
         }
     }
     
     let tb1 = InMemoryTable {
-        rows: records1.into_iter().map(|r| Rec::from_bytes(*r)).collect()
+        rows: records1.iter().map(|r| Rec::from_bytes(*r).unwrap()).collect()
     };
 
     assert_eq!(
@@ -169,12 +168,12 @@ fn test_in_memory_table() {
         InMemoryTable {
             rows: vec![
                 Rec {
-                    date: NaiveDate::from_ymd(2020, 2, 29),
+                    date: NaiveDate::from_ymd_opt(2020, 2, 29).unwrap(),
                     name: "ab".to_string(),
                     cost: 0.0001
                 },
                 Rec {
-                    date: NaiveDate::from_ymd(2020, 3, 01),
+                    date: NaiveDate::from_ymd_opt(2020, 3, 1).unwrap(),
                     name: "cd".to_string(),
                     cost: 0.0002
                 }
@@ -219,7 +218,7 @@ fn test_join_table_fn() {
     }
 
     impl RecordOps for Rec {
-        fn from_bytes(record: &[u8]) -> Rec {
+        fn from_bytes(record: &[u8]) -> Result<Rec> {
             let encoding = Encoding::for_label("tis-620".as_bytes()).unwrap();
             let mut decoder = encoding.new_decoder();
             let mut name = String::with_capacity(2);
@@ -235,19 +234,18 @@ fn test_join_table_fn() {
                 }
             }
             let cost = i64::from_le_bytes(record[2..10].try_into().unwrap());
-            let date = NaiveDate::from_num_days_from_ce(u64::from_le_bytes(record[10..18].try_into().unwrap()) as i32);
-            Rec {
-                date: date,
-                name: name,
+            let date = NaiveDate::from_num_days_from_ce_opt(u64::from_le_bytes(record[10..18].try_into().unwrap()) as i32).unwrap();
+            Ok(Rec {
+                date,
+                name,
                 cost: (cost as f64) / 10000f64
-            }
+            })
         }
 
-        fn to_bytes(&self) -> Vec<u8> {
+        fn to_bytes(&self) -> Result<Vec<u8>> {
             let encoding = Encoding::for_label("tis-620".as_bytes()).unwrap();
             let mut encoder = encoding.new_encoder();
-            let mut result = Vec::with_capacity(18);
-            unsafe {result.set_len(18)}
+            let mut result = vec![0; 18];
             let (enc_res, _read_size, write_size, _last) = encoder.encode_from_utf8(self.name.as_str(), &mut result, true);
             if write_size != 2 {
                 match enc_res {
@@ -261,10 +259,10 @@ fn test_join_table_fn() {
             }
             let cost = ((self.cost * 10000f64) as i64).to_le_bytes();
             result[2..10].iter_mut().zip(cost.iter()).for_each(|(r, c)| {*r = *c});
-            let days = (self.date - NaiveDate::from_num_days_from_ce(0)).num_days().to_le_bytes();
+            let days = (self.date - NaiveDate::from_num_days_from_ce_opt(0).unwrap()).num_days().to_le_bytes();
             result[10..].iter_mut().zip(days.iter()).for_each(|(r, c)| {*r = *c});
 
-            result
+            Ok(result)
         }
     }
 
@@ -275,21 +273,21 @@ fn test_join_table_fn() {
     }
 
     impl RecordOps for JoinedRec {
-        fn from_bytes(_record: &[u8]) -> Self {
+        fn from_bytes(_record: &[u8]) -> Result<Self> {
             unimplemented!("Doesn't support")
         }
 
-        fn to_bytes(&self) -> Vec<u8> {
-            self.name1.as_bytes().into_iter().chain(self.name2.as_bytes().into_iter()).map(|b| *b).collect()
+        fn to_bytes(&self) -> Result<Vec<u8>> {
+            Ok(self.name1.as_bytes().iter().chain(self.name2.as_bytes()).copied().collect())
         }
     }
     
     let tb1 = InMemoryTable {
-        rows: records1.into_iter().map(|r| Rec::from_bytes(*r)).collect()
+        rows: records1.iter().map(|r| Rec::from_bytes(*r).unwrap()).collect()
     };
     
     let tb2 = InMemoryTable {
-        rows: records2.into_iter().map(|r| Rec::from_bytes(*r)).collect()
+        rows: records2.iter().map(|r| Rec::from_bytes(*r).unwrap()).collect()
     };
 
     let tb3: InMemoryTable<JoinedRec> = join(&tb1, &tb2, |r1, r2| {
@@ -356,7 +354,7 @@ fn test_join_table_trait() {
     }
 
     impl RecordOps for Rec {
-        fn from_bytes(record: &[u8]) -> Rec {
+        fn from_bytes(record: &[u8]) -> Result<Rec> {
             let encoding = Encoding::for_label("tis-620".as_bytes()).unwrap();
             let mut decoder = encoding.new_decoder();
             let mut name = String::with_capacity(2);
@@ -372,19 +370,18 @@ fn test_join_table_trait() {
                 }
             }
             let cost = i64::from_le_bytes(record[2..10].try_into().unwrap());
-            let date = NaiveDate::from_num_days_from_ce(u64::from_le_bytes(record[10..18].try_into().unwrap()) as i32);
-            Rec {
-                date: date,
-                name: name,
+            let date = NaiveDate::from_num_days_from_ce_opt(u64::from_le_bytes(record[10..18].try_into().unwrap()) as i32).unwrap();
+            Ok(Rec {
+                date,
+                name,
                 cost: (cost as f64) / 10000f64
-            }
+            })
         }
 
-        fn to_bytes(&self) -> Vec<u8> {
+        fn to_bytes(&self) -> Result<Vec<u8>> {
             let encoding = Encoding::for_label("tis-620".as_bytes()).unwrap();
             let mut encoder = encoding.new_encoder();
-            let mut result = Vec::with_capacity(18);
-            unsafe {result.set_len(18)}
+            let mut result = vec![0; 18];
             let (enc_res, _read_size, write_size, _last) = encoder.encode_from_utf8(self.name.as_str(), &mut result, true);
             if write_size != 2 {
                 match enc_res {
@@ -398,10 +395,10 @@ fn test_join_table_trait() {
             }
             let cost = ((self.cost * 10000f64) as i64).to_le_bytes();
             result[2..10].iter_mut().zip(cost.iter()).for_each(|(r, c)| {*r = *c});
-            let days = (self.date - NaiveDate::from_num_days_from_ce(0)).num_days().to_le_bytes();
+            let days = (self.date - NaiveDate::from_num_days_from_ce_opt(0).unwrap()).num_days().to_le_bytes();
             result[10..].iter_mut().zip(days.iter()).for_each(|(r, c)| {*r = *c});
 
-            result
+            Ok(result)
         }
     }
 
@@ -412,21 +409,21 @@ fn test_join_table_trait() {
     }
 
     impl RecordOps for JoinedRec {
-        fn from_bytes(_record: &[u8]) -> Self {
+        fn from_bytes(_record: &[u8]) -> Result<Self> {
             unimplemented!("Doesn't support")
         }
 
-        fn to_bytes(&self) -> Vec<u8> {
-            self.name1.as_bytes().into_iter().chain(self.name2.as_bytes().into_iter()).map(|b| *b).collect()
+        fn to_bytes(&self) -> Result<Vec<u8>> {
+            Ok(self.name1.as_bytes().iter().chain(self.name2.as_bytes()).copied().collect())
         }
     }
     
     let tb1 = InMemoryTable {
-        rows: records1.into_iter().map(|r| Rec::from_bytes(*r)).collect()
+        rows: records1.iter().map(|r| Rec::from_bytes(*r).unwrap()).collect()
     };
     
     let tb2 = InMemoryTable {
-        rows: records2.into_iter().map(|r| Rec::from_bytes(*r)).collect()
+        rows: records2.iter().map(|r| Rec::from_bytes(*r).unwrap()).collect()
     };
 
     let tb3: InMemoryTable<JoinedRec> = tb1.join(&tb2).on(|r1, r2| {
@@ -490,7 +487,7 @@ fn test_select() {
     }
 
     impl RecordOps for Rec {
-        fn from_bytes(record: &[u8]) -> Rec {
+        fn from_bytes(record: &[u8]) -> Result<Rec> {
             let encoding = Encoding::for_label("tis-620".as_bytes()).unwrap();
             let mut decoder = encoding.new_decoder();
             let mut name = String::with_capacity(2);
@@ -506,19 +503,18 @@ fn test_select() {
                 }
             }
             let cost = i64::from_le_bytes(record[2..10].try_into().unwrap());
-            let date = NaiveDate::from_num_days_from_ce(u64::from_le_bytes(record[10..18].try_into().unwrap()) as i32);
-            Rec {
-                date: date,
-                name: name,
+            let date = NaiveDate::from_num_days_from_ce_opt(u64::from_le_bytes(record[10..18].try_into().unwrap()) as i32).unwrap();
+            Ok(Rec {
+                date,
+                name,
                 cost: (cost as f64) / 10000f64
-            }
+            })
         }
 
-        fn to_bytes(&self) -> Vec<u8> {
+        fn to_bytes(&self) -> Result<Vec<u8>> {
             let encoding = Encoding::for_label("tis-620".as_bytes()).unwrap();
             let mut encoder = encoding.new_encoder();
-            let mut result = Vec::with_capacity(18);
-            unsafe {result.set_len(18)}
+            let mut result = vec![0; 18];
             let (enc_res, _read_size, write_size, _last) = encoder.encode_from_utf8(self.name.as_str(), &mut result, true);
             if write_size != 2 {
                 match enc_res {
@@ -532,15 +528,15 @@ fn test_select() {
             }
             let cost = ((self.cost * 10000f64) as i64).to_le_bytes();
             result[2..10].iter_mut().zip(cost.iter()).for_each(|(r, c)| {*r = *c});
-            let days = (self.date - NaiveDate::from_num_days_from_ce(0)).num_days().to_le_bytes();
+            let days = (self.date - NaiveDate::from_num_days_from_ce_opt(0).unwrap()).num_days().to_le_bytes();
             result[10..].iter_mut().zip(days.iter()).for_each(|(r, c)| {*r = *c});
 
-            result
+            Ok(result)
         }
     }
     
     let tb1 = InMemoryTable {
-        rows: records1.into_iter().map(|r| Rec::from_bytes(*r)).collect()
+        rows: records1.iter().map(|r| Rec::from_bytes(*r).unwrap()).collect()
     };
 
     let result: InMemoryTable<Rec> = tb1.select(|r| {
@@ -556,12 +552,12 @@ fn test_select() {
         InMemoryTable {
             rows: vec![
                 Rec {
-                    date: NaiveDate::from_ymd(2020, 2, 29),
+                    date: NaiveDate::from_ymd_opt(2020, 2, 29).unwrap(),
                     name: "ab".to_string(),
                     cost: 0.0001
                 },
                 Rec {
-                    date: NaiveDate::from_ymd(2020, 2, 29),
+                    date: NaiveDate::from_ymd_opt(2020, 2, 29).unwrap(),
                     name: "gg".to_string(),
                     cost: 0.0001
                 }
@@ -593,7 +589,7 @@ fn test_in_memory_update() {
     }
 
     impl RecordOps for Rec {
-        fn from_bytes(record: &[u8]) -> Rec {
+        fn from_bytes(record: &[u8]) -> Result<Rec> {
             let encoding = Encoding::for_label("tis-620".as_bytes()).unwrap();
             let mut decoder = encoding.new_decoder();
             let mut name = String::with_capacity(2);
@@ -609,19 +605,18 @@ fn test_in_memory_update() {
                 }
             }
             let cost = i64::from_le_bytes(record[2..10].try_into().unwrap());
-            let date = NaiveDate::from_num_days_from_ce(u64::from_le_bytes(record[10..18].try_into().unwrap()) as i32);
-            Rec {
-                date: date,
-                name: name,
+            let date = NaiveDate::from_num_days_from_ce_opt(u64::from_le_bytes(record[10..18].try_into().unwrap()) as i32).unwrap();
+            Ok(Rec {
+                date,
+                name,
                 cost: (cost as f64) / 10000f64
-            }
+            })
         }
 
-        fn to_bytes(&self) -> Vec<u8> {
+        fn to_bytes(&self) -> Result<Vec<u8>> {
             let encoding = Encoding::for_label("tis-620".as_bytes()).unwrap();
             let mut encoder = encoding.new_encoder();
-            let mut result = Vec::with_capacity(18);
-            unsafe {result.set_len(18)}
+            let mut result = vec![0; 18];
             let (enc_res, _read_size, write_size, _last) = encoder.encode_from_utf8(self.name.as_str(), &mut result, true);
             if write_size != 2 {
                 match enc_res {
@@ -635,34 +630,34 @@ fn test_in_memory_update() {
             }
             let cost = ((self.cost * 10000f64) as i64).to_le_bytes();
             result[2..10].iter_mut().zip(cost.iter()).for_each(|(r, c)| {*r = *c});
-            let days = (self.date - NaiveDate::from_num_days_from_ce(0)).num_days().to_le_bytes();
+            let days = (self.date - NaiveDate::from_num_days_from_ce_opt(0).unwrap()).num_days().to_le_bytes();
             result[10..].iter_mut().zip(days.iter()).for_each(|(r, c)| {*r = *c});
 
-            result
+            Ok(result)
         }
     }
     
     let mut tb1 = InMemoryTable {
-        rows: records1.into_iter().map(|r| Rec::from_bytes(*r)).collect()
+        rows: records1.iter().map(|r| Rec::from_bytes(*r).unwrap()).collect()
     };
 
-    tb1.update(|mut r| {
+    tb1.update(|r| {
         if r.cost < 0.0002 {
             r.cost = 0.0002;
         }
-    });
+    }).unwrap();
 
     assert_eq!(
         tb1, 
         InMemoryTable {
             rows: vec![
                 Rec {
-                    date: NaiveDate::from_ymd(2020, 2, 29),
+                    date: NaiveDate::from_ymd_opt(2020, 2, 29).unwrap(),
                     name: "ab".to_string(),
                     cost: 0.0002
                 },
                 Rec {
-                    date: NaiveDate::from_ymd(2020, 3, 01),
+                    date: NaiveDate::from_ymd_opt(2020, 3, 1).unwrap(),
                     name: "cd".to_string(),
                     cost: 0.0002
                 }
@@ -681,7 +676,7 @@ fn test_insert_rows() {
     }
 
     impl RecordOps for Rec {
-        fn from_bytes(record: &[u8]) -> Rec {
+        fn from_bytes(record: &[u8]) -> Result<Rec> {
             let encoding = Encoding::for_label("tis-620".as_bytes()).unwrap();
             let mut decoder = encoding.new_decoder();
             let mut name = String::with_capacity(2);
@@ -697,19 +692,18 @@ fn test_insert_rows() {
                 }
             }
             let cost = i64::from_le_bytes(record[2..10].try_into().unwrap());
-            let date = NaiveDate::from_num_days_from_ce(u64::from_le_bytes(record[10..18].try_into().unwrap()) as i32);
-            Rec {
-                date: date,
-                name: name,
+            let date = NaiveDate::from_num_days_from_ce_opt(u64::from_le_bytes(record[10..18].try_into().unwrap()) as i32).unwrap();
+            Ok(Rec {
+                date,
+                name,
                 cost: (cost as f64) / 10000f64
-            }
+            })
         }
 
-        fn to_bytes(&self) -> Vec<u8> {
+        fn to_bytes(&self) -> Result<Vec<u8>> {
             let encoding = Encoding::for_label("tis-620".as_bytes()).unwrap();
             let mut encoder = encoding.new_encoder();
-            let mut result = Vec::with_capacity(18);
-            unsafe {result.set_len(18)}
+            let mut result = vec![0; 18];
             let (enc_res, _read_size, write_size, _last) = encoder.encode_from_utf8(self.name.as_str(), &mut result, true);
             if write_size != 2 {
                 match enc_res {
@@ -723,10 +717,10 @@ fn test_insert_rows() {
             }
             let cost = ((self.cost * 10000f64) as i64).to_le_bytes();
             result[2..10].iter_mut().zip(cost.iter()).for_each(|(r, c)| {*r = *c});
-            let days = (self.date - NaiveDate::from_num_days_from_ce(0)).num_days().to_le_bytes();
+            let days = (self.date - NaiveDate::from_num_days_from_ce_opt(0).unwrap()).num_days().to_le_bytes();
             result[10..].iter_mut().zip(days.iter()).for_each(|(r, c)| {*r = *c});
 
-            result
+            Ok(result)
         }
     }
 
@@ -737,40 +731,40 @@ fn test_insert_rows() {
     tb.insert_owned(Rec {
         name: "ab".to_string(),
         cost: 1.0001,
-        date: NaiveDate::from_ymd(2020, 10, 20)
-    });
+        date: NaiveDate::from_ymd_opt(2020, 10, 20).unwrap()
+    }).unwrap();
 
     let recs = &[
         Rec {
             name: "cd".to_string(),
             cost: 2.0002,
-            date: NaiveDate::from_ymd(2019, 11, 21)
+            date: NaiveDate::from_ymd_opt(2019, 11, 21).unwrap()
         }, 
         Rec {
             name: "ef".to_string(),
             cost: 3.0003,
-            date: NaiveDate::from_ymd(2021, 03, 31)
+            date: NaiveDate::from_ymd_opt(2021, 3, 31).unwrap()
         }
     ];
 
-    tb.insert(recs);
+    tb.insert(recs).unwrap();
 
     assert_eq!(tb, InMemoryTable {
         rows: vec![
             Rec {
                 name: "ab".to_string(),
                 cost: 1.0001,
-                date: NaiveDate::from_ymd(2020, 10, 20)
+                date: NaiveDate::from_ymd_opt(2020, 10, 20).unwrap()
             },
             Rec {
                 name: "cd".to_string(),
                 cost: 2.0002,
-                date: NaiveDate::from_ymd(2019, 11, 21)
+                date: NaiveDate::from_ymd_opt(2019, 11, 21).unwrap()
             }, 
             Rec {
                 name: "ef".to_string(),
                 cost: 3.0003,
-                date: NaiveDate::from_ymd(2021, 03, 31)
+                date: NaiveDate::from_ymd_opt(2021, 3, 31).unwrap()
             }
         ]
     })
@@ -799,7 +793,7 @@ fn test_agg() {
     }
 
     impl RecordOps for Rec {
-        fn from_bytes(record: &[u8]) -> Rec {
+        fn from_bytes(record: &[u8]) -> Result<Rec> {
             let encoding = Encoding::for_label("tis-620".as_bytes()).unwrap();
             let mut decoder = encoding.new_decoder();
             let mut name = String::with_capacity(2);
@@ -815,19 +809,18 @@ fn test_agg() {
                 }
             }
             let cost = i64::from_le_bytes(record[2..10].try_into().unwrap());
-            let date = NaiveDate::from_num_days_from_ce(u64::from_le_bytes(record[10..18].try_into().unwrap()) as i32);
-            Rec {
-                date: date,
-                name: name,
+            let date = NaiveDate::from_num_days_from_ce_opt(u64::from_le_bytes(record[10..18].try_into().unwrap()) as i32).unwrap();
+            Ok(Rec {
+                date,
+                name,
                 cost: (cost as f64) / 10000f64
-            }
+            })
         }
 
-        fn to_bytes(&self) -> Vec<u8> {
+        fn to_bytes(&self) -> Result<Vec<u8>> {
             let encoding = Encoding::for_label("tis-620".as_bytes()).unwrap();
             let mut encoder = encoding.new_encoder();
-            let mut result = Vec::with_capacity(18);
-            unsafe {result.set_len(18)}
+            let mut result = vec![0; 18];
             let (enc_res, _read_size, write_size, _last) = encoder.encode_from_utf8(self.name.as_str(), &mut result, true);
             if write_size != 2 {
                 match enc_res {
@@ -841,15 +834,15 @@ fn test_agg() {
             }
             let cost = ((self.cost * 10000f64) as i64).to_le_bytes();
             result[2..10].iter_mut().zip(cost.iter()).for_each(|(r, c)| {*r = *c});
-            let days = (self.date - NaiveDate::from_num_days_from_ce(0)).num_days().to_le_bytes();
+            let days = (self.date - NaiveDate::from_num_days_from_ce_opt(0).unwrap()).num_days().to_le_bytes();
             result[10..].iter_mut().zip(days.iter()).for_each(|(r, c)| {*r = *c});
 
-            result
+            Ok(result)
         }
     }
 
     let tb = InMemoryTable {
-        rows: records1.into_iter().map(|b| Rec::from_bytes(*b)).collect()
+        rows: records1.iter().map(|b| Rec::from_bytes(*b).unwrap()).collect()
     };
 
     let result = tb.aggregate(100f64, |v, r| v + r.cost);